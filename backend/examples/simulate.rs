@@ -0,0 +1,79 @@
+//! Runs a policy simulation without the HTTP server
+//!
+//! Requires `AZURE_API_KEY` in the environment (or a `.env` file) and should be
+//! run from the `backend` directory so the neighborhood GeoJSON can be found:
+//!
+//! ```bash
+//! cargo run --example simulate -- "Build a new light rail line connecting Midtown to the airport"
+//! ```
+//!
+//! Streamed `summary` pieces are skipped; the summary is printed in full from the
+//! `complete` chunk.
+
+use backend::breaker::CircuitBreaker;
+use backend::telemetry::ParseTelemetry;
+use backend::types::{SimulationChunk, SimulationRequest};
+use backend::{NeighborhoodDatabase, Phase1Cache, SimulationConfig};
+use futures_util::StreamExt;
+use std::sync::Arc;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv::dotenv().ok();
+
+    let prompt = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "Add protected bike lanes along Peachtree Street".to_string());
+
+    let request = SimulationRequest {
+        prompt,
+        selected_zones: vec!["Downtown".to_string(), "Midtown".to_string()],
+        ..Default::default()
+    };
+
+    let db = Arc::new(NeighborhoodDatabase::default());
+    let config = Arc::new(SimulationConfig::from_env());
+    let phase1_cache = Arc::new(Phase1Cache::from_config(&config));
+    let breaker = Arc::new(CircuitBreaker::from_config(&config));
+    let stream = backend::generate_simulation(
+        request,
+        db,
+        config,
+        phase1_cache,
+        Arc::new(ParseTelemetry::new()),
+        breaker,
+    )
+    .await?;
+    futures_util::pin_mut!(stream);
+
+    while let Some(frame) = stream.next().await {
+        let frame = frame?;
+        let text = String::from_utf8_lossy(&frame);
+        let Some(data) = text.trim().strip_prefix("data: ") else {
+            continue;
+        };
+
+        match serde_json::from_str::<SimulationChunk>(data)? {
+            SimulationChunk::Event { data } => {
+                println!("[{}] {} — {}", data.zone_name, data.title, data.description)
+            }
+            SimulationChunk::Update { data } => println!("Expecting ~{} events", data.total),
+            SimulationChunk::Targets { data } => {
+                println!("Targets: {}", data.neighborhoods.join(", "))
+            }
+            SimulationChunk::Baseline { data } => {
+                println!("Baseline for {} neighborhoods", data.neighborhoods.len())
+            }
+            SimulationChunk::FinalState { data } => println!(
+                "Final state of {}: livability {:.1}",
+                data.zone_id, data.properties.livability_index
+            ),
+            SimulationChunk::Stable { data } => println!("No change in {}", data.zone_id),
+            SimulationChunk::Summary { .. } => {}
+            SimulationChunk::Warning { data } => println!("Warning: {}", data.message),
+            SimulationChunk::Complete { data } => println!("\nSummary: {}", data.summary),
+        }
+    }
+
+    Ok(())
+}
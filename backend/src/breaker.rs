@@ -0,0 +1,158 @@
+//! Azure Circuit Breaker
+//!
+//! When Azure is down, every simulation would otherwise wait on both phases before
+//! failing, piling up slow requests. After repeated upstream failures this module
+//! opens the circuit and fails new simulations fast with a 503 until a cooldown
+//! passes, then lets a single trial request through to test recovery.
+
+use crate::config::SimulationConfig;
+use crate::error::SimulationError;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Whether requests are currently allowed through to Azure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Requests flow normally while failures are counted
+    Closed,
+    /// Requests are rejected until the cooldown ends
+    Open,
+    /// One trial request is in flight; its outcome closes or re-opens the circuit
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum Circuit {
+    Closed { failures: Vec<Instant> },
+    Open { since: Instant },
+    HalfOpen { since: Instant },
+}
+
+/// Tracks consecutive Azure failures shared across all simulations
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: usize,
+    window: Duration,
+    cooldown: Duration,
+    circuit: Mutex<Circuit>,
+}
+
+impl CircuitBreaker {
+    /// Creates a breaker that opens after `failure_threshold` failures within
+    /// `window` and stays open for `cooldown` (a threshold of 0 disables it)
+    pub fn new(failure_threshold: usize, window: Duration, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            window,
+            cooldown,
+            circuit: Mutex::new(Circuit::Closed {
+                failures: Vec::new(),
+            }),
+        }
+    }
+
+    /// Creates a breaker that never opens
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::ZERO, Duration::ZERO)
+    }
+
+    pub fn from_config(config: &SimulationConfig) -> Self {
+        Self::new(
+            config.breaker_failure_threshold,
+            Duration::from_secs(config.breaker_window_secs),
+            Duration::from_secs(config.breaker_cooldown_secs),
+        )
+    }
+
+    /// Returns the current state
+    ///
+    /// An open circuit stays open after its cooldown until [`check`](Self::check)
+    /// admits the trial request.
+    pub fn state(&self) -> BreakerState {
+        match *self.lock() {
+            Circuit::Closed { .. } => BreakerState::Closed,
+            Circuit::Open { .. } => BreakerState::Open,
+            Circuit::HalfOpen { .. } => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Admits a new simulation unless the circuit is open
+    ///
+    /// Once the cooldown has passed, one trial simulation is admitted and the circuit
+    /// goes half-open; others are rejected until that trial succeeds, or until another
+    /// cooldown passes without an outcome.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SimulationError::AzureUnavailable`] while the circuit is open or a
+    /// trial is in flight
+    pub fn check(&self) -> Result<(), SimulationError> {
+        if self.failure_threshold == 0 {
+            return Ok(());
+        }
+
+        let mut circuit = self.lock();
+        let since = match *circuit {
+            Circuit::Closed { .. } => return Ok(()),
+            Circuit::Open { since } | Circuit::HalfOpen { since } => since,
+        };
+
+        let elapsed = since.elapsed();
+        if elapsed < self.cooldown {
+            return Err(SimulationError::AzureUnavailable {
+                retry_after_secs: (self.cooldown - elapsed).as_secs().max(1),
+            });
+        }
+
+        logln!("   ⚠️  Azure circuit half-open; letting one trial simulation through");
+        *circuit = Circuit::HalfOpen {
+            since: Instant::now(),
+        };
+        Ok(())
+    }
+
+    /// Records a successful Azure call, closing the circuit
+    pub fn record_success(&self) {
+        let mut circuit = self.lock();
+        if !matches!(*circuit, Circuit::Closed { .. }) {
+            logln!("   ✓ Azure recovered; circuit closed");
+        }
+        *circuit = Circuit::Closed {
+            failures: Vec::new(),
+        };
+    }
+
+    /// Records a failed Azure call, opening the circuit if the threshold is reached
+    /// or the half-open trial failed
+    pub fn record_failure(&self) {
+        if self.failure_threshold == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut circuit = self.lock();
+        let open = match &mut *circuit {
+            Circuit::Closed { failures } => {
+                failures.retain(|failure| now.duration_since(*failure) < self.window);
+                failures.push(now);
+                failures.len() >= self.failure_threshold
+            }
+            Circuit::HalfOpen { .. } => true,
+            Circuit::Open { .. } => false,
+        };
+
+        if open {
+            logln!(
+                "   ✗ Azure circuit opened; rejecting simulations for {}s",
+                self.cooldown.as_secs()
+            );
+            *circuit = Circuit::Open { since: now };
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Circuit> {
+        self.circuit
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,110 @@
+//! Phase 1 Result Cache
+//!
+//! Repeated prompts with the same zone selection (common during demos and
+//! iteration) would otherwise re-run the Phase 1 LLM call every time. This module
+//! keeps recently resolved target neighborhood lists in a bounded LRU cache with a
+//! time-to-live, shared across request handlers.
+
+use crate::config::SimulationConfig;
+use crate::types::PromptProfile;
+use lru::LruCache;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A cached Phase 1 result and when it was stored
+struct CachedTargets {
+    targets: Vec<String>,
+    stored_at: Instant,
+}
+
+/// Concurrency-safe LRU cache of Phase 1 target neighborhoods
+///
+/// Entries are keyed by [`Phase1Cache::key`] and expire after the configured TTL.
+/// A disabled cache never stores anything.
+pub struct Phase1Cache {
+    entries: Option<Mutex<LruCache<u64, CachedTargets>>>,
+    ttl: Duration,
+}
+
+impl Phase1Cache {
+    /// Creates a cache holding up to `capacity` results for `ttl` each
+    ///
+    /// A zero capacity disables the cache.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: NonZeroUsize::new(capacity).map(|cap| Mutex::new(LruCache::new(cap))),
+            ttl,
+        }
+    }
+
+    /// Creates a cache that never stores results
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// Creates a cache from the `phase1_cache_*` settings
+    pub fn from_config(config: &SimulationConfig) -> Self {
+        if config.phase1_cache_enabled {
+            Self::new(
+                config.phase1_cache_capacity,
+                Duration::from_secs(config.phase1_cache_ttl_secs),
+            )
+        } else {
+            Self::disabled()
+        }
+    }
+
+    /// Computes the cache key for a Phase 1 call
+    ///
+    /// Selected zones are sorted first so their order does not affect the key. The
+    /// rendered minimal context is hashed whole, so a changed description, current
+    /// event, neighbor, or scale hint misses even when the names are the same. The
+    /// prompt profile is part of the key because it changes the Phase 1 system prompt.
+    pub fn key(
+        prompt: &str,
+        selected_zones: &[String],
+        minimal_context: &str,
+        profile: PromptProfile,
+    ) -> u64 {
+        let mut zones = selected_zones.to_vec();
+        zones.sort();
+
+        let mut hasher = DefaultHasher::new();
+        prompt.trim().hash(&mut hasher);
+        zones.hash(&mut hasher);
+        minimal_context.hash(&mut hasher);
+        profile.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns the cached targets for `key` if present and not expired
+    pub fn get(&self, key: u64) -> Option<Vec<String>> {
+        let mut entries = self.entries.as_ref()?.lock().ok()?;
+        match entries.get(&key) {
+            Some(entry) if entry.stored_at.elapsed() <= self.ttl => Some(entry.targets.clone()),
+            Some(_) => {
+                entries.pop(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Stores the targets resolved for `key`, evicting the least recently used entry if full
+    pub fn insert(&self, key: u64, targets: Vec<String>) {
+        if let Some(entries) = &self.entries
+            && let Ok(mut entries) = entries.lock()
+        {
+            entries.put(
+                key,
+                CachedTargets {
+                    targets,
+                    stored_at: Instant::now(),
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,449 @@
+//! Runtime Configuration
+//!
+//! This module holds operator-tunable settings for the simulation. Values are read
+//! from environment variables at startup, falling back to defaults that match the
+//! original hardcoded behavior.
+
+use crate::types::ContextualFields;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Azure AI chat completions endpoint used when `AZURE_CHAT_URL` is unset
+pub const DEFAULT_AZURE_CHAT_URL: &str =
+    "https://aiatlai.services.ai.azure.com/models/chat/completions?api-version=2024-05-01-preview";
+
+/// Embedding model assumed when `AZURE_EMBEDDING_MODEL` is unset, matching the
+/// precomputed persona embeddings
+pub const DEFAULT_AZURE_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+/// Azure embeddings endpoint for `deployment`, used when `AZURE_EMBEDDING_URL` is unset
+pub fn default_azure_embedding_url(deployment: &str) -> String {
+    format!(
+        "https://aiatlai.cognitiveservices.azure.com/openai/deployments/{}/embeddings?api-version=2023-05-15",
+        deployment
+    )
+}
+
+/// Operator-tunable simulation settings
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    /// Sampling temperature for Phase 1 neighborhood identification (`PHASE1_TEMPERATURE`)
+    ///
+    /// Lower values make the classification step more reliable.
+    pub phase1_temperature: f32,
+    /// Sampling temperature for Phase 2 event generation (`PHASE2_TEMPERATURE`)
+    ///
+    /// Higher values make generated events more varied.
+    pub phase2_temperature: f32,
+    /// Estimated token budget for the Phase 2 neighborhood context (`PHASE2_CONTEXT_TOKEN_BUDGET`)
+    ///
+    /// Less relevant context fields are dropped when the full context exceeds it.
+    pub phase2_context_token_budget: usize,
+    /// Whether the Phase 2 neighborhood context writes metrics at full precision
+    /// instead of rounded to one or two decimals (`CONTEXT_FULL_PRECISION`)
+    ///
+    /// Rounded values cost fewer tokens, but the model may anchor on them and then
+    /// "change" a metric by less than the meaningful-change threshold.
+    pub context_full_precision: bool,
+    /// Contextual fields in the Phase 1 neighborhood context (`PHASE1_CONTEXT_FIELDS`)
+    ///
+    /// See [`ContextualFields`] for the format; an unparseable list keeps the default
+    /// of every field.
+    pub phase1_context_fields: ContextualFields,
+    /// Whether the Phase 1 context gives each neighborhood's population and median
+    /// income bracket (`PHASE1_NUMERIC_HINTS`)
+    ///
+    /// Off by default. Helps Phase 1 size the impact of scale-sensitive policies for
+    /// a few extra tokens per neighborhood.
+    pub phase1_numeric_hints: bool,
+    /// Contextual fields in the Phase 2 neighborhood context (`PHASE2_CONTEXT_FIELDS`)
+    pub phase2_context_fields: ContextualFields,
+    /// Whether repeated Phase 1 calls are served from cache (`PHASE1_CACHE_ENABLED`)
+    pub phase1_cache_enabled: bool,
+    /// Maximum number of cached Phase 1 results (`PHASE1_CACHE_CAPACITY`)
+    pub phase1_cache_capacity: usize,
+    /// Seconds a cached Phase 1 result stays valid (`PHASE1_CACHE_TTL_SECS`)
+    pub phase1_cache_ttl_secs: u64,
+    /// Seconds Phase 1, including retries, may take (`PHASE1_TIMEOUT_SECS`)
+    ///
+    /// On timeout the request's selected zones become the targets, or the simulation
+    /// fails if none were selected.
+    pub phase1_timeout_secs: u64,
+    /// Seconds a Phase 2 stream may go without data before it is abandoned
+    /// (`PHASE2_IDLE_TIMEOUT_SECS`)
+    ///
+    /// The events received so far are kept and the `complete` chunk reports the
+    /// timeout. 0 waits forever.
+    pub phase2_idle_timeout_secs: u64,
+    /// Times a Phase 1 request that got a server error (5xx) is retried
+    /// (`PHASE1_RETRIES`)
+    pub phase1_retries: u32,
+    /// Directory to write each phase's request and raw response to (`PROMPT_LOG_DIR`)
+    ///
+    /// Unset by default, which disables prompt logging.
+    pub prompt_log_dir: Option<PathBuf>,
+    /// Personas responding to a maximum-severity event (`MAX_MESSAGE_PERSONAS`)
+    ///
+    /// Low-severity events hear from two personas; the count scales up to this.
+    pub max_message_personas: usize,
+    /// Default minimum cosine similarity for a persona to respond (`MIN_PERSONA_SIMILARITY`)
+    ///
+    /// Requests can override it with `minSimilarity`.
+    pub min_persona_similarity: f64,
+    /// Sampling temperature for constituent replies (`PERSONA_TEMPERATURE`)
+    pub persona_temperature: f64,
+    /// Whether the `complete` chunk reports drop counts (`COMPLETE_DIAGNOSTICS`)
+    pub complete_diagnostics: bool,
+    /// Whether Phase 2 is shown an example user/assistant exchange (`PHASE2_FEW_SHOT`)
+    ///
+    /// Demonstrating the exact array format tends to cut parse errors more than
+    /// longer instructions do, at the cost of extra prompt tokens.
+    pub phase2_few_shot: bool,
+    /// Simulation streams allowed to run at once (`MAX_CONCURRENT_SIMULATIONS`)
+    ///
+    /// Further requests get a 503 until one finishes. 0 disables the limit.
+    pub max_concurrent_simulations: usize,
+    /// Whether responses are gzip/brotli-compressed for clients that accept it
+    /// (`RESPONSE_COMPRESSION`)
+    ///
+    /// The simulation SSE stream is always sent uncompressed.
+    pub response_compression: bool,
+    /// Chat completions endpoint used by both simulation phases (`AZURE_CHAT_URL`)
+    ///
+    /// Overridable so tests and staging can point at a fake or alternate deployment.
+    pub azure_chat_url: String,
+    /// Embeddings endpoint used for persona matching and `POST /api/embed`
+    /// (`AZURE_EMBEDDING_URL`)
+    ///
+    /// Defaults to the endpoint of `azure_embedding_deployment`.
+    pub azure_embedding_url: String,
+    /// Embedding model behind the deployment (`AZURE_EMBEDDING_MODEL`)
+    ///
+    /// Persona embeddings are precomputed with one model, so startup fails when this
+    /// model's dimensions differ from theirs.
+    pub azure_embedding_model: String,
+    /// Azure deployment serving `azure_embedding_model` (`AZURE_EMBEDDING_DEPLOYMENT`)
+    ///
+    /// Defaults to the model name.
+    pub azure_embedding_deployment: String,
+    /// Directory caching `POST /api/embed` results by text hash (`EMBEDDING_CACHE_DIR`)
+    ///
+    /// Unset by default, which embeds every text on each request.
+    pub embedding_cache_dir: Option<PathBuf>,
+    /// Bearer token required by endpoints that spend Azure tokens on arbitrary input,
+    /// such as `POST /api/embed` (`API_TOKEN`)
+    ///
+    /// Unset by default, which disables those endpoints.
+    pub api_token: Option<String>,
+    /// Times a rate-limited (429) chat request is retried (`AZURE_RATE_LIMIT_RETRIES`)
+    pub rate_limit_retries: u32,
+    /// City named in the generic prompt profile (`CITY_NAME`)
+    pub city_name: String,
+    /// Whether constituent messages use deterministic fake embeddings and canned
+    /// replies instead of calling Azure (`MOCK_AZURE`)
+    ///
+    /// Exercises persona selection end to end without an API key, e.g. in CI.
+    pub mock_azure: bool,
+    /// Model to retry a phase with when the primary model fails (`FALLBACK_MODEL`)
+    ///
+    /// Used after a 404 (e.g. a deprecated or misconfigured deployment), a server
+    /// error, or exhausted rate-limit retries. Unset by default, which disables it.
+    pub fallback_model: Option<String>,
+    /// Whether every Phase 2 parse failure is logged in full and streamed as a
+    /// `warning` chunk (`DEBUG_PARSE_ERRORS`)
+    ///
+    /// Off by default, which logs only the first three failures with a short preview.
+    pub debug_parse_errors: bool,
+    /// Upstream failures within the window that open the Azure circuit breaker
+    /// (`AZURE_BREAKER_FAILURES`)
+    ///
+    /// While open, new simulations fail fast with a 503. 0 disables the breaker.
+    pub breaker_failure_threshold: usize,
+    /// Seconds over which breaker failures are counted (`AZURE_BREAKER_WINDOW_SECS`)
+    pub breaker_window_secs: u64,
+    /// Seconds the breaker stays open before a trial simulation is let through
+    /// (`AZURE_BREAKER_COOLDOWN_SECS`)
+    pub breaker_cooldown_secs: u64,
+    /// Most events a simulation streams (`MAX_TOTAL_EVENTS`)
+    ///
+    /// The prompt asks for at most 13 (or the request's `maxEvents`, which cannot
+    /// exceed this), but the model does not always comply; later events are dropped
+    /// once the cap is reached. 0 disables the cap.
+    pub max_total_events: u32,
+    /// Word overlap (Jaccard similarity of title and description words, 0-1) at which
+    /// an event is dropped as a near-duplicate of an earlier one
+    /// (`NEAR_DUPLICATE_THRESHOLD`)
+    ///
+    /// Catches the model repeating one event across zones with slightly different
+    /// wording. 0 (the default) disables the check; around 0.8 is a reasonable start.
+    pub near_duplicate_threshold: f64,
+    /// Severity below which events are generated but not streamed (`MIN_SEVERITY`)
+    ///
+    /// A request's `minSeverity` overrides it. 0 (the default) streams every event.
+    pub min_severity: f64,
+    /// Whether `GET /api/prompts` returns the system prompts (`EXPOSE_PROMPTS`)
+    ///
+    /// Disable it where the prompt text should not be public; the endpoint then 404s.
+    pub expose_prompts: bool,
+    /// Whether Phase 2 is retried once when its output has no parseable chunks
+    /// (`PHASE2_RETRY_ON_EMPTY`)
+    ///
+    /// Output cut off by the token limit is not retried.
+    pub phase2_retry_on_empty: bool,
+    /// Whether a Phase 2 stream that ends without the model's `complete` chunk gets
+    /// its summary from a short follow-up model call over the parsed events
+    /// (`LLM_FALLBACK_SUMMARY`)
+    ///
+    /// Off by default since it costs an extra request. When off, or if that call
+    /// fails, the summary is the mechanical event count.
+    pub llm_fallback_summary: bool,
+    /// Whether event coordinates always come from the zone's GeoJSON centroid plus a
+    /// small jitter seeded by the event id, instead of from the model
+    /// (`AUTHORITATIVE_COORDINATES`)
+    ///
+    /// Off by default, which keeps the model's coordinates and only snaps those that
+    /// fall outside their zone. Zones without a known boundary keep the model's.
+    pub authoritative_coordinates: bool,
+    /// Decimal places event coordinates are rounded to (`COORDINATE_DECIMALS`)
+    ///
+    /// The default of 5 is about 1 m, finer than any marker needs, and keeps models
+    /// that emit a dozen decimals from bloating the stream.
+    pub coordinate_decimals: u32,
+    /// Most target neighborhoods per Phase 2 request (`PHASE2_BATCH_SIZE`)
+    ///
+    /// Larger target sets are split into batches generated concurrently and merged
+    /// into one stream. Smaller prompts truncate less and cover every neighborhood
+    /// more evenly, and the batches can finish sooner than one long call, but each
+    /// batch costs a request and repeats the system prompt, and batches cannot see
+    /// each other's events. 0 (the default) always uses a single request.
+    pub phase2_batch_size: usize,
+    /// Template for the ids given to streamed events (`EVENT_ID_TEMPLATE`)
+    ///
+    /// `{n}` is replaced by the event's position in the stream and `{request_id}` by
+    /// the simulation id, e.g. `sim-{request_id}-evt-{n}` for globally unique ids.
+    /// Templates without `{n}` are ignored, since their ids would repeat.
+    pub event_id_template: String,
+    /// URL the neighborhoods GeoJSON is fetched from once at startup (`NEIGHBORHOODS_URL`)
+    ///
+    /// Unset by default, which reads `data/neighborhoods.geojson`.
+    pub neighborhoods_url: Option<String>,
+    /// Most simulation runs kept for export and stream resumption
+    /// (`SIMULATION_STORE_CAPACITY`)
+    ///
+    /// When full, the least recently used run is evicted.
+    pub simulation_store_capacity: usize,
+    /// Seconds a finished simulation run stays retrievable
+    /// (`SIMULATION_STORE_RETENTION_SECS`)
+    pub simulation_store_retention_secs: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            phase1_temperature: 0.7,
+            phase2_temperature: 0.8,
+            phase2_context_token_budget: 8000,
+            context_full_precision: false,
+            phase1_context_fields: ContextualFields::ALL,
+            phase1_numeric_hints: false,
+            phase2_context_fields: ContextualFields::ALL,
+            phase1_cache_enabled: true,
+            phase1_cache_capacity: 128,
+            phase1_cache_ttl_secs: 600,
+            phase1_timeout_secs: 30,
+            phase2_idle_timeout_secs: 60,
+            phase1_retries: 1,
+            prompt_log_dir: None,
+            max_message_personas: 5,
+            min_persona_similarity: 0.0,
+            persona_temperature: 0.8,
+            complete_diagnostics: true,
+            phase2_few_shot: false,
+            max_concurrent_simulations: 16,
+            response_compression: true,
+            azure_chat_url: DEFAULT_AZURE_CHAT_URL.to_string(),
+            azure_embedding_url: default_azure_embedding_url(DEFAULT_AZURE_EMBEDDING_MODEL),
+            azure_embedding_model: DEFAULT_AZURE_EMBEDDING_MODEL.to_string(),
+            azure_embedding_deployment: DEFAULT_AZURE_EMBEDDING_MODEL.to_string(),
+            embedding_cache_dir: None,
+            api_token: None,
+            rate_limit_retries: 2,
+            city_name: "Atlanta, Georgia".to_string(),
+            mock_azure: false,
+            fallback_model: None,
+            debug_parse_errors: false,
+            breaker_failure_threshold: 5,
+            breaker_window_secs: 60,
+            breaker_cooldown_secs: 30,
+            max_total_events: crate::events::DEFAULT_MAX_EVENTS,
+            near_duplicate_threshold: 0.0,
+            min_severity: 0.0,
+            expose_prompts: true,
+            phase2_retry_on_empty: true,
+            llm_fallback_summary: false,
+            authoritative_coordinates: false,
+            coordinate_decimals: 5,
+            phase2_batch_size: 0,
+            event_id_template: "event-{n}".to_string(),
+            neighborhoods_url: None,
+            simulation_store_capacity: crate::store::DEFAULT_STORE_CAPACITY,
+            simulation_store_retention_secs: crate::store::DEFAULT_STORE_RETENTION.as_secs(),
+        }
+    }
+}
+
+impl SimulationConfig {
+    /// Loads configuration from environment variables
+    ///
+    /// Unset or unparseable variables keep their default value.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let azure_embedding_model = std::env::var("AZURE_EMBEDDING_MODEL")
+            .ok()
+            .map(|model| model.trim().to_string())
+            .filter(|model| !model.is_empty())
+            .unwrap_or(defaults.azure_embedding_model);
+        let azure_embedding_deployment = std::env::var("AZURE_EMBEDDING_DEPLOYMENT")
+            .ok()
+            .map(|deployment| deployment.trim().to_string())
+            .filter(|deployment| !deployment.is_empty())
+            .unwrap_or_else(|| azure_embedding_model.clone());
+        Self {
+            phase1_temperature: env_or("PHASE1_TEMPERATURE", defaults.phase1_temperature),
+            phase2_temperature: env_or("PHASE2_TEMPERATURE", defaults.phase2_temperature),
+            phase2_context_token_budget: env_or(
+                "PHASE2_CONTEXT_TOKEN_BUDGET",
+                defaults.phase2_context_token_budget,
+            ),
+            context_full_precision: env_flag(
+                "CONTEXT_FULL_PRECISION",
+                defaults.context_full_precision,
+            ),
+            phase1_context_fields: env_or("PHASE1_CONTEXT_FIELDS", defaults.phase1_context_fields),
+            phase1_numeric_hints: env_flag("PHASE1_NUMERIC_HINTS", defaults.phase1_numeric_hints),
+            phase2_context_fields: env_or("PHASE2_CONTEXT_FIELDS", defaults.phase2_context_fields),
+            phase1_cache_enabled: env_flag("PHASE1_CACHE_ENABLED", defaults.phase1_cache_enabled),
+            phase1_cache_capacity: env_or("PHASE1_CACHE_CAPACITY", defaults.phase1_cache_capacity),
+            phase1_cache_ttl_secs: env_or("PHASE1_CACHE_TTL_SECS", defaults.phase1_cache_ttl_secs),
+            phase1_timeout_secs: env_or("PHASE1_TIMEOUT_SECS", defaults.phase1_timeout_secs),
+            phase2_idle_timeout_secs: env_or(
+                "PHASE2_IDLE_TIMEOUT_SECS",
+                defaults.phase2_idle_timeout_secs,
+            ),
+            phase1_retries: env_or("PHASE1_RETRIES", defaults.phase1_retries),
+            prompt_log_dir: std::env::var("PROMPT_LOG_DIR")
+                .ok()
+                .filter(|dir| !dir.trim().is_empty())
+                .map(PathBuf::from),
+            max_message_personas: env_or("MAX_MESSAGE_PERSONAS", defaults.max_message_personas),
+            min_persona_similarity: env_or(
+                "MIN_PERSONA_SIMILARITY",
+                defaults.min_persona_similarity,
+            ),
+            persona_temperature: env_or("PERSONA_TEMPERATURE", defaults.persona_temperature),
+            complete_diagnostics: env_flag("COMPLETE_DIAGNOSTICS", defaults.complete_diagnostics),
+            phase2_few_shot: env_flag("PHASE2_FEW_SHOT", defaults.phase2_few_shot),
+            max_concurrent_simulations: env_or(
+                "MAX_CONCURRENT_SIMULATIONS",
+                defaults.max_concurrent_simulations,
+            ),
+            response_compression: env_flag("RESPONSE_COMPRESSION", defaults.response_compression),
+            azure_chat_url: std::env::var("AZURE_CHAT_URL")
+                .ok()
+                .filter(|url| !url.trim().is_empty())
+                .unwrap_or(defaults.azure_chat_url),
+            azure_embedding_url: std::env::var("AZURE_EMBEDDING_URL")
+                .ok()
+                .filter(|url| !url.trim().is_empty())
+                .unwrap_or_else(|| default_azure_embedding_url(&azure_embedding_deployment)),
+            azure_embedding_model,
+            azure_embedding_deployment,
+            embedding_cache_dir: std::env::var("EMBEDDING_CACHE_DIR")
+                .ok()
+                .filter(|dir| !dir.trim().is_empty())
+                .map(PathBuf::from),
+            api_token: std::env::var("API_TOKEN")
+                .ok()
+                .map(|token| token.trim().to_string())
+                .filter(|token| !token.is_empty()),
+            rate_limit_retries: env_or("AZURE_RATE_LIMIT_RETRIES", defaults.rate_limit_retries),
+            city_name: std::env::var("CITY_NAME")
+                .ok()
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .unwrap_or(defaults.city_name),
+            mock_azure: env_flag("MOCK_AZURE", defaults.mock_azure),
+            fallback_model: std::env::var("FALLBACK_MODEL")
+                .ok()
+                .map(|model| model.trim().to_string())
+                .filter(|model| !model.is_empty()),
+            debug_parse_errors: env_flag("DEBUG_PARSE_ERRORS", defaults.debug_parse_errors),
+            breaker_failure_threshold: env_or(
+                "AZURE_BREAKER_FAILURES",
+                defaults.breaker_failure_threshold,
+            ),
+            breaker_window_secs: env_or("AZURE_BREAKER_WINDOW_SECS", defaults.breaker_window_secs),
+            breaker_cooldown_secs: env_or(
+                "AZURE_BREAKER_COOLDOWN_SECS",
+                defaults.breaker_cooldown_secs,
+            ),
+            max_total_events: env_or("MAX_TOTAL_EVENTS", defaults.max_total_events),
+            near_duplicate_threshold: env_or(
+                "NEAR_DUPLICATE_THRESHOLD",
+                defaults.near_duplicate_threshold,
+            ),
+            min_severity: env_or("MIN_SEVERITY", defaults.min_severity),
+            expose_prompts: env_flag("EXPOSE_PROMPTS", defaults.expose_prompts),
+            phase2_retry_on_empty: env_flag(
+                "PHASE2_RETRY_ON_EMPTY",
+                defaults.phase2_retry_on_empty,
+            ),
+            llm_fallback_summary: env_flag("LLM_FALLBACK_SUMMARY", defaults.llm_fallback_summary),
+            authoritative_coordinates: env_flag(
+                "AUTHORITATIVE_COORDINATES",
+                defaults.authoritative_coordinates,
+            ),
+            coordinate_decimals: env_or("COORDINATE_DECIMALS", defaults.coordinate_decimals),
+            phase2_batch_size: env_or("PHASE2_BATCH_SIZE", defaults.phase2_batch_size),
+            event_id_template: std::env::var("EVENT_ID_TEMPLATE")
+                .ok()
+                .map(|template| template.trim().to_string())
+                .filter(|template| template.contains("{n}"))
+                .unwrap_or(defaults.event_id_template),
+            neighborhoods_url: std::env::var("NEIGHBORHOODS_URL")
+                .ok()
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty()),
+            simulation_store_capacity: env_or(
+                "SIMULATION_STORE_CAPACITY",
+                defaults.simulation_store_capacity,
+            ),
+            simulation_store_retention_secs: env_or(
+                "SIMULATION_STORE_RETENTION_SECS",
+                defaults.simulation_store_retention_secs,
+            ),
+        }
+    }
+}
+
+/// Reads and parses an environment variable, returning `default` if unset or invalid
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Reads a boolean environment variable, returning `default` if unset or invalid
+///
+/// Accepts `1`/`0`, `true`/`false`, and `yes`/`no`, ignoring case.
+pub(crate) fn env_flag(name: &str, default: bool) -> bool {
+    match std::env::var(name)
+        .map(|value| value.trim().to_lowercase())
+        .as_deref()
+    {
+        Ok("1" | "true" | "yes") => true,
+        Ok("0" | "false" | "no") => false,
+        _ => default,
+    }
+}
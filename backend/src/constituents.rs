@@ -1,6 +1,16 @@
-use actix_web::{web, HttpResponse, Error};
+//! Constituent Messages
+//!
+//! This module matches city events to resident personas using embedding similarity
+//! and generates in-character responses from the most relevant personas.
+
+use crate::config::SimulationConfig;
+use crate::error::SimulationError;
+use crate::utils::{read_to_string_bounded, stable_hash};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize)]
 pub struct EventRequest {
@@ -11,6 +21,173 @@ pub struct EventRequest {
     pub severity: f64,
     #[serde(default)]
     pub exclusions: Vec<String>,
+    /// Personas less similar than this are left out even if they rank in the top
+    /// matches; defaults to `MIN_PERSONA_SIMILARITY`
+    #[serde(rename = "minSimilarity", default)]
+    pub min_similarity: Option<f64>,
+    /// Prior turns of a conversation with the persona about this event
+    ///
+    /// Only `user` and `assistant` turns are used, and only the most recent
+    /// [`MAX_HISTORY_MESSAGES`] are sent to the model.
+    #[serde(default)]
+    pub history: Vec<ChatMessage>,
+    /// Longest reply, in sentences, e.g. 1 for a short post or 8 for a letter
+    ///
+    /// Capped at [`MAX_PERSONA_SENTENCES`]; unset asks for the usual 2-3 sentences.
+    #[serde(rename = "maxSentences", default)]
+    pub max_sentences: Option<u32>,
+    /// Tone layered over the persona's own voice; unset leaves it to the persona
+    #[serde(default)]
+    pub tone: Option<PersonaTone>,
+    /// Ad-hoc persona that responds instead of the similarity-selected ones
+    ///
+    /// Used for one-off voices such as "the mayor's office" without registering
+    /// them in `personas.json`; no embedding is needed.
+    #[serde(rename = "customPersona", default)]
+    pub custom_persona: Option<CustomPersona>,
+}
+
+/// Longest name accepted for an inline custom persona, in characters
+pub const MAX_CUSTOM_PERSONA_NAME_CHARS: usize = 100;
+
+/// Longest prompt accepted for an inline custom persona, in characters
+pub const MAX_CUSTOM_PERSONA_PROMPT_CHARS: usize = 4000;
+
+/// A persona supplied inline with a request rather than loaded from `personas.json`
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomPersona {
+    pub name: String,
+    pub agent_prompt: String,
+}
+
+impl CustomPersona {
+    /// Checks that the name and prompt are present and within their length limits
+    fn validate(&self) -> Result<(), SimulationError> {
+        for (field, value, max) in [
+            ("name", &self.name, MAX_CUSTOM_PERSONA_NAME_CHARS),
+            (
+                "agent_prompt",
+                &self.agent_prompt,
+                MAX_CUSTOM_PERSONA_PROMPT_CHARS,
+            ),
+        ] {
+            if value.trim().is_empty() {
+                return Err(SimulationError::InvalidRequest(format!(
+                    "customPersona.{} must not be empty",
+                    field
+                )));
+            }
+            if value.chars().count() > max {
+                return Err(SimulationError::InvalidRequest(format!(
+                    "customPersona.{} must be at most {} characters",
+                    field, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn to_persona(&self) -> Persona {
+        Persona {
+            name: self.name.trim().to_string(),
+            agent_prompt: self.agent_prompt.trim().to_string(),
+            description: String::new(),
+            embeddings: Vec::new(),
+        }
+    }
+}
+
+/// Tone a persona's reply is written in, on top of its personality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PersonaTone {
+    Angry,
+    Supportive,
+    Neutral,
+}
+
+impl PersonaTone {
+    fn instruction(self) -> &'static str {
+        match self {
+            PersonaTone::Angry => {
+                "Write in an angry, frustrated tone, as this person would sound when upset."
+            }
+            PersonaTone::Supportive => {
+                "Write in a supportive, encouraging tone, as this person would sound when pleased."
+            }
+            PersonaTone::Neutral => {
+                "Write in a measured, neutral tone, as this person would sound when reporting facts."
+            }
+        }
+    }
+}
+
+impl EventRequest {
+    /// Checks that the event has a title and description to react to
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SimulationError::InvalidRequest`] if either is empty or whitespace,
+    /// `maxSentences` is 0, or an inline `customPersona` is blank or too long
+    pub fn validate(&self) -> Result<(), SimulationError> {
+        for (field, value) in [("title", &self.title), ("description", &self.description)] {
+            if value.trim().is_empty() {
+                return Err(SimulationError::InvalidRequest(format!(
+                    "event {} must not be empty",
+                    field
+                )));
+            }
+        }
+        if self.max_sentences == Some(0) {
+            return Err(SimulationError::InvalidRequest(
+                "maxSentences must be at least 1".to_string(),
+            ));
+        }
+        if let Some(custom) = &self.custom_persona {
+            custom.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Reply length asked for in the prompt, e.g. "2-3 sentence"
+    fn length_phrase(&self) -> String {
+        match self.max_sentences.map(|n| n.min(MAX_PERSONA_SENTENCES)) {
+            None => "2-3 sentence".to_string(),
+            Some(1) => "one sentence".to_string(),
+            Some(n) => format!("1-{} sentence", n),
+        }
+    }
+}
+
+/// Maximum number of prior conversation turns sent to the model
+pub const MAX_HISTORY_MESSAGES: usize = 10;
+
+/// Longest reply a persona can be asked for, in sentences
+pub const MAX_PERSONA_SENTENCES: u32 = 10;
+
+/// Completion tokens allowed per requested sentence, plus one sentence of slack
+const TOKENS_PER_SENTENCE: u32 = 50;
+
+/// Completion tokens allowed when the request sets no `maxSentences`
+const DEFAULT_PERSONA_MAX_TOKENS: u32 = 200;
+
+/// Completion token budget for a persona's reply to `event`
+///
+/// Scales with `maxSentences` (capped at [`MAX_PERSONA_SENTENCES`]) so short
+/// replies stay cheap and long ones are not cut off mid-sentence.
+pub fn persona_max_tokens(event: &EventRequest) -> u32 {
+    match event.max_sentences {
+        None => DEFAULT_PERSONA_MAX_TOKENS,
+        Some(n) => (n.clamp(1, MAX_PERSONA_SENTENCES) + 1) * TOKENS_PER_SENTENCE,
+    }
+}
+
+/// Request payload for hearing from one specific persona
+#[derive(Debug, Deserialize)]
+pub struct NamedPersonaRequest {
+    /// Name of the persona to respond, as listed in `personas.json`
+    pub persona: String,
+    pub event: EventRequest,
 }
 
 #[derive(Debug, Serialize)]
@@ -20,12 +197,11 @@ pub struct PersonaResponse {
 }
 
 #[derive(Debug, Deserialize)]
-struct Persona {
-    name: String,
-    agent_prompt: String,
-    #[allow(dead_code)]
-    description: String,
-    embeddings: Vec<f64>,
+pub struct Persona {
+    pub name: String,
+    pub agent_prompt: String,
+    pub description: String,
+    pub embeddings: Vec<f64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -42,12 +218,15 @@ struct EmbeddingResponse {
 #[derive(Debug, Deserialize)]
 struct EmbeddingData {
     embedding: Vec<f64>,
+    #[serde(default)]
+    index: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ChatMessage {
-    role: String,
-    content: String,
+/// A single chat turn, as sent to the chat completions API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -68,6 +247,10 @@ struct ChatChoice {
     message: ChatMessage,
 }
 
+/// Cosine similarity of two embeddings, or 0 when either has zero magnitude
+///
+/// A non-finite result, from a NaN component in either embedding, is also 0 so it
+/// cannot poison the persona ranking.
 fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
     let dot_product: f64 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
     let magnitude_a: f64 = a.iter().map(|x| x * x).sum::<f64>().sqrt();
@@ -77,187 +260,810 @@ fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
         return 0.0;
     }
 
-    dot_product / (magnitude_a * magnitude_b)
+    let similarity = dot_product / (magnitude_a * magnitude_b);
+    if similarity.is_finite() {
+        similarity
+    } else {
+        0.0
+    }
 }
 
-async fn get_embedding(text: &str, api_key: &str) -> Result<Vec<f64>, Error> {
+async fn get_embedding(
+    text: &str,
+    api_key: &str,
+    config: &SimulationConfig,
+) -> Result<Vec<f64>, SimulationError> {
+    get_embeddings(&[text.to_string()], api_key, config)
+        .await?
+        .pop()
+        .ok_or_else(|| SimulationError::InvalidResponse("No embedding data returned".to_string()))
+}
+
+/// Embeds several texts in one embeddings API call
+///
+/// Calls `azure_embedding_url` with the configured embedding deployment.
+///
+/// # Returns
+///
+/// One embedding per text, in the same order as `texts`
+///
+/// # Errors
+///
+/// Returns a [`SimulationError`] if the request fails or the response does not hold
+/// exactly one embedding per text
+pub(crate) async fn get_embeddings(
+    texts: &[String],
+    api_key: &str,
+    config: &SimulationConfig,
+) -> Result<Vec<Vec<f64>>, SimulationError> {
     let client = reqwest::Client::new();
-    let url = "https://aiatlai.cognitiveservices.azure.com/openai/deployments/text-embedding-3-small/embeddings?api-version=2023-05-15";
 
     let request_body = EmbeddingRequest {
-        input: vec![text.to_string()],
-        deployment: "text-embedding-3-small".to_string(),
+        input: texts.to_vec(),
+        deployment: config.azure_embedding_deployment.clone(),
     };
 
     let response = client
-        .post(url)
+        .post(&config.azure_embedding_url)
         .header("Content-Type", "application/json")
         .header("Authorization", format!("Bearer {}", api_key))
         .json(&request_body)
         .send()
         .await
         .map_err(|e| {
-            eprintln!("Embedding API request failed: {}", e);
-            actix_web::error::ErrorInternalServerError("Embedding API request failed")
+            logln!("Embedding API request failed: {}", e);
+            SimulationError::Upstream("Embedding API request failed".to_string())
         })?;
 
     let status = response.status();
     if !status.is_success() {
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        eprintln!("Embedding API error: {} - {}", status, error_text);
-        return Err(actix_web::error::ErrorInternalServerError("Embedding API failed"));
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        logln!("Embedding API error: {} - {}", status, error_text);
+        return Err(SimulationError::Upstream(
+            "Embedding API failed".to_string(),
+        ));
     }
 
-    let embedding_response: EmbeddingResponse = response.json().await.map_err(|e| {
-        eprintln!("Failed to parse embedding response: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to parse embedding response")
+    let mut embedding_response: EmbeddingResponse = response.json().await.map_err(|e| {
+        logln!("Failed to parse embedding response: {}", e);
+        SimulationError::InvalidResponse("Failed to parse embedding response".to_string())
     })?;
 
-    embedding_response.data
-        .first()
-        .map(|d| d.embedding.clone())
-        .ok_or_else(|| actix_web::error::ErrorInternalServerError("No embedding data returned"))
+    if embedding_response.data.len() != texts.len() {
+        return Err(SimulationError::InvalidResponse(format!(
+            "Expected {} embeddings, got {}",
+            texts.len(),
+            embedding_response.data.len()
+        )));
+    }
+    embedding_response.data.sort_by_key(|d| d.index);
+    Ok(embedding_response
+        .data
+        .into_iter()
+        .map(|d| d.embedding)
+        .collect())
 }
 
-async fn generate_persona_response(
+/// Deterministic stand-in for an event embedding, used when `MOCK_AZURE` is set
+///
+/// The text is hashed into a seed that is expanded with splitmix64 into `dimensions`
+/// values in -1..1, so the same text always embeds to the same vector, across Rust
+/// releases too.
+pub fn mock_embedding(text: &str, dimensions: usize) -> Vec<f64> {
+    let mut state = stable_hash(text);
+    (0..dimensions)
+        .map(|_| {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            (z >> 11) as f64 / (1_u64 << 53) as f64 * 2.0 - 1.0
+        })
+        .collect()
+}
+
+/// Canned persona reply used when `MOCK_AZURE` is set
+fn mock_persona_response(persona: &Persona, event: &EventRequest) -> String {
+    format!(
+        "[mock] {} heard about \"{}\" in {}.",
+        persona.name, event.title, event.zone
+    )
+}
+
+/// Asks the chat model for a persona's reply, or returns a canned one without a key
+async fn persona_response(
     persona: &Persona,
     event: &EventRequest,
-    api_key: &str,
-) -> Result<String, Error> {
-    let client = reqwest::Client::new();
-    let url = "https://aiatlai.services.ai.azure.com/models/chat/completions?api-version=2024-05-01-preview";
+    api_key: Option<&str>,
+    config: &SimulationConfig,
+) -> Result<String, SimulationError> {
+    match api_key {
+        Some(api_key) => generate_persona_response(persona, event, api_key, config).await,
+        None => Ok(mock_persona_response(persona, event)),
+    }
+}
 
-    let system_prompt = format!(
+/// Reads `AZURE_API_KEY`, or returns `None` in `MOCK_AZURE` mode
+fn api_key_unless_mocked(config: &SimulationConfig) -> Result<Option<String>, SimulationError> {
+    if config.mock_azure {
+        return Ok(None);
+    }
+    env::var("AZURE_API_KEY")
+        .map(Some)
+        .map_err(|_| SimulationError::MissingApiKey)
+}
+
+/// Builds the chat messages asking a persona to respond to an event
+///
+/// The persona's system prompt and the event description always come first so the
+/// persona stays anchored; prior conversation turns from `event.history` follow.
+/// System turns in the history are ignored and only the most recent
+/// [`MAX_HISTORY_MESSAGES`] turns are kept.
+pub fn build_persona_messages(persona: &Persona, event: &EventRequest) -> Vec<ChatMessage> {
+    let length = event.length_phrase();
+    let mut system_prompt = format!(
         "{}\\n\\nYou are responding as a constituent who just heard about an event in their city. \
-        Generate a realistic 2-3 sentence response that this person would send as a message. \
+        Generate a realistic {} response that this person would send as a message. \
         The response should reflect their personality, concerns, and perspective. \
         Be conversational and authentic to their character. Do not use formal language unless it fits their persona.",
-        persona.agent_prompt
+        persona.agent_prompt, length
     );
+    if let Some(tone) = event.tone {
+        system_prompt.push(' ');
+        system_prompt.push_str(tone.instruction());
+    }
 
     let user_prompt = format!(
         "An event just happened in the {} zone:\\n\\nTitle: {}\\nDescription: {}\\n\\n\
         This event has a positivity score of {} (ranging from -1 to 1, where -1 is very negative and 1 is very positive) \
         and a severity of {} (0 to 1, where 1 is very severe).\\n\\n\
-        Write a 2-3 sentence message responding to this event from your perspective as this person.",
-        event.zone, event.title, event.description, event.positivity, event.severity
+        Write a {} message responding to this event from your perspective as this person.",
+        event.zone, event.title, event.description, event.positivity, event.severity, length
     );
 
+    let history: Vec<&ChatMessage> = event
+        .history
+        .iter()
+        .filter(|message| message.role == "user" || message.role == "assistant")
+        .collect();
+    let skip = history.len().saturating_sub(MAX_HISTORY_MESSAGES);
+
+    let mut messages = vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: system_prompt,
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: user_prompt,
+        },
+    ];
+    messages.extend(history.into_iter().skip(skip).cloned());
+    messages
+}
+
+async fn generate_persona_response(
+    persona: &Persona,
+    event: &EventRequest,
+    api_key: &str,
+    config: &SimulationConfig,
+) -> Result<String, SimulationError> {
+    let client = reqwest::Client::new();
+
     let chat_request = ChatRequest {
-        messages: vec![
-            ChatMessage {
-                role: "system".to_string(),
-                content: system_prompt,
-            },
-            ChatMessage {
-                role: "user".to_string(),
-                content: user_prompt,
-            },
-        ],
-        max_tokens: 200,
-        temperature: 0.8,
+        messages: build_persona_messages(persona, event),
+        max_tokens: persona_max_tokens(event),
+        temperature: config.persona_temperature,
         model: "DeepSeek-V3.1".to_string(),
     };
 
     let response = client
-        .post(url)
+        .post(&config.azure_chat_url)
         .header("Content-Type", "application/json")
         .header("Authorization", format!("Bearer {}", api_key))
         .json(&chat_request)
         .send()
         .await
         .map_err(|e| {
-            eprintln!("Chat API request failed: {}", e);
-            actix_web::error::ErrorInternalServerError("Chat API request failed")
+            logln!("Chat API request failed: {}", e);
+            SimulationError::Upstream("Chat API request failed".to_string())
         })?;
 
     let status = response.status();
     if !status.is_success() {
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        eprintln!("Chat API error: {} - {}", status, error_text);
-        return Err(actix_web::error::ErrorInternalServerError("Chat API failed"));
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        logln!("Chat API error: {} - {}", status, error_text);
+        return Err(SimulationError::Upstream("Chat API failed".to_string()));
     }
 
     let chat_response: ChatResponse = response.json().await.map_err(|e| {
-        eprintln!("Failed to parse chat response: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to parse chat response")
+        logln!("Failed to parse chat response: {}", e);
+        SimulationError::InvalidResponse("Failed to parse chat response".to_string())
     })?;
 
-    chat_response.choices
+    chat_response
+        .choices
         .first()
         .map(|choice| choice.message.content.clone())
-        .ok_or_else(|| actix_web::error::ErrorInternalServerError("No chat response returned"))
+        .ok_or_else(|| SimulationError::InvalidResponse("No chat response returned".to_string()))
 }
 
-fn load_personas() -> Result<Vec<Persona>, Error> {
+/// Largest `personas.json` that will be loaded
+pub const MAX_PERSONAS_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Longest a request waits for the persona set to load
+const PERSONAS_LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Persona set shared by every request once loaded
+static PERSONAS: OnceLock<Vec<Persona>> = OnceLock::new();
+
+/// Loads the persona set from `personas.json` in the working directory
+///
+/// # Errors
+///
+/// Returns [`SimulationError::Personas`] if the file is missing, larger than
+/// [`MAX_PERSONAS_BYTES`], not a regular file, or not valid persona JSON
+pub fn load_personas() -> Result<Vec<Persona>, SimulationError> {
     let personas_path = std::path::Path::new("personas.json");
-    let personas_content = std::fs::read_to_string(personas_path).map_err(|e| {
-        eprintln!("Failed to read personas.json: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to read personas.json")
-    })?;
+    let personas_content =
+        read_to_string_bounded(personas_path, MAX_PERSONAS_BYTES).map_err(|e| {
+            logln!("Failed to read personas.json: {}", e);
+            SimulationError::Personas(format!("Failed to read personas.json: {}", e))
+        })?;
 
     serde_json::from_str(&personas_content).map_err(|e| {
-        eprintln!("Failed to parse personas.json: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to parse personas.json")
+        logln!("Failed to parse personas.json: {}", e);
+        SimulationError::Personas("Failed to parse personas.json".to_string())
     })
 }
 
-pub async fn handle_messages(event: web::Json<EventRequest>) -> Result<HttpResponse, Error> {
-    eprintln!("\\n=== GENERATING CONSTITUENT MESSAGES ===");
-    eprintln!("Event: {} in {}", event.title, event.zone);
+/// Number of dimensions in the embeddings produced by a known embedding model
+pub fn embedding_dimensions(model: &str) -> Option<usize> {
+    match model {
+        "text-embedding-3-small" | "text-embedding-ada-002" => Some(1536),
+        "text-embedding-3-large" => Some(3072),
+        _ => None,
+    }
+}
 
-    let api_key = env::var("AZURE_API_KEY")
-        .map_err(|_| actix_web::error::ErrorInternalServerError("AZURE_API_KEY not set"))?;
+/// Checks that the personas' stored embeddings were computed with `model`
+///
+/// Event embeddings from a different model cannot be compared with the persona
+/// embeddings, so a mismatch should stop the server at startup. Models with unknown
+/// dimensions are checked only for agreement among the personas themselves.
+///
+/// # Errors
+///
+/// Returns [`SimulationError::Personas`] naming the first persona whose embedding
+/// length differs from the model's dimensions, or from the first persona's
+pub fn check_persona_embedding_dimensions(
+    personas: &[Persona],
+    model: &str,
+) -> Result<(), SimulationError> {
+    let Some(expected) =
+        embedding_dimensions(model).or_else(|| personas.first().map(|p| p.embeddings.len()))
+    else {
+        return Ok(());
+    };
 
-    let combined_text = format!("{} {}", event.title, event.description);
-    eprintln!("Getting embedding for event...");
-    let event_embedding = get_embedding(&combined_text, &api_key).await?;
+    match personas.iter().find(|p| p.embeddings.len() != expected) {
+        Some(persona) => Err(SimulationError::Personas(format!(
+            "Persona {} has {}-dimensional embeddings but AZURE_EMBEDDING_MODEL {} produces {}; \
+             recompute personas.json or change the model",
+            persona.name,
+            persona.embeddings.len(),
+            model,
+            expected
+        ))),
+        None => Ok(()),
+    }
+}
 
-    eprintln!("Loading personas...");
-    let personas = load_personas()?;
-    eprintln!("Loaded {} personas", personas.len());
+/// A persona entry as written in a candidate `personas.json`, before validation
+///
+/// Every field is optional so one malformed persona is reported instead of failing
+/// the whole file.
+#[derive(Debug, Default, Deserialize)]
+pub struct PersonaEntry {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub agent_prompt: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub embeddings: Option<Vec<f64>>,
+}
 
-    if !event.exclusions.is_empty() {
-        eprintln!("Excluding {} personas: {:?}", event.exclusions.len(), event.exclusions);
+/// A problem with one persona in a candidate persona set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersonaIssue {
+    /// Position of the persona in the file
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    pub message: String,
+}
+
+/// Result of validating a candidate persona set
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersonaValidation {
+    /// Whether the set can be deployed as `personas.json` unchanged
+    pub valid: bool,
+    pub personas: usize,
+    /// Embedding length every persona was checked against, if one could be
+    /// determined
+    #[serde(rename = "expectedDimensions")]
+    pub expected_dimensions: Option<usize>,
+    pub issues: Vec<PersonaIssue>,
+}
+
+/// Checks a candidate persona set without loading it
+///
+/// Every persona needs a non-empty name and agent prompt, a description, and a
+/// finite embedding with the dimensions of `model` (or, for an unknown model, of the
+/// first persona with an embedding). Names must be unique, ignoring case, since
+/// `/api/messages/persona` looks personas up that way.
+pub fn validate_personas(entries: &[PersonaEntry], model: &str) -> PersonaValidation {
+    let expected = embedding_dimensions(model).or_else(|| {
+        entries
+            .iter()
+            .find_map(|e| e.embeddings.as_ref().filter(|v| !v.is_empty()))
+            .map(Vec::len)
+    });
+    let mut issues = Vec::new();
+    let mut seen_names: HashMap<String, usize> = HashMap::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let name = entry
+            .name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty());
+        let mut issue = |message: String| {
+            issues.push(PersonaIssue {
+                index,
+                name: name.map(str::to_string),
+                message,
+            })
+        };
+
+        match name {
+            None => issue("name is missing or empty".to_string()),
+            Some(name) => {
+                if let Some(first) = seen_names.insert(name.to_lowercase(), index) {
+                    issue(format!(
+                        "duplicate name; persona {} has the same name",
+                        first
+                    ));
+                }
+            }
+        }
+        if entry
+            .agent_prompt
+            .as_deref()
+            .is_none_or(|p| p.trim().is_empty())
+        {
+            issue("agent_prompt is missing or empty".to_string());
+        }
+        if entry.description.is_none() {
+            issue("description is missing".to_string());
+        }
+        match entry.embeddings.as_deref() {
+            None | Some([]) => issue("embeddings are missing or empty".to_string()),
+            Some(embedding) => {
+                if let Some(expected) = expected
+                    && embedding.len() != expected
+                {
+                    issue(format!(
+                        "embeddings have {} dimensions, expected {}",
+                        embedding.len(),
+                        expected
+                    ));
+                }
+                if embedding.iter().any(|x| !x.is_finite()) {
+                    issue("embeddings contain non-finite values".to_string());
+                }
+            }
+        }
     }
 
-    eprintln!("Calculating cosine similarities...");
+    PersonaValidation {
+        valid: !entries.is_empty() && issues.is_empty(),
+        personas: entries.len(),
+        expected_dimensions: expected,
+        issues,
+    }
+}
+
+/// Returns the persona set, loading it on a blocking thread the first time
+///
+/// A failed load is not cached, so fixing the file takes effect on the next request.
+async fn cached_personas() -> Result<&'static [Persona], SimulationError> {
+    if let Some(personas) = PERSONAS.get() {
+        return Ok(personas);
+    }
+
+    let loaded = tokio::time::timeout(
+        PERSONAS_LOAD_TIMEOUT,
+        tokio::task::spawn_blocking(load_personas),
+    )
+    .await
+    .map_err(|_| SimulationError::Personas("Timed out loading personas.json".to_string()))?
+    .map_err(|e| SimulationError::Personas(format!("Failed to load personas.json: {}", e)))??;
+
+    Ok(PERSONAS.get_or_init(|| loaded))
+}
+
+/// Finds a persona by name, ignoring case and surrounding whitespace
+pub fn find_persona<'a>(personas: &'a [Persona], name: &str) -> Option<&'a Persona> {
+    let name = name.trim();
+    personas
+        .iter()
+        .find(|persona| persona.name.eq_ignore_ascii_case(name))
+}
+
+/// Ranks personas by cosine similarity to an event embedding
+///
+/// Personas named in `exclusions` are skipped. Returns `(index, similarity)` pairs
+/// indexing into `personas`, most similar first. Ties are broken by persona name so
+/// the same event always selects the same personas.
+pub fn rank_personas(
+    event_embedding: &[f64],
+    personas: &[Persona],
+    exclusions: &[String],
+) -> Vec<(usize, f64)> {
     let mut similarities: Vec<(usize, f64)> = personas
         .iter()
         .enumerate()
-        .filter(|(_, persona)| !event.exclusions.contains(&persona.name))
+        .filter(|(_, persona)| !exclusions.contains(&persona.name))
         .map(|(idx, persona)| {
-            let similarity = cosine_similarity(&event_embedding, &persona.embeddings);
+            let similarity = cosine_similarity(event_embedding, &persona.embeddings);
             (idx, similarity)
         })
         .collect();
 
-    similarities.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    similarities.sort_by(|a, b| {
+        b.1.total_cmp(&a.1)
+            .then_with(|| personas[a.0].name.cmp(&personas[b.0].name))
+    });
+    similarities
+}
+
+/// Pairwise cosine similarity between personas, keyed by persona name on both axes
+pub type SimilarityMatrix = BTreeMap<String, BTreeMap<String, f64>>;
+
+/// Computes the cosine similarity of every pair of persona embeddings
+///
+/// The matrix is symmetric, with each persona's similarity to itself on the
+/// diagonal (1.0 unless its embedding is all zeros). Rows with high off-diagonal
+/// values point at personas that are redundant or always selected together.
+pub fn persona_similarity_matrix(personas: &[Persona]) -> SimilarityMatrix {
+    let mut matrix = SimilarityMatrix::new();
+    for (i, a) in personas.iter().enumerate() {
+        for b in &personas[i..] {
+            let similarity = cosine_similarity(&a.embeddings, &b.embeddings);
+            matrix
+                .entry(a.name.clone())
+                .or_default()
+                .insert(b.name.clone(), similarity);
+            matrix
+                .entry(b.name.clone())
+                .or_default()
+                .insert(a.name.clone(), similarity);
+        }
+    }
+    matrix
+}
 
-    let top_2: Vec<&Persona> = similarities
+/// Loads the persona set and returns its [`persona_similarity_matrix`]
+///
+/// # Errors
+///
+/// Returns [`SimulationError::Personas`] if `personas.json` cannot be loaded
+pub async fn persona_similarity() -> Result<SimilarityMatrix, SimulationError> {
+    let personas = cached_personas().await?;
+    Ok(persona_similarity_matrix(personas))
+}
+
+/// Picks the top `count` ranked personas whose similarity reaches `min_similarity`
+///
+/// May return fewer than `count`, or none, when too few personas match the event
+/// strongly enough; a weak match produces an irrelevant reaction.
+pub fn select_personas(
+    ranked: &[(usize, f64)],
+    count: usize,
+    min_similarity: f64,
+) -> Vec<(usize, f64)> {
+    ranked
         .iter()
-        .take(2)
-        .map(|(idx, _)| &personas[*idx])
-        .collect();
+        .take(count)
+        .filter(|(_, similarity)| *similarity >= min_similarity)
+        .copied()
+        .collect()
+}
 
-    eprintln!("Top 2 similar personas:");
-    for (i, persona) in top_2.iter().enumerate() {
-        eprintln!("  {}. {} (similarity: {:.4})", i + 1, persona.name, similarities[i].1);
+/// Minimum number of personas that respond to any event
+pub const MIN_MESSAGE_PERSONAS: usize = 2;
+
+/// Number of personas that should respond to an event of the given severity
+///
+/// Scales linearly from [`MIN_MESSAGE_PERSONAS`] at severity 0 to `max_personas`
+/// at severity 1, so bigger events draw more (and more varied) reactions.
+pub fn persona_count_for_severity(severity: f64, max_personas: usize) -> usize {
+    let max_personas = max_personas.max(MIN_MESSAGE_PERSONAS);
+    let severity = if severity.is_finite() {
+        severity.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let extra = (max_personas - MIN_MESSAGE_PERSONAS) as f64 * severity;
+    MIN_MESSAGE_PERSONAS + extra.round() as usize
+}
+
+/// Embeds an event's title and description, or mocks the embedding without a key
+async fn embed_event(
+    event: &EventRequest,
+    personas: &[Persona],
+    api_key: Option<&str>,
+    config: &SimulationConfig,
+) -> Result<Vec<f64>, SimulationError> {
+    let combined_text = format!("{} {}", event.title, event.description);
+    match api_key {
+        Some(api_key) => get_embedding(&combined_text, api_key, config).await,
+        None => {
+            logln!("MOCK_AZURE is set; using a mock embedding");
+            let dimensions = personas.first().map_or(0, |p| p.embeddings.len());
+            Ok(mock_embedding(&combined_text, dimensions))
+        }
     }
+}
 
-    eprintln!("Generating responses...");
+/// Predicted stances within this distance of zero count as neutral
+pub const NEUTRAL_STANCE_BAND: f64 = 0.15;
+
+/// City-wide mood toward an event, aggregated over every persona
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SentimentDistribution {
+    /// Percent of the similarity-weighted personas predicted to support the event
+    pub supportive: f64,
+    /// Percent predicted to be neutral
+    pub neutral: f64,
+    /// Percent predicted to oppose it
+    pub opposed: f64,
+    /// Similarity-weighted mean stance, from -1 (opposed) to 1 (supportive)
+    pub mean_stance: f64,
+    /// Number of personas counted
+    pub personas: usize,
+}
+
+/// Aggregates predicted persona stances toward an event without generating text
+///
+/// Each persona's stance is the event's positivity, scaled up by its severity and
+/// by how closely the persona relates to it: `similarities` are the
+/// `(index, similarity)` pairs from [`rank_personas`], normalized by the strongest
+/// one, so unrelated personas stay near neutral and negatively related ones lean
+/// the other way. Personas count toward the percentages and the mean in proportion
+/// to the magnitude of their similarity. With no similarity at all, everyone is
+/// neutral.
+pub fn sentiment_distribution(
+    event: &EventRequest,
+    similarities: &[(usize, f64)],
+) -> SentimentDistribution {
+    let strongest = similarities
+        .iter()
+        .map(|(_, similarity)| similarity.abs())
+        .fold(0.0, f64::max);
+    let intensity = 0.5 + 0.5 * event.severity.clamp(0.0, 1.0);
+
+    let (mut supportive, mut neutral, mut opposed, mut stance_sum) = (0.0, 0.0, 0.0, 0.0);
+    for &(_, similarity) in similarities {
+        let weight = similarity.abs();
+        if strongest == 0.0 || weight == 0.0 {
+            continue;
+        }
+        let stance = (event.positivity * intensity * similarity / strongest).clamp(-1.0, 1.0);
+        stance_sum += stance * weight;
+        if stance > NEUTRAL_STANCE_BAND {
+            supportive += weight;
+        } else if stance < -NEUTRAL_STANCE_BAND {
+            opposed += weight;
+        } else {
+            neutral += weight;
+        }
+    }
+
+    let total = supportive + neutral + opposed;
+    if total == 0.0 {
+        return SentimentDistribution {
+            supportive: 0.0,
+            neutral: 100.0,
+            opposed: 0.0,
+            mean_stance: 0.0,
+            personas: similarities.len(),
+        };
+    }
+    SentimentDistribution {
+        supportive: supportive / total * 100.0,
+        neutral: neutral / total * 100.0,
+        opposed: opposed / total * 100.0,
+        mean_stance: stance_sum / total,
+        personas: similarities.len(),
+    }
+}
+
+/// Predicts the distribution of public sentiment toward an event across all personas
+///
+/// Embeds the event once and ranks every persona, but makes no chat calls, so it
+/// costs a single embedding regardless of the number of personas. See
+/// [`sentiment_distribution`] for how stances are predicted.
+///
+/// # Errors
+///
+/// Returns [`SimulationError::InvalidRequest`] if the event has no title or
+/// description, or another [`SimulationError`] if `AZURE_API_KEY` is not set,
+/// `personas.json` cannot be loaded, or the embedding call fails. With
+/// `MOCK_AZURE` set, the event gets a [`mock_embedding`] and no key is needed.
+pub async fn public_sentiment(
+    event: &EventRequest,
+    config: &SimulationConfig,
+) -> Result<SentimentDistribution, SimulationError> {
+    event.validate()?;
+
+    let api_key = api_key_unless_mocked(config)?;
+    let personas = cached_personas().await?;
+    let event_embedding = embed_event(event, personas, api_key.as_deref(), config).await?;
+    let similarities = rank_personas(&event_embedding, personas, &event.exclusions);
+
+    let distribution = sentiment_distribution(event, &similarities);
+    logln!(
+        "Sentiment for {} across {} personas: {:.0}% supportive, {:.0}% neutral, {:.0}% opposed",
+        event.title,
+        distribution.personas,
+        distribution.supportive,
+        distribution.neutral,
+        distribution.opposed
+    );
+    Ok(distribution)
+}
+
+/// Generates messages from the personas most relevant to an event
+///
+/// Embeds the event title and description, ranks personas by similarity,
+/// and asks the chat model to respond in character for the top matches. The
+/// number of personas scales with the event's severity (see
+/// [`persona_count_for_severity`]), and matches below the similarity threshold
+/// are dropped, so the result may be empty. An inline `customPersona` skips all of
+/// this and is the only one to respond.
+///
+/// # Errors
+///
+/// Returns [`SimulationError::InvalidRequest`] if the event has no title or
+/// description, or another [`SimulationError`] if `AZURE_API_KEY` is not set,
+/// `personas.json` cannot be loaded, or the embedding or chat API calls fail.
+///
+/// With `MOCK_AZURE` set, no key is needed: the event gets a [`mock_embedding`]
+/// and each persona a canned reply.
+pub async fn generate_constituent_messages(
+    event: &EventRequest,
+    config: &SimulationConfig,
+) -> Result<Vec<PersonaResponse>, SimulationError> {
+    event.validate()?;
+
+    logln!("\\n=== GENERATING CONSTITUENT MESSAGES ===");
+    logln!("Event: {} in {}", event.title, event.zone);
+
+    let api_key = api_key_unless_mocked(config)?;
+
+    if let Some(custom) = &event.custom_persona {
+        let persona = custom.to_persona();
+        logln!("Responding as custom persona {}", persona.name);
+        let message = persona_response(&persona, event, api_key.as_deref(), config).await?;
+        return Ok(vec![PersonaResponse {
+            name: persona.name,
+            message,
+        }]);
+    }
+
+    logln!("Loading personas...");
+    let personas = cached_personas().await?;
+    logln!("Loaded {} personas", personas.len());
+
+    logln!("Getting embedding for event...");
+    let event_embedding = embed_event(event, personas, api_key.as_deref(), config).await?;
+
+    if !event.exclusions.is_empty() {
+        logln!(
+            "Excluding {} personas: {:?}",
+            event.exclusions.len(),
+            event.exclusions
+        );
+    }
+
+    logln!("Calculating cosine similarities...");
+    let similarities = rank_personas(&event_embedding, personas, &event.exclusions);
+
+    let persona_count = persona_count_for_severity(event.severity, config.max_message_personas);
+    let min_similarity = event
+        .min_similarity
+        .unwrap_or(config.min_persona_similarity);
+    let selected = select_personas(&similarities, persona_count, min_similarity);
+
+    logln!(
+        "Top {} similar personas (severity {}, min similarity {}):",
+        selected.len(),
+        event.severity,
+        min_similarity
+    );
+    for (i, (idx, similarity)) in selected.iter().enumerate() {
+        logln!(
+            "  {}. {} (similarity: {:.4})",
+            i + 1,
+            personas[*idx].name,
+            similarity
+        );
+    }
+    if selected.is_empty() {
+        logln!("No strongly matching constituents for this event");
+    }
+
+    let top_personas: Vec<&Persona> = selected.iter().map(|(idx, _)| &personas[*idx]).collect();
+
+    logln!("Generating responses...");
     let mut responses = Vec::new();
 
-    for persona in top_2 {
-        let message = generate_persona_response(persona, &event, &api_key).await?;
+    for persona in top_personas {
+        let message = persona_response(persona, event, api_key.as_deref(), config).await?;
         responses.push(PersonaResponse {
             name: persona.name.clone(),
             message,
         });
-        eprintln!("  ✓ Generated response for {}", persona.name);
+        logln!("  ✓ Generated response for {}", persona.name);
     }
 
-    eprintln!("=== CONSTITUENT MESSAGES COMPLETE ===\\n");
+    logln!("=== CONSTITUENT MESSAGES COMPLETE ===\\n");
+
+    Ok(responses)
+}
+
+/// Generates a message from one named persona, regardless of similarity
+///
+/// # Errors
+///
+/// Returns [`SimulationError::InvalidRequest`] if the event has no title or
+/// description, [`SimulationError::PersonaNotFound`] if no persona has the given
+/// name, or another [`SimulationError`] if `personas.json` cannot be loaded,
+/// `AZURE_API_KEY` is not set, or the chat API call fails. With `MOCK_AZURE` set,
+/// the reply is canned and no key is needed.
+pub async fn generate_named_persona_message(
+    persona_name: &str,
+    event: &EventRequest,
+    config: &SimulationConfig,
+) -> Result<PersonaResponse, SimulationError> {
+    event.validate()?;
+
+    logln!("\n=== GENERATING MESSAGE FROM {} ===", persona_name);
+    logln!("Event: {} in {}", event.title, event.zone);
+
+    let personas = cached_personas().await?;
+    let persona = find_persona(personas, persona_name)
+        .ok_or_else(|| SimulationError::PersonaNotFound(persona_name.to_string()))?;
+
+    let api_key = api_key_unless_mocked(config)?;
 
-    Ok(HttpResponse::Ok().json(responses))
+    let message = persona_response(persona, event, api_key.as_deref(), config).await?;
+    logln!("  ✓ Generated response for {}", persona.name);
+
+    Ok(PersonaResponse {
+        name: persona.name.clone(),
+        message,
+    })
 }
@@ -0,0 +1,138 @@
+//! Neighborhood Snapshot Diffs
+//!
+//! This module compares two snapshots of neighborhood properties (e.g. the baseline
+//! and the state after applying simulation events) and reports which metrics
+//! changed, so clients can build before/after views without diffing every field.
+
+use crate::export::CSV_METRIC_COLUMNS;
+use crate::metrics;
+use crate::types::NeighborhoodProperties;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Changes smaller than this are treated as floating-point noise
+const CHANGE_EPSILON: f64 = 1e-9;
+
+/// Request payload for the neighborhoods diff endpoint
+#[derive(Debug, Deserialize)]
+pub struct NeighborhoodDiffRequest {
+    pub baseline: Vec<NeighborhoodProperties>,
+    pub modified: Vec<NeighborhoodProperties>,
+}
+
+/// The metrics that changed in one neighborhood
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NeighborhoodDiff {
+    pub name: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// One changed metric, identified by its dotted path (e.g. `commute.avg_minutes`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub baseline: f64,
+    pub modified: f64,
+    pub delta: f64,
+    /// Change relative to the baseline, in percent; `None` when the baseline is 0
+    #[serde(rename = "percentChange")]
+    pub percent_change: Option<f64>,
+}
+
+/// Diffs every neighborhood present in both snapshots, matched by name
+///
+/// Derived metrics (`derived.higher_ed_percent`, `derived.density_index`,
+/// `diversity_index`, `vacancy_rate`) are recomputed for the modified snapshot
+/// whenever their inputs changed, so a client that only edited the inputs still
+/// sees the knock-on effects. Unchanged fields and unchanged neighborhoods are
+/// omitted, as are neighborhoods missing from the baseline.
+///
+/// # Returns
+///
+/// One entry per changed neighborhood, in the order of `modified`
+pub fn diff_neighborhoods(
+    baseline: &[NeighborhoodProperties],
+    modified: &[NeighborhoodProperties],
+) -> Vec<NeighborhoodDiff> {
+    modified
+        .iter()
+        .filter_map(|modified| {
+            let original = baseline.iter().find(|n| n.name == modified.name)?;
+            let mut modified = modified.clone();
+            recompute_derived(original, &mut modified);
+
+            let changes = diff_fields(original, &modified);
+            (!changes.is_empty()).then(|| NeighborhoodDiff {
+                name: modified.name.clone(),
+                changes,
+            })
+        })
+        .collect()
+}
+
+/// Recomputes derived metrics whose inputs differ from the baseline
+fn recompute_derived(original: &NeighborhoodProperties, modified: &mut NeighborhoodProperties) {
+    let education = |n: &NeighborhoodProperties| {
+        let e = &n.education_distribution;
+        [
+            e.high_school_or_less,
+            e.some_college,
+            e.bachelors,
+            e.graduate,
+        ]
+    };
+    if education(original) != education(modified) {
+        modified.derived.higher_ed_percent = metrics::higher_ed(&modified.education_distribution);
+    }
+
+    let race = |n: &NeighborhoodProperties| {
+        let r = &n.race_distribution;
+        [r.white, r.black, r.asian, r.mixed, r.hispanic]
+    };
+    if race(original) != race(modified) {
+        modified.diversity_index = metrics::diversity(&modified.race_distribution);
+    }
+
+    if original.population_total != modified.population_total
+        || original.area_acres != modified.area_acres
+    {
+        modified.derived.density_index =
+            metrics::density(modified.population_total as f64, modified.area_acres);
+    }
+
+    if original.vacant_units != modified.vacant_units
+        || original.housing_units != modified.housing_units
+    {
+        modified.vacancy_rate =
+            metrics::vacancy(modified.vacant_units as f64, modified.housing_units as f64);
+    }
+}
+
+fn diff_fields(
+    original: &NeighborhoodProperties,
+    modified: &NeighborhoodProperties,
+) -> Vec<FieldChange> {
+    let original = serde_json::to_value(original).unwrap_or(Value::Null);
+    let modified = serde_json::to_value(modified).unwrap_or(Value::Null);
+    let number = |value: &Value, path: &str| {
+        path.split('.')
+            .try_fold(value, |value, key| value.get(key))
+            .and_then(Value::as_f64)
+    };
+
+    CSV_METRIC_COLUMNS
+        .iter()
+        .filter_map(|&field| {
+            let baseline = number(&original, field)?;
+            let modified = number(&modified, field)?;
+            let delta = modified - baseline;
+            (delta.abs() > CHANGE_EPSILON).then(|| FieldChange {
+                field: field.to_string(),
+                baseline,
+                modified,
+                delta,
+                percent_change: (baseline != 0.0).then(|| delta / baseline.abs() * 100.0),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,186 @@
+//! Text Embeddings
+//!
+//! This module exposes the embedding model used for persona matching as a general
+//! primitive, e.g. for clustering events or semantic search over neighborhoods.
+//! Embeddings can be cached on disk by model and text hash so identical strings are
+//! only sent to Azure once.
+
+use crate::config::SimulationConfig;
+use crate::constituents::{get_embeddings, mock_embedding};
+use crate::error::SimulationError;
+use crate::utils::stable_hash;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Most texts accepted in one embed request
+pub const MAX_EMBED_TEXTS: usize = 256;
+
+/// Dimensions of the mock embeddings returned when `MOCK_AZURE` is set, matching
+/// `text-embedding-3-small`
+pub const MOCK_EMBEDDING_DIMENSIONS: usize = 1536;
+
+/// Request payload for the embed endpoint
+#[derive(Debug, Deserialize)]
+pub struct EmbedRequest {
+    pub texts: Vec<String>,
+}
+
+/// Embeddings for an embed request, in the order the texts were given
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmbedResponse {
+    pub embeddings: Vec<Vec<f64>>,
+    /// How many of the embeddings were served from the cache
+    pub cached: usize,
+}
+
+/// On-disk cache of embeddings keyed by a hash of the embedding model and the text
+///
+/// Each entry is a JSON file named after the hash that also stores the model and
+/// text, so a hash collision is treated as a miss. Vectors from one model are never
+/// served for another, whose dimensions may differ. A cache without a directory
+/// never hits.
+#[derive(Debug, Clone)]
+pub struct EmbeddingCache {
+    dir: Option<PathBuf>,
+    model: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedEmbedding {
+    #[serde(default)]
+    model: String,
+    text: String,
+    embedding: Vec<f64>,
+}
+
+impl EmbeddingCache {
+    /// Creates a cache of `model`'s embeddings storing entries in `dir`, or a
+    /// disabled one if `None`
+    pub fn new(dir: Option<PathBuf>, model: &str) -> Self {
+        Self {
+            dir,
+            model: model.to_string(),
+        }
+    }
+
+    /// Creates the cache for `config`'s embedding model
+    ///
+    /// With `MOCK_AZURE` set the entries are namespaced apart, so mock vectors
+    /// are never served once the server talks to Azure again.
+    pub fn from_config(config: &SimulationConfig) -> Self {
+        let model = if config.mock_azure {
+            format!("mock:{}", config.azure_embedding_model)
+        } else {
+            config.azure_embedding_model.clone()
+        };
+        Self::new(config.embedding_cache_dir.clone(), &model)
+    }
+
+    /// Returns the cached embedding for `text`, if any
+    pub fn get(&self, text: &str) -> Option<Vec<f64>> {
+        let contents = std::fs::read_to_string(self.path(text)?).ok()?;
+        let entry: CachedEmbedding = serde_json::from_str(&contents).ok()?;
+        (entry.model == self.model && entry.text == text).then_some(entry.embedding)
+    }
+
+    /// Stores the embedding for `text`, logging rather than failing on write errors
+    pub fn insert(&self, text: &str, embedding: &[f64]) {
+        let (Some(dir), Some(path)) = (&self.dir, self.path(text)) else {
+            return;
+        };
+        let entry = CachedEmbedding {
+            model: self.model.clone(),
+            text: text.to_string(),
+            embedding: embedding.to_vec(),
+        };
+        let written = std::fs::create_dir_all(dir).and_then(|_| {
+            let json = serde_json::to_string(&entry).map_err(std::io::Error::other)?;
+            std::fs::write(&path, json)
+        });
+        if let Err(e) = written {
+            logln!(
+                "   ⚠️  Could not cache embedding at {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+
+    fn path(&self, text: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| {
+            dir.join(format!(
+                "{:016x}.json",
+                stable_hash(&format!("{}\n{}", self.model, text))
+            ))
+        })
+    }
+}
+
+/// Embeds each text, reusing cached embeddings and batching the rest into one call
+///
+/// # Arguments
+///
+/// * `request` - The texts to embed
+/// * `config` - Supplies the embeddings endpoint and `MOCK_AZURE`
+/// * `cache` - Checked before calling Azure and filled with the new embeddings
+///
+/// # Errors
+///
+/// Returns a [`SimulationError`] if:
+/// - `texts` is empty, longer than [`MAX_EMBED_TEXTS`], or contains a blank text
+/// - `AZURE_API_KEY` is not set and `MOCK_AZURE` is off
+/// - The embeddings API request fails
+pub async fn embed_texts(
+    request: &EmbedRequest,
+    config: &SimulationConfig,
+    cache: &EmbeddingCache,
+) -> Result<EmbedResponse, SimulationError> {
+    if request.texts.is_empty() || request.texts.len() > MAX_EMBED_TEXTS {
+        return Err(SimulationError::InvalidRequest(format!(
+            "texts must contain 1 to {} entries (got {})",
+            MAX_EMBED_TEXTS,
+            request.texts.len()
+        )));
+    }
+    if let Some(index) = request.texts.iter().position(|t| t.trim().is_empty()) {
+        return Err(SimulationError::InvalidRequest(format!(
+            "texts[{}] must not be empty",
+            index
+        )));
+    }
+
+    let mut embeddings: Vec<Option<Vec<f64>>> =
+        request.texts.iter().map(|text| cache.get(text)).collect();
+    let cached = embeddings.iter().filter(|e| e.is_some()).count();
+    let missing: Vec<usize> = (0..embeddings.len())
+        .filter(|&i| embeddings[i].is_none())
+        .collect();
+
+    if !missing.is_empty() {
+        let texts: Vec<String> = missing.iter().map(|&i| request.texts[i].clone()).collect();
+        let fresh = if config.mock_azure {
+            texts
+                .iter()
+                .map(|text| mock_embedding(text, MOCK_EMBEDDING_DIMENSIONS))
+                .collect()
+        } else {
+            let api_key =
+                std::env::var("AZURE_API_KEY").map_err(|_| SimulationError::MissingApiKey)?;
+            get_embeddings(&texts, &api_key, config).await?
+        };
+        for (&index, embedding) in missing.iter().zip(fresh) {
+            cache.insert(&request.texts[index], &embedding);
+            embeddings[index] = Some(embedding);
+        }
+    }
+
+    logln!(
+        "   ✓ Embedded {} texts ({} from cache)",
+        embeddings.len(),
+        cached
+    );
+    Ok(EmbedResponse {
+        embeddings: embeddings.into_iter().flatten().collect(),
+        cached,
+    })
+}
@@ -0,0 +1,67 @@
+//! Simulation Error Types
+//!
+//! This module defines the domain error returned by the simulation and constituent
+//! logic. It is independent of the web framework; HTTP handlers map it to a response
+//! at the boundary.
+
+use std::fmt;
+
+/// Errors that can occur while generating simulations or constituent messages
+#[derive(Debug)]
+pub enum SimulationError {
+    /// `AZURE_API_KEY` environment variable is not set
+    MissingApiKey,
+    /// A request to an Azure API failed or returned an error status
+    Upstream(String),
+    /// An Azure API response could not be parsed or was missing expected data
+    InvalidResponse(String),
+    /// Phase 1 did not identify any target neighborhoods
+    NoTargetNeighborhoods,
+    /// None of the target neighborhoods had properties available for Phase 2
+    MissingNeighborhoodData,
+    /// The persona set could not be loaded
+    Personas(String),
+    /// No stored simulation exists with the given id
+    SimulationNotFound(String),
+    /// No persona exists with the given name
+    PersonaNotFound(String),
+    /// The request was well-formed JSON but contained invalid values
+    InvalidRequest(String),
+    /// The maximum number of concurrent simulations are already running
+    TooManySimulations,
+    /// Recent Azure calls kept failing, so new simulations are rejected for a while
+    AzureUnavailable { retry_after_secs: u64 },
+    /// The request lacked the `API_TOKEN` bearer token a protected endpoint requires
+    Unauthorized,
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::MissingApiKey => write!(f, "AZURE_API_KEY not set"),
+            SimulationError::Upstream(message)
+            | SimulationError::InvalidResponse(message)
+            | SimulationError::Personas(message) => write!(f, "{}", message),
+            SimulationError::NoTargetNeighborhoods => {
+                write!(f, "No target neighborhoods identified in Phase 1")
+            }
+            SimulationError::MissingNeighborhoodData => {
+                write!(f, "No full properties found for target neighborhoods")
+            }
+            SimulationError::SimulationNotFound(id) => write!(f, "Simulation {} not found", id),
+            SimulationError::PersonaNotFound(name) => write!(f, "Persona {} not found", name),
+            SimulationError::InvalidRequest(message) => write!(f, "Invalid request: {}", message),
+            SimulationError::TooManySimulations => {
+                write!(f, "Too many simulations are running; try again shortly")
+            }
+            SimulationError::AzureUnavailable { retry_after_secs } => write!(
+                f,
+                "Azure AI is failing repeatedly; try again in {}s",
+                retry_after_secs
+            ),
+            SimulationError::Unauthorized => write!(f, "Missing or invalid API token"),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
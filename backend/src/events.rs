@@ -0,0 +1,1158 @@
+//! Phase 2 Event Processing
+//!
+//! This module decides what happens to each chunk parsed from the Phase 2 model
+//! output before it reaches the client: events are validated against the baseline,
+//! their metrics are completed, and off-target, duplicate, and filtered events are
+//! dropped. It also keeps the running counts reported in the completion chunk.
+
+use crate::breaker::CircuitBreaker;
+use crate::config::SimulationConfig;
+use crate::geo::{
+    CoordinateCheck, ZoneBoundaries, distance_meters, normalize_coordinates, round_coordinates,
+};
+use crate::telemetry::ParseTelemetry;
+use crate::types::{
+    EventNotification, NeighborhoodFinalState, NeighborhoodMetrics, NeighborhoodProperties,
+    SCHEMA_VERSION, SimulationBaseline, SimulationChunk, SimulationComplete, SimulationDiagnostics,
+    SimulationRequest, SimulationWarning, StableZone, StreamTermination, SummaryHighlights,
+};
+use crate::utils::{
+    apply_metric_overrides, complete_interdependent_metrics, has_meaningful_change,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Lowest event severity whose effects are expected to spill into neighboring zones
+pub const SPILLOVER_MIN_SEVERITY: f64 = 0.5;
+
+/// Fewest events a simulation asks for when the request does not set `minEvents`
+pub const DEFAULT_MIN_EVENTS: u32 = 3;
+
+/// Most events a simulation asks for when neither the request's `maxEvents` nor
+/// `MAX_TOTAL_EVENTS` says otherwise
+pub const DEFAULT_MAX_EVENTS: u32 = 13;
+
+/// Per-request options controlling which Phase 2 chunks are streamed
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    /// Events with a lower positivity are generated but not streamed
+    pub min_positivity: Option<f64>,
+    /// Events with a higher positivity are generated but not streamed
+    pub max_positivity: Option<f64>,
+    /// Events with a lower severity are generated but not streamed; 0 streams every
+    /// event
+    pub min_severity: f64,
+    /// Generate every event but stream only the completion summary
+    pub summary_only: bool,
+    /// Attach drop and filter counts to the completion chunk
+    pub include_diagnostics: bool,
+    /// Recompute derived metrics from the model's changes before streaming
+    pub auto_complete_metrics: bool,
+    /// Lowercased event types to keep; empty keeps every type
+    pub allowed_event_types: Vec<String>,
+    /// Model Phase 2 fell back to after the primary failed, reported in the
+    /// completion chunk
+    pub fallback_model: Option<String>,
+    /// Log every parse failure in full and stream it as a `warning` chunk
+    pub debug_parse_errors: bool,
+    /// Hold events back until the model output ends, then stream them one target
+    /// neighborhood at a time
+    pub group_by_zone: bool,
+    /// Hold events back until the model output ends, then stream them most severe
+    /// first (within each zone when also grouping)
+    pub sort_by_severity: bool,
+    /// Valid events accepted before later ones are dropped; 0 accepts every event
+    pub max_total_events: u32,
+    /// Word overlap at which an event is dropped as a near-duplicate; 0 disables it
+    pub near_duplicate_threshold: f64,
+    /// Replace event coordinates with a jittered zone centroid; see
+    /// [`SimulationConfig::authoritative_coordinates`]
+    pub authoritative_coordinates: bool,
+    /// Decimal places event coordinates are rounded to
+    pub coordinate_decimals: u32,
+    /// Seconds without model data after which the stream is abandoned; 0 waits forever
+    pub idle_timeout_secs: u64,
+    /// Valid events below which Phase 2 is retried once, if a retry is available; 0
+    /// never retries for too few events
+    ///
+    /// Only set from an explicit `minEvents`, so the default range in the prompt does
+    /// not cost a retry on every narrow policy.
+    pub min_events: u32,
+    /// Drop events that change metrics without explaining why
+    pub require_rationale: bool,
+    /// Emit a `stable` chunk for each target neighborhood without events
+    pub mark_stable_zones: bool,
+    /// Number of policies in a multi-policy request; below 2, events carry no
+    /// `drivenBy` attribution
+    pub policy_count: usize,
+    /// Template for assigned event ids; see [`SimulationConfig::event_id_template`]
+    pub event_id_template: String,
+    /// Simulation id substituted for `{request_id}` in the event id template
+    pub request_id: String,
+    /// Model that generated the stream, used to label parse telemetry
+    pub model: String,
+    /// Shared per-model parse counts this stream is added to when it ends
+    pub parse_telemetry: Option<Arc<ParseTelemetry>>,
+    /// Shared Azure circuit breaker told how this stream ended
+    pub breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            min_positivity: None,
+            max_positivity: None,
+            min_severity: 0.0,
+            summary_only: false,
+            include_diagnostics: true,
+            auto_complete_metrics: true,
+            allowed_event_types: Vec::new(),
+            fallback_model: None,
+            debug_parse_errors: false,
+            group_by_zone: false,
+            sort_by_severity: false,
+            max_total_events: SimulationConfig::default().max_total_events,
+            near_duplicate_threshold: 0.0,
+            authoritative_coordinates: false,
+            coordinate_decimals: SimulationConfig::default().coordinate_decimals,
+            idle_timeout_secs: 0,
+            min_events: 0,
+            require_rationale: false,
+            mark_stable_zones: false,
+            policy_count: 0,
+            event_id_template: SimulationConfig::default().event_id_template,
+            request_id: String::new(),
+            model: String::new(),
+            parse_telemetry: None,
+            breaker: None,
+        }
+    }
+}
+
+impl StreamOptions {
+    /// Extracts the streaming options from a simulation request
+    pub fn from_request(request: &SimulationRequest, config: &SimulationConfig) -> Self {
+        Self {
+            min_positivity: request.min_positivity,
+            max_positivity: request.max_positivity,
+            min_severity: request.min_severity.unwrap_or(config.min_severity),
+            summary_only: request.summary_only,
+            include_diagnostics: config.complete_diagnostics,
+            auto_complete_metrics: request.auto_complete_metrics,
+            allowed_event_types: request
+                .allowed_event_types
+                .iter()
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            fallback_model: None,
+            debug_parse_errors: config.debug_parse_errors,
+            group_by_zone: request.group_by_zone,
+            sort_by_severity: request.sort_by_severity,
+            max_total_events: match request.max_events {
+                Some(max) if config.max_total_events > 0 => max.min(config.max_total_events),
+                Some(max) => max,
+                None => config.max_total_events,
+            },
+            near_duplicate_threshold: config.near_duplicate_threshold,
+            authoritative_coordinates: config.authoritative_coordinates,
+            coordinate_decimals: config.coordinate_decimals,
+            idle_timeout_secs: config.phase2_idle_timeout_secs,
+            min_events: request.min_events.unwrap_or(0),
+            require_rationale: request.require_rationale,
+            mark_stable_zones: request.mark_stable_zones,
+            policy_count: request.prompts.len(),
+            event_id_template: config.event_id_template.clone(),
+            request_id: request.request_id.clone().unwrap_or_default(),
+            model: String::new(),
+            parse_telemetry: None,
+            breaker: None,
+        }
+    }
+
+    /// Options for one batch of a batched simulation
+    ///
+    /// Filters, summary mode, and ordering are cleared because the [`BatchMerger`]
+    /// applies them to the merged events itself. `min_events` is cleared too: the
+    /// minimum applies to the whole simulation, so batches never retry for it.
+    pub fn for_batch(&self) -> Self {
+        Self {
+            min_events: 0,
+            min_positivity: None,
+            max_positivity: None,
+            min_severity: 0.0,
+            summary_only: false,
+            group_by_zone: false,
+            sort_by_severity: false,
+            ..self.clone()
+        }
+    }
+
+    fn event_type_allowed(&self, event_type: &str) -> bool {
+        self.allowed_event_types.is_empty()
+            || self
+                .allowed_event_types
+                .contains(&event_type.trim().to_lowercase())
+    }
+
+    /// Whether `chunk` is sent to the client; with `summary_only`, only the
+    /// `complete` chunk is
+    pub fn streams(&self, chunk: &SimulationChunk) -> bool {
+        !self.summary_only || matches!(chunk, SimulationChunk::Complete { .. })
+    }
+
+    /// Whether events are held back until the model output ends
+    fn holds_events(&self) -> bool {
+        self.group_by_zone || self.sort_by_severity
+    }
+
+    /// Whether an event passes the positivity range and severity floor
+    fn passes_filters(&self, event: &EventNotification) -> bool {
+        self.min_positivity
+            .is_none_or(|min| event.positivity >= min)
+            && self
+                .max_positivity
+                .is_none_or(|max| event.positivity <= max)
+            && event.severity >= self.min_severity
+    }
+}
+
+/// Running state for one Phase 2 stream
+///
+/// Feed each JSON object extracted by the chunk parser to [`handle_chunk_json`],
+/// forward whatever it returns, then emit [`complete_chunk`] once the model output
+/// ends. The model's own completion summary is held back until then so it can carry
+/// the final diagnostics.
+///
+/// [`handle_chunk_json`]: Phase2State::handle_chunk_json
+/// [`complete_chunk`]: Phase2State::complete_chunk
+pub struct Phase2State {
+    full_properties: Vec<NeighborhoodProperties>,
+    neighbor_properties: Vec<NeighborhoodProperties>,
+    boundaries: ZoneBoundaries,
+    options: StreamOptions,
+    seen_events: HashSet<(String, String)>,
+    seen_wordings: Vec<HashSet<String>>,
+    model_summary: Option<String>,
+    event_lines: Vec<String>,
+    grouped_events: Vec<EventNotification>,
+    final_states: FinalStates,
+    tally: EventTally,
+    /// Valid events generated by the model, including ones hidden by filters
+    pub event_count: u32,
+    /// Titles of those events, in generation order
+    pub event_titles: Vec<String>,
+    /// JSON objects extracted from the model output
+    pub chunks_found_by_parser: u32,
+    /// Why generated output did not reach the client
+    pub diagnostics: SimulationDiagnostics,
+}
+
+impl Phase2State {
+    /// Creates the state for a stream grounded on `full_properties`
+    pub fn new(full_properties: Vec<NeighborhoodProperties>, options: StreamOptions) -> Self {
+        Self {
+            full_properties,
+            neighbor_properties: Vec::new(),
+            boundaries: ZoneBoundaries::default(),
+            options,
+            seen_events: HashSet::new(),
+            seen_wordings: Vec::new(),
+            model_summary: None,
+            event_lines: Vec::new(),
+            grouped_events: Vec::new(),
+            final_states: FinalStates::default(),
+            tally: EventTally::default(),
+            event_count: 0,
+            event_titles: Vec::new(),
+            chunks_found_by_parser: 0,
+            diagnostics: SimulationDiagnostics::default(),
+        }
+    }
+
+    /// Adds this stream's parser counts to the shared per-model telemetry, if any
+    pub fn record_parse_telemetry(&self) {
+        if let Some(telemetry) = &self.options.parse_telemetry {
+            telemetry.record(
+                &self.options.model,
+                self.chunks_found_by_parser,
+                self.diagnostics.parse_errors,
+            );
+        }
+    }
+
+    /// Reports the stream's outcome to the shared circuit breaker, if any
+    ///
+    /// A stream that ended on a read error or an idle timeout counts as an Azure
+    /// failure; any other ending counts as a success.
+    pub fn record_breaker_outcome(&self) {
+        if let Some(breaker) = &self.options.breaker {
+            match self.diagnostics.termination {
+                Some(StreamTermination::Error | StreamTermination::Timeout) => {
+                    breaker.record_failure()
+                }
+                _ => breaker.record_success(),
+            }
+        }
+    }
+
+    /// Whether the model sent its own completion summary
+    pub fn has_model_summary(&self) -> bool {
+        self.model_summary.is_some()
+    }
+
+    /// Uses `summary` in [`Phase2State::complete_chunk`] as if the model had sent it
+    pub fn set_model_summary(&mut self, summary: String) {
+        self.model_summary = Some(summary);
+    }
+
+    /// One `Title (Zone): description` line per valid event, in generation order
+    pub fn event_digest(&self) -> String {
+        self.event_lines.join("\n")
+    }
+
+    /// Whether `chunk` is sent to the client; see [`StreamOptions::streams`]
+    pub fn streams(&self, chunk: &SimulationChunk) -> bool {
+        self.options.streams(chunk)
+    }
+
+    /// Seconds without model data after which the stream is abandoned; 0 waits forever
+    pub fn idle_timeout_secs(&self) -> u64 {
+        self.options.idle_timeout_secs
+    }
+
+    /// Whether the `max_total_events` limit has been reached, so the rest of the
+    /// model output can be skipped
+    pub fn cap_reached(&self) -> bool {
+        self.options.max_total_events > 0 && self.event_count >= self.options.max_total_events
+    }
+
+    /// Valid events below which the stream should be retried for more
+    pub fn min_events(&self) -> u32 {
+        self.options.min_events
+    }
+
+    /// Uses `boundaries` to re-zone events whose zone does not match a target
+    ///
+    /// An event naming an unknown zone is moved to the target neighborhood whose
+    /// polygon contains its coordinates instead of being dropped as off-target, and
+    /// an event plotted outside its own zone is snapped to the zone's centroid.
+    pub fn with_boundaries(mut self, boundaries: ZoneBoundaries) -> Self {
+        self.boundaries = boundaries;
+        self
+    }
+
+    /// Accepts events whose metrics describe one of these non-target neighbors
+    ///
+    /// Such spillover events are completed against the neighbor's own baseline
+    /// instead of being dropped as off-target.
+    pub fn with_neighbor_properties(mut self, neighbors: Vec<NeighborhoodProperties>) -> Self {
+        self.neighbor_properties = neighbors;
+        self
+    }
+
+    /// Parses and processes one JSON object extracted from the model output
+    ///
+    /// Progress updates are owned by the server; the system prompt forbids the
+    /// model from emitting them, so any that slip through are dropped.
+    ///
+    /// # Returns
+    ///
+    /// The chunk to forward to the client, or `None` if it was invalid, filtered
+    /// out, or is a completion summary held back for [`Phase2State::complete_chunk`]
+    pub fn handle_chunk_json(&mut self, chunk_json: &str) -> Option<SimulationChunk> {
+        self.chunks_found_by_parser += 1;
+
+        match serde_json::from_str::<SimulationChunk>(chunk_json) {
+            Ok(SimulationChunk::Event { data }) => self.handle_event(data),
+            Ok(SimulationChunk::Update { .. }) => {
+                logln!("   ⚠️  Received update chunk from LLM (forbidden by prompt, skipping)");
+                None
+            }
+            Ok(
+                SimulationChunk::Targets { .. }
+                | SimulationChunk::Baseline { .. }
+                | SimulationChunk::FinalState { .. }
+                | SimulationChunk::Stable { .. }
+                | SimulationChunk::Summary { .. }
+                | SimulationChunk::Warning { .. },
+            ) => {
+                logln!("   ⚠️  Received server-owned chunk type from LLM (skipping)");
+                None
+            }
+            Ok(SimulationChunk::Complete { data }) => {
+                logln!("   ✓ Completion summary");
+                self.model_summary = Some(data.summary);
+                None
+            }
+            Err(err) => {
+                self.diagnostics.parse_errors += 1;
+                if self.options.debug_parse_errors {
+                    let message = parse_error_message(&err);
+                    logln!(
+                        "   ⚠️  Parse error #{}: {}",
+                        self.diagnostics.parse_errors,
+                        message
+                    );
+                    logln!("      Chunk: {}", chunk_json);
+                    return Some(SimulationChunk::Warning {
+                        data: SimulationWarning {
+                            message,
+                            chunk: Some(chunk_json.to_string()),
+                        },
+                    });
+                }
+                if self.diagnostics.parse_errors <= 3 {
+                    let preview = chunk_json.chars().take(100).collect::<String>();
+                    logln!(
+                        "   ⚠️  Parse error #{}: {} (skipping)",
+                        self.diagnostics.parse_errors,
+                        err
+                    );
+                    logln!("      Preview: {}", preview);
+                }
+                None
+            }
+        }
+    }
+
+    fn handle_event(&mut self, mut data: EventNotification) -> Option<SimulationChunk> {
+        match normalize_coordinates(&mut data.coordinates) {
+            CoordinateCheck::Valid => {}
+            CoordinateCheck::Swapped => {
+                logln!(
+                    "   ⚠️  Swapped transposed coordinates for event '{}' to [lat, lng]",
+                    data.title
+                );
+            }
+            CoordinateCheck::Invalid => {
+                self.diagnostics.dropped_out_of_bounds += 1;
+                logln!(
+                    "   ⚠️  Dropped event '{}': invalid coordinates {:?}",
+                    data.title,
+                    data.coordinates
+                );
+                return None;
+            }
+        }
+
+        if !self.options.event_type_allowed(&data.event_type) {
+            self.diagnostics.dropped_disallowed_type += 1;
+            logln!(
+                "   ⚠️  Dropped event '{}': type '{}' is not allowed",
+                data.title,
+                data.event_type
+            );
+            return None;
+        }
+
+        data.rationale = data
+            .rationale
+            .take()
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty());
+        if self.options.require_rationale && data.metrics.is_some() && data.rationale.is_none() {
+            self.diagnostics.dropped_missing_rationale += 1;
+            logln!(
+                "   ⚠️  Dropped event '{}': metrics changed without a rationale",
+                data.title
+            );
+            return None;
+        }
+
+        if self.options.policy_count < 2 {
+            data.driven_by.clear();
+        } else {
+            data.driven_by
+                .retain(|&policy| policy < self.options.policy_count);
+            data.driven_by.sort_unstable();
+            data.driven_by.dedup();
+        }
+
+        let baseline_zone = data.metrics.as_ref().map_or(&data.zone_id, |m| &m.zone_id);
+        if !self
+            .full_properties
+            .iter()
+            .chain(&self.neighbor_properties)
+            .any(|n| &n.name == baseline_zone)
+        {
+            self.rezone_by_coordinates(&mut data);
+        }
+
+        let baseline_zone = data.metrics.as_ref().map_or(&data.zone_id, |m| &m.zone_id);
+        let original_neighborhood = self
+            .full_properties
+            .iter()
+            .chain(&self.neighbor_properties)
+            .find(|n| &n.name == baseline_zone);
+
+        if original_neighborhood.is_none() && !self.full_properties.is_empty() {
+            self.diagnostics.dropped_off_target += 1;
+            logln!(
+                "   ⚠️  Dropped event '{}': {} is not a target neighborhood",
+                data.title,
+                baseline_zone
+            );
+            return None;
+        }
+
+        let below_threshold = original_neighborhood.is_some_and(|original| {
+            !data
+                .metrics
+                .as_ref()
+                .is_some_and(|m| has_meaningful_change(m, original))
+        });
+
+        if below_threshold {
+            self.diagnostics.dropped_sub_threshold += 1;
+            logln!(
+                "   ⚠️  Dropped event '{}' in {}: no metric change meets the minimum thresholds",
+                data.title,
+                data.zone_id
+            );
+            return None;
+        }
+
+        let dedupe_key = (
+            data.zone_id.trim().to_lowercase(),
+            data.title.trim().to_lowercase(),
+        );
+        if !self.seen_events.insert(dedupe_key) {
+            self.diagnostics.dropped_duplicate += 1;
+            logln!(
+                "   ⚠️  Dropped event '{}' in {}: duplicate of an earlier event",
+                data.title,
+                data.zone_id
+            );
+            return None;
+        }
+
+        if self.options.near_duplicate_threshold > 0.0 {
+            let wording = word_set(&format!("{} {}", data.title, data.description));
+            if self
+                .seen_wordings
+                .iter()
+                .any(|seen| word_jaccard(seen, &wording) >= self.options.near_duplicate_threshold)
+            {
+                self.diagnostics.dropped_near_duplicate += 1;
+                logln!(
+                    "   ⚠️  Dropped event '{}' in {}: near-duplicate of an earlier event",
+                    data.title,
+                    data.zone_id
+                );
+                return None;
+            }
+            self.seen_wordings.push(wording);
+        }
+
+        if !self.options.authoritative_coordinates {
+            self.snap_into_zone(&mut data);
+        }
+
+        data.spillover = original_neighborhood
+            .filter(|_| data.severity >= SPILLOVER_MIN_SEVERITY)
+            .and_then(|n| n.neighboring_neighborhoods.clone())
+            .unwrap_or_default();
+
+        if self.options.auto_complete_metrics
+            && let Some(ref mut metrics) = data.metrics
+            && let Some(original_neighborhood) = original_neighborhood
+        {
+            complete_interdependent_metrics(metrics, original_neighborhood);
+        }
+        if self.cap_reached() {
+            self.diagnostics.dropped_over_limit += 1;
+            logln!(
+                "   ⚠️  Dropped event '{}': the {}-event limit was reached",
+                data.title,
+                self.options.max_total_events
+            );
+            return None;
+        }
+
+        self.event_count += 1;
+        let assigned = render_event_id(
+            &self.options.event_id_template,
+            &self.options.request_id,
+            self.event_count,
+        );
+        assign_event_id(&mut data, assigned);
+        if self.options.authoritative_coordinates {
+            self.place_at_centroid(&mut data);
+        }
+        round_coordinates(&mut data.coordinates, self.options.coordinate_decimals);
+        self.tally.record(&data);
+        self.event_titles.push(data.title.clone());
+        self.event_lines.push(format!(
+            "{} ({}): {}",
+            data.title, data.zone_name, data.description
+        ));
+        if let Some(metrics) = &data.metrics {
+            self.tally.population_change += self.final_states.apply(
+                metrics,
+                self.full_properties.iter().chain(&self.neighbor_properties),
+            );
+        }
+
+        if !self.options.passes_filters(&data) {
+            self.diagnostics.hidden_by_filter += 1;
+            logln!(
+                "   ✓ Event #{} (hidden by positivity or severity filter)",
+                self.event_count
+            );
+            return None;
+        }
+
+        if self.options.summary_only {
+            logln!(
+                "   ✓ Event #{} (summary only, not streamed)",
+                self.event_count
+            );
+            return None;
+        }
+
+        logln!("   ✓ Event #{}", self.event_count);
+        Some(SimulationChunk::Event { data })
+    }
+
+    /// Takes a `final_state` chunk for each neighborhood that had an event, in the
+    /// order their first events arrived
+    pub fn take_final_states(&mut self) -> Vec<SimulationChunk> {
+        std::mem::take(&mut self.final_states).into_chunks()
+    }
+
+    /// Takes a `stable` chunk for each target neighborhood without any valid event,
+    /// in target order, if the options ask for them
+    ///
+    /// Events hidden by request filters still count, since their zone did change.
+    pub fn take_stable_zones(&mut self) -> Vec<SimulationChunk> {
+        if !self.options.mark_stable_zones {
+            return Vec::new();
+        }
+        self.full_properties
+            .iter()
+            .filter(|n| !self.tally.zones.contains(&n.name))
+            .map(|n| SimulationChunk::Stable {
+                data: StableZone {
+                    zone_id: n.name.clone(),
+                },
+            })
+            .collect()
+    }
+
+    /// Moves an event to the target neighborhood containing its coordinates
+    ///
+    /// Leaves the event unchanged if its coordinates fall in no target's polygon.
+    fn rezone_by_coordinates(&self, data: &mut EventNotification) {
+        let &[lat, lng] = data.coordinates.as_slice() else {
+            return;
+        };
+        let Some(zone) = self.boundaries.locate(lat, lng) else {
+            return;
+        };
+        if !self.full_properties.iter().any(|n| n.name == zone) {
+            return;
+        }
+
+        logln!(
+            "   ⚠️  Re-zoned event '{}' from {} to {} by its coordinates",
+            data.title,
+            data.zone_id,
+            zone
+        );
+        data.zone_id = zone.to_string();
+        data.zone_name = zone.to_string();
+        if let Some(metrics) = data.metrics.as_mut() {
+            metrics.zone_id = zone.to_string();
+            metrics.zone_name = zone.to_string();
+        }
+    }
+
+    /// Moves an event's coordinates to its zone's centroid when they lie outside it
+    ///
+    /// Keeps map markers inside the neighborhood the event names. Events in zones
+    /// without a known boundary are left unchanged.
+    fn snap_into_zone(&self, data: &mut EventNotification) {
+        let &[lat, lng] = data.coordinates.as_slice() else {
+            return;
+        };
+        let Some((snapped_lat, snapped_lng)) = self.boundaries.snap_into(&data.zone_id, lat, lng)
+        else {
+            return;
+        };
+
+        logln!(
+            "   ⚠️  Snapped event '{}' into {}: moved {:.0} m",
+            data.title,
+            data.zone_id,
+            distance_meters(lat, lng, snapped_lat, snapped_lng)
+        );
+        data.coordinates = vec![snapped_lat, snapped_lng];
+    }
+
+    /// Replaces an event's coordinates with its zone's centroid, jittered by event id
+    ///
+    /// Events in zones without a known boundary keep the model's coordinates.
+    fn place_at_centroid(&self, data: &mut EventNotification) {
+        if let Some((lat, lng)) = self.boundaries.jittered_centroid(&data.zone_id, &data.id) {
+            data.coordinates = vec![lat, lng];
+        }
+    }
+
+    /// Records that the model output ended early and processes any salvaged chunk
+    ///
+    /// # Arguments
+    ///
+    /// * `salvaged` - A partial chunk repaired by the parser, if one could be recovered
+    ///
+    /// # Returns
+    ///
+    /// The chunk to forward to the client, as for [`Phase2State::handle_chunk_json`]
+    pub fn handle_truncation(&mut self, salvaged: Option<String>) -> Option<SimulationChunk> {
+        self.diagnostics.truncated = true;
+        logln!("   ⚠️  Phase 2 output was cut off before the event array closed");
+
+        let chunk = self.handle_chunk_json(&salvaged?);
+        if chunk.is_some() {
+            self.diagnostics.recovered_partial = true;
+            logln!("   ✓ Recovered the partial event from the truncated output");
+        }
+        chunk
+    }
+
+    /// Builds the `baseline` chunk announcing the properties events are grounded on
+    pub fn baseline_chunk(&self) -> SimulationChunk {
+        SimulationChunk::Baseline {
+            data: SimulationBaseline {
+                neighborhoods: self.full_properties.clone(),
+            },
+        }
+    }
+
+    /// Holds an outgoing event back when events are grouped by zone or sorted by
+    /// severity
+    ///
+    /// Returns the chunk unchanged if it should be streamed now.
+    pub fn hold_for_grouping(&mut self, chunk: SimulationChunk) -> Option<SimulationChunk> {
+        match chunk {
+            SimulationChunk::Event { data } if self.options.holds_events() => {
+                self.grouped_events.push(data);
+                None
+            }
+            chunk => Some(chunk),
+        }
+    }
+
+    /// Takes the held-back events in the order given by [`order_held_events`]
+    pub fn take_grouped_events(&mut self) -> Vec<SimulationChunk> {
+        let mut events = std::mem::take(&mut self.grouped_events);
+        let targets: Vec<&str> = self
+            .full_properties
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect();
+        order_held_events(&mut events, &targets, &self.options);
+        events
+            .into_iter()
+            .map(|data| SimulationChunk::Event { data })
+            .collect()
+    }
+
+    /// Builds the final completion chunk
+    ///
+    /// Uses the model's summary if it sent one, otherwise a fallback summary built
+    /// from the event counts. Diagnostics are attached unless disabled in the options.
+    pub fn complete_chunk(&mut self) -> SimulationChunk {
+        let summary = self.model_summary.take().unwrap_or_else(|| {
+            let mut summary = format!(
+                "Simulation completed with {} events generated. {} events were skipped due to parsing errors. {} events were dropped for changing no metric meaningfully.",
+                self.event_count,
+                self.diagnostics.parse_errors,
+                self.diagnostics.dropped_sub_threshold
+            );
+            if self.diagnostics.hidden_by_filter > 0 {
+                summary.push_str(&format!(
+                    " {} events were hidden by the positivity or severity filter.",
+                    self.diagnostics.hidden_by_filter
+                ));
+            }
+            if self.diagnostics.truncated {
+                summary.push_str(" The model output was cut off, so some events may be missing.");
+            }
+            summary
+        });
+
+        let diagnostics = self
+            .options
+            .include_diagnostics
+            .then(|| self.diagnostics.clone());
+
+        SimulationChunk::Complete {
+            data: SimulationComplete {
+                summary,
+                schema_version: SCHEMA_VERSION,
+                fallback_model: self.options.fallback_model.clone(),
+                diagnostics,
+                highlights: self.tally.highlights(),
+            },
+        }
+    }
+}
+
+/// Running state of each neighborhood with an event, for its `final_state` chunk
+#[derive(Debug, Default)]
+struct FinalStates {
+    states: Vec<NeighborhoodProperties>,
+}
+
+impl FinalStates {
+    /// Applies an event's metrics to the state of the neighborhood it describes,
+    /// starting from its entry in `baselines`, and returns the population change
+    ///
+    /// Metrics for a neighborhood without a baseline are ignored.
+    fn apply<'a>(
+        &mut self,
+        metrics: &NeighborhoodMetrics,
+        mut baselines: impl Iterator<Item = &'a NeighborhoodProperties>,
+    ) -> i32 {
+        let index = match self.states.iter().position(|n| n.name == metrics.zone_id) {
+            Some(index) => index,
+            None => {
+                let Some(baseline) = baselines.find(|n| n.name == metrics.zone_id) else {
+                    return 0;
+                };
+                self.states.push(baseline.clone());
+                self.states.len() - 1
+            }
+        };
+        let state = &mut self.states[index];
+        let population_before = state.population_total;
+        apply_metric_overrides(state, metrics);
+        state.population_total - population_before
+    }
+
+    /// One `final_state` chunk per neighborhood, in the order their first events
+    /// arrived
+    fn into_chunks(self) -> Vec<SimulationChunk> {
+        self.states
+            .into_iter()
+            .map(|properties| SimulationChunk::FinalState {
+                data: NeighborhoodFinalState {
+                    zone_id: properties.name.clone(),
+                    properties,
+                },
+            })
+            .collect()
+    }
+}
+
+/// Running totals over a simulation's valid events, reported as [`SummaryHighlights`]
+#[derive(Debug, Default)]
+struct EventTally {
+    zones: Vec<String>,
+    type_counts: Vec<(String, u32)>,
+    positivity_sum: f64,
+    events: u32,
+    population_change: i32,
+}
+
+impl EventTally {
+    fn record(&mut self, event: &EventNotification) {
+        if !self.zones.contains(&event.zone_id) {
+            self.zones.push(event.zone_id.clone());
+        }
+        let event_type = event.event_type.trim().to_lowercase();
+        match self.type_counts.iter_mut().find(|(t, _)| *t == event_type) {
+            Some((_, count)) => *count += 1,
+            None => self.type_counts.push((event_type, 1)),
+        }
+        self.positivity_sum += event.positivity;
+        self.events += 1;
+    }
+
+    fn highlights(&self) -> SummaryHighlights {
+        let mut dominant: Option<&(String, u32)> = None;
+        for entry in &self.type_counts {
+            if dominant.is_none_or(|best| entry.1 > best.1) {
+                dominant = Some(entry);
+            }
+        }
+        SummaryHighlights {
+            affected_neighborhoods: self.zones.clone(),
+            net_population_change: self.population_change,
+            dominant_event_type: dominant.map(|(event_type, _)| event_type.clone()),
+            overall_positivity: (self.events > 0)
+                .then(|| self.positivity_sum / f64::from(self.events)),
+        }
+    }
+}
+
+/// Combines the streams of concurrent Phase 2 batches into one simulation stream
+///
+/// Each batch is processed by its own [`Phase2State`]; feed every chunk they emit to
+/// [`handle`](Self::handle) and forward what it returns, then emit
+/// [`finish`](Self::finish) once all batches end. Event ids are reassigned in arrival
+/// order and `MAX_TOTAL_EVENTS` is applied across batches, followed by the positivity
+/// and summary-only filters, which batches must leave off so the merged highlights
+/// cover every event. The batches' completion
+/// chunks are merged into one, and their baseline and live summary chunks are dropped
+/// since interleaved deltas from several models would be unreadable.
+///
+/// Final states and the population change are recomputed from the events the
+/// merger accepts, so events over the cross-batch limit never reach them, and the
+/// batches' own `final_state` chunks are dropped. `stable` chunks are held until
+/// the end and dropped for any zone another batch sent an event for.
+#[derive(Debug)]
+pub struct BatchMerger {
+    options: StreamOptions,
+    targets: Vec<String>,
+    baselines: Vec<NeighborhoodProperties>,
+    event_count: u32,
+    grouped_events: Vec<EventNotification>,
+    final_states: FinalStates,
+    stable_zones: Vec<SimulationChunk>,
+    summaries: Vec<String>,
+    diagnostics: SimulationDiagnostics,
+    fallback_model: Option<String>,
+    tally: EventTally,
+}
+
+impl BatchMerger {
+    /// Creates a merger for batches covering `targets`, in Phase 1 order
+    pub fn new(targets: Vec<String>, options: StreamOptions) -> Self {
+        Self {
+            options,
+            targets,
+            baselines: Vec::new(),
+            event_count: 0,
+            grouped_events: Vec::new(),
+            final_states: FinalStates::default(),
+            stable_zones: Vec::new(),
+            summaries: Vec::new(),
+            diagnostics: SimulationDiagnostics::default(),
+            fallback_model: None,
+            tally: EventTally::default(),
+        }
+    }
+
+    /// Uses `baselines` (targets and their non-target neighbors) as the starting
+    /// point of each zone's final state
+    pub fn with_baselines(mut self, baselines: Vec<NeighborhoodProperties>) -> Self {
+        self.baselines = baselines;
+        self
+    }
+
+    /// Takes one chunk from a batch, returning what should be streamed now
+    pub fn handle(&mut self, chunk: SimulationChunk) -> Option<SimulationChunk> {
+        match chunk {
+            SimulationChunk::Event { mut data } => {
+                if self.options.max_total_events > 0
+                    && self.event_count >= self.options.max_total_events
+                {
+                    self.diagnostics.dropped_over_limit += 1;
+                    return None;
+                }
+                self.event_count += 1;
+                data.id = render_event_id(
+                    &self.options.event_id_template,
+                    &self.options.request_id,
+                    self.event_count,
+                );
+                self.tally.record(&data);
+                if let Some(metrics) = &data.metrics {
+                    self.tally.population_change +=
+                        self.final_states.apply(metrics, self.baselines.iter());
+                }
+                if !self.options.passes_filters(&data) {
+                    self.diagnostics.hidden_by_filter += 1;
+                    return None;
+                }
+                if self.options.summary_only {
+                    return None;
+                }
+                if self.options.holds_events() {
+                    self.grouped_events.push(data);
+                    return None;
+                }
+                Some(SimulationChunk::Event { data })
+            }
+            SimulationChunk::Stable { .. } => {
+                self.stable_zones.push(chunk);
+                None
+            }
+            SimulationChunk::Complete { data } => {
+                self.summaries.push(data.summary);
+                if self.fallback_model.is_none() {
+                    self.fallback_model = data.fallback_model;
+                }
+                if let Some(diagnostics) = data.diagnostics {
+                    add_diagnostics(&mut self.diagnostics, &diagnostics);
+                }
+                None
+            }
+            SimulationChunk::Baseline { .. }
+            | SimulationChunk::Summary { .. }
+            | SimulationChunk::FinalState { .. } => None,
+            SimulationChunk::Update { .. }
+            | SimulationChunk::Targets { .. }
+            | SimulationChunk::Warning { .. } => Some(chunk).filter(|c| self.options.streams(c)),
+        }
+    }
+
+    /// Whether `chunk` is sent to the client; see [`StreamOptions::streams`]
+    pub fn streams(&self, chunk: &SimulationChunk) -> bool {
+        self.options.streams(chunk)
+    }
+
+    /// Returns the held events (see [`order_held_events`]), the merged final states,
+    /// the `stable` chunks of zones no batch sent an event for, and the merged
+    /// completion chunk
+    pub fn finish(mut self) -> Vec<SimulationChunk> {
+        let targets: Vec<&str> = self.targets.iter().map(String::as_str).collect();
+        order_held_events(&mut self.grouped_events, &targets, &self.options);
+
+        let complete = SimulationChunk::Complete {
+            data: SimulationComplete {
+                summary: self.summaries.join(" "),
+                schema_version: SCHEMA_VERSION,
+                fallback_model: self.fallback_model,
+                diagnostics: self.options.include_diagnostics.then_some(self.diagnostics),
+                highlights: self.tally.highlights(),
+            },
+        };
+        let zones_with_events = self.tally.zones;
+        let stable_zones = self.stable_zones.into_iter().filter(|chunk| {
+            !matches!(chunk, SimulationChunk::Stable { data } if zones_with_events.contains(&data.zone_id))
+        });
+        let options = self.options;
+        self.grouped_events
+            .into_iter()
+            .map(|data| SimulationChunk::Event { data })
+            .chain(self.final_states.into_chunks())
+            .chain(stable_zones)
+            .chain(std::iter::once(complete))
+            .filter(|chunk| options.streams(chunk))
+            .collect()
+    }
+}
+
+/// Sorts held-back events into the order they are flushed
+///
+/// With `group_by_zone`, events are grouped by zone in `targets` order; zones that
+/// are not targets (only possible when no targets were given) come last. With
+/// `sort_by_severity`, events (within each zone, when grouped) go from most to least
+/// severe, a NaN severity counting as the lowest, and equal severities are ordered by
+/// zone name. The sort is stable, so events that still tie keep their arrival order.
+pub fn order_held_events(
+    events: &mut [EventNotification],
+    targets: &[&str],
+    options: &StreamOptions,
+) {
+    let target_rank = |zone: &str| {
+        if options.group_by_zone {
+            targets
+                .iter()
+                .position(|name| *name == zone)
+                .unwrap_or(targets.len())
+        } else {
+            0
+        }
+    };
+    let severity = |event: &EventNotification| {
+        if event.severity.is_nan() {
+            f64::NEG_INFINITY
+        } else {
+            event.severity
+        }
+    };
+    events.sort_by(|a, b| {
+        let by_zone = target_rank(&a.zone_id).cmp(&target_rank(&b.zone_id));
+        if !options.sort_by_severity {
+            return by_zone;
+        }
+        by_zone
+            .then_with(|| severity(b).total_cmp(&severity(a)))
+            .then_with(|| a.zone_id.cmp(&b.zone_id))
+    });
+}
+
+/// Describes a chunk parse failure, with its position when serde reports one
+///
+/// Errors inside the tagged `data` object carry no position, so only the message
+/// is given for those.
+fn parse_error_message(err: &serde_json::Error) -> String {
+    if err.line() > 0 {
+        format!(
+            "Chunk failed to parse at line {}, column {}: {}",
+            err.line(),
+            err.column(),
+            err
+        )
+    } else {
+        format!("Chunk failed to parse: {}", err)
+    }
+}
+
+/// Adds one batch's drop and filter counts to the running totals
+///
+/// The termination reported is the first abnormal one, since an abnormal end in
+/// any batch is worth more than another batch's `[DONE]`.
+fn add_diagnostics(total: &mut SimulationDiagnostics, batch: &SimulationDiagnostics) {
+    total.parse_errors += batch.parse_errors;
+    total.dropped_off_target += batch.dropped_off_target;
+    total.dropped_sub_threshold += batch.dropped_sub_threshold;
+    total.dropped_duplicate += batch.dropped_duplicate;
+    total.dropped_near_duplicate += batch.dropped_near_duplicate;
+    total.dropped_out_of_bounds += batch.dropped_out_of_bounds;
+    total.dropped_disallowed_type += batch.dropped_disallowed_type;
+    total.dropped_over_limit += batch.dropped_over_limit;
+    total.dropped_missing_rationale += batch.dropped_missing_rationale;
+    total.hidden_by_filter += batch.hidden_by_filter;
+    total.truncated |= batch.truncated;
+    total.recovered_partial |= batch.recovered_partial;
+    if batch.termination.is_some()
+        && total
+            .termination
+            .is_none_or(|termination| termination == StreamTermination::Done)
+    {
+        total.termination = batch.termination;
+    }
+}
+
+/// Lowercased alphanumeric words of `text`
+fn word_set(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Jaccard similarity of two word sets: shared words over all distinct words
+fn word_jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Fills an event id template with the simulation id and the event's sequence number
+///
+/// `{request_id}` and `{n}` are replaced; any other text is kept as is.
+pub fn render_event_id(template: &str, request_id: &str, seq: u32) -> String {
+    template
+        .replace("{request_id}", request_id)
+        .replace("{n}", &seq.to_string())
+}
+
+/// Replaces the model's event id with the stream-unique `assigned` id
+///
+/// Models sometimes restart their counter and repeat ids, which breaks client-side
+/// keying. The model's id is kept in `source_id` when it differs.
+fn assign_event_id(event: &mut EventNotification, assigned: String) {
+    if !event.id.is_empty() && event.id != assigned {
+        event.source_id = Some(std::mem::replace(&mut event.id, assigned));
+    } else {
+        event.id = assigned;
+    }
+}
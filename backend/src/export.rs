@@ -0,0 +1,163 @@
+//! Simulation Exports
+//!
+//! This module converts stored simulation results into formats for external tools,
+//! such as CSV for spreadsheets and GeoJSON for mapping tools like QGIS or Mapbox.
+
+use crate::types::EventNotification;
+use actix_web::web::Bytes;
+use futures_util::Stream;
+use serde_json::{Value, json};
+
+/// Event columns that precede the flattened metrics in the CSV export
+const CSV_EVENT_COLUMNS: &[&str] = &[
+    "id",
+    "zone",
+    "type",
+    "title",
+    "severity",
+    "positivity",
+    "lat",
+    "lng",
+];
+
+/// Every metric an event can change, flattened with dotted paths
+///
+/// Events only carry the metrics they change, so the export always emits this
+/// full superset and leaves unchanged metrics empty. This keeps the columns stable
+/// across neighborhoods and runs.
+pub const CSV_METRIC_COLUMNS: &[&str] = &[
+    "population_total",
+    "median_age",
+    "population_density",
+    "median_income",
+    "median_home_value",
+    "affordability_index",
+    "housing_units",
+    "households",
+    "vacant_units",
+    "vacancy_rate",
+    "owner_occupancy",
+    "housing_density",
+    "education_distribution.high_school_or_less",
+    "education_distribution.some_college",
+    "education_distribution.bachelors",
+    "education_distribution.graduate",
+    "race_distribution.white",
+    "race_distribution.black",
+    "race_distribution.asian",
+    "race_distribution.mixed",
+    "race_distribution.hispanic",
+    "diversity_index",
+    "livability_index",
+    "commute.avg_minutes",
+    "commute.car_dependence",
+    "commute.transit_usage",
+    "derived.higher_ed_percent",
+    "derived.density_index",
+];
+
+/// Returns the CSV header row fields
+pub fn csv_header() -> Vec<String> {
+    CSV_EVENT_COLUMNS
+        .iter()
+        .chain(CSV_METRIC_COLUMNS)
+        .map(|column| column.to_string())
+        .collect()
+}
+
+/// Returns the CSV fields for one event, aligned with [`csv_header`]
+pub fn event_csv_record(event: &EventNotification) -> Vec<String> {
+    let coordinate = |index: usize| {
+        event
+            .coordinates
+            .get(index)
+            .map(|value| value.to_string())
+            .unwrap_or_default()
+    };
+
+    let mut record = vec![
+        event.id.clone(),
+        event.zone_id.clone(),
+        event.event_type.clone(),
+        event.title.clone(),
+        event.severity.to_string(),
+        event.positivity.to_string(),
+        coordinate(0),
+        coordinate(1),
+    ];
+
+    let metrics = event
+        .metrics
+        .as_ref()
+        .and_then(|m| serde_json::to_value(m).ok())
+        .unwrap_or(Value::Null);
+
+    record.extend(CSV_METRIC_COLUMNS.iter().map(|column| {
+        let value = column
+            .split('.')
+            .try_fold(&metrics, |value, key| value.get(key));
+        match value {
+            Some(Value::Number(number)) => number.to_string(),
+            Some(Value::String(text)) => text.clone(),
+            _ => String::new(),
+        }
+    }));
+
+    record
+}
+
+/// Encodes one CSV row, quoting fields as needed
+fn encode_row(fields: &[String]) -> Result<Bytes, std::io::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(fields).map_err(std::io::Error::other)?;
+    writer
+        .into_inner()
+        .map(Bytes::from)
+        .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+/// Streams events as CSV, one row per stream item, starting with the header
+pub fn events_csv_stream(
+    events: Vec<EventNotification>,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+    let header = std::iter::once(csv_header());
+    let rows = events.into_iter().map(|event| event_csv_record(&event));
+
+    futures_util::stream::iter(header.chain(rows).map(|fields| encode_row(&fields)))
+}
+
+/// Builds a GeoJSON FeatureCollection with one Point feature per event
+///
+/// Event `coordinates` are `[lat, lng]` as used by the app's map, whereas GeoJSON
+/// positions are `[lng, lat]`, so the pair is swapped here. Events without a full
+/// coordinate pair are omitted.
+pub fn events_geojson(events: &[EventNotification]) -> Value {
+    let features: Vec<Value> = events
+        .iter()
+        .filter_map(|event| {
+            let [lat, lng] = event.coordinates.get(..2)? else {
+                return None;
+            };
+            Some(json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [lng, lat],
+                },
+                "properties": {
+                    "id": event.id,
+                    "zone": event.zone_id,
+                    "title": event.title,
+                    "type": event.event_type,
+                    "severity": event.severity,
+                    "positivity": event.positivity,
+                },
+            }))
+        })
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
@@ -0,0 +1,323 @@
+//! Geographic Helpers
+//!
+//! This module defines the city's bounding box and the coordinate checks built on
+//! it, so every feature that validates locations shares one definition. It also
+//! parses neighborhood boundary polygons for point-in-polygon lookups.
+//!
+//! Event coordinates are `[latitude, longitude]` throughout the app. GeoJSON uses
+//! `[longitude, latitude]`; conversions happen only at the GeoJSON boundary.
+
+use crate::utils::stable_hash;
+use serde_json::Value;
+use std::f64::consts::TAU;
+
+/// Closest a jittered event marker is placed to its zone's centroid, in meters
+const MIN_JITTER_METERS: f64 = 25.0;
+
+/// Farthest a jittered event marker is placed from its zone's centroid, in meters
+const MAX_JITTER_METERS: f64 = 125.0;
+
+/// Meters per degree of latitude
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// A latitude/longitude bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lng: f64,
+    pub max_lng: f64,
+}
+
+impl Bounds {
+    /// Whether the point lies inside the box, edges included
+    pub fn contains(&self, lat: f64, lng: f64) -> bool {
+        (self.min_lat..=self.max_lat).contains(&lat) && (self.min_lng..=self.max_lng).contains(&lng)
+    }
+}
+
+/// Bounding box of the City of Atlanta's neighborhoods
+pub const ATLANTA_BOUNDS: Bounds = Bounds {
+    min_lat: 33.5,
+    max_lat: 34.1,
+    min_lng: -84.7,
+    max_lng: -84.1,
+};
+
+/// Whether a point lies within [`ATLANTA_BOUNDS`]
+pub fn in_bounds(lat: f64, lng: f64) -> bool {
+    ATLANTA_BOUNDS.contains(lat, lng)
+}
+
+/// Whether a pair is a valid latitude/longitude anywhere on Earth
+fn is_valid_lat_lng(lat: f64, lng: f64) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lng)
+}
+
+/// Outcome of validating an event's coordinate pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinateCheck {
+    /// The pair is a valid `[lat, lng]`
+    Valid,
+    /// The pair was `[lng, lat]` and has been swapped in place
+    Swapped,
+    /// The pair is missing, malformed, or outside valid latitude/longitude ranges
+    Invalid,
+}
+
+/// Validates event coordinates against the `[lat, lng]` convention
+///
+/// Models sometimes emit the GeoJSON order instead, so a pair is swapped in place
+/// when it only makes sense transposed: either it lands in the city once swapped,
+/// or its first value is not a valid latitude while the swapped pair is valid.
+///
+/// # Returns
+///
+/// Whether the pair was already valid, was swapped, or could not be salvaged
+pub fn normalize_coordinates(coordinates: &mut [f64]) -> CoordinateCheck {
+    let &mut [lat, lng] = coordinates else {
+        return CoordinateCheck::Invalid;
+    };
+
+    if !lat.is_finite() || !lng.is_finite() {
+        return CoordinateCheck::Invalid;
+    }
+
+    if in_bounds(lat, lng) {
+        return CoordinateCheck::Valid;
+    }
+
+    if in_bounds(lng, lat) || (!is_valid_lat_lng(lat, lng) && is_valid_lat_lng(lng, lat)) {
+        coordinates.swap(0, 1);
+        return CoordinateCheck::Swapped;
+    }
+
+    if is_valid_lat_lng(lat, lng) {
+        CoordinateCheck::Valid
+    } else {
+        CoordinateCheck::Invalid
+    }
+}
+
+/// A boundary polygon, stored in GeoJSON `[lng, lat]` order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Polygon {
+    /// The outer ring followed by any holes
+    pub rings: Vec<Vec<[f64; 2]>>,
+}
+
+impl Polygon {
+    /// Whether the point lies inside the outer ring and outside every hole
+    pub fn contains(&self, lat: f64, lng: f64) -> bool {
+        let Some((outer, holes)) = self.rings.split_first() else {
+            return false;
+        };
+        ring_contains(outer, lat, lng) && !holes.iter().any(|hole| ring_contains(hole, lat, lng))
+    }
+
+    /// Area of the outer ring in square degrees
+    pub fn area(&self) -> f64 {
+        let Some(outer) = self.rings.first() else {
+            return 0.0;
+        };
+        let twice_area: f64 = outer
+            .iter()
+            .enumerate()
+            .map(|(i, [x1, y1])| {
+                let [x2, y2] = outer[(i + 1) % outer.len()];
+                x1 * y2 - x2 * y1
+            })
+            .sum();
+        twice_area.abs() / 2.0
+    }
+
+    /// Area-weighted centroid of the outer ring as `(lat, lng)`
+    ///
+    /// Falls back to the average vertex for degenerate rings with no area.
+    pub fn centroid(&self) -> Option<(f64, f64)> {
+        let outer = self.rings.first().filter(|ring| !ring.is_empty())?;
+        let (mut area, mut x, mut y) = (0.0, 0.0, 0.0);
+        for (i, [x1, y1]) in outer.iter().enumerate() {
+            let [x2, y2] = outer[(i + 1) % outer.len()];
+            let cross = x1 * y2 - x2 * y1;
+            area += cross;
+            x += (x1 + x2) * cross;
+            y += (y1 + y2) * cross;
+        }
+
+        if area.abs() < f64::EPSILON {
+            let n = outer.len() as f64;
+            let lng = outer.iter().map(|[x, _]| x).sum::<f64>() / n;
+            let lat = outer.iter().map(|[_, y]| y).sum::<f64>() / n;
+            return Some((lat, lng));
+        }
+        Some((y / (3.0 * area), x / (3.0 * area)))
+    }
+}
+
+/// Rounds a `[lat, lng]` pair to `decimals` places
+///
+/// A point inside [`ATLANTA_BOUNDS`] stays inside it: a value rounded past an edge
+/// is clamped back onto the edge. Pairs of any other length are left unchanged.
+pub fn round_coordinates(coordinates: &mut [f64], decimals: u32) {
+    let &mut [lat, lng] = coordinates else {
+        return;
+    };
+    let scale = 10f64.powi(decimals.min(15) as i32);
+    let round = |value: f64| (value * scale).round() / scale;
+    let (mut rounded_lat, mut rounded_lng) = (round(lat), round(lng));
+    if in_bounds(lat, lng) {
+        rounded_lat = rounded_lat.clamp(ATLANTA_BOUNDS.min_lat, ATLANTA_BOUNDS.max_lat);
+        rounded_lng = rounded_lng.clamp(ATLANTA_BOUNDS.min_lng, ATLANTA_BOUNDS.max_lng);
+    }
+    coordinates.copy_from_slice(&[rounded_lat, rounded_lng]);
+}
+
+/// Great-circle distance between two points, in meters
+pub fn distance_meters(lat1: f64, lng1: f64, lat2: f64, lng2: f64) -> f64 {
+    const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+    let (d_lat, d_lng) = ((lat2 - lat1).to_radians(), (lng2 - lng1).to_radians());
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lng / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+}
+
+/// Even-odd ray casting test for a single closed ring
+fn ring_contains(ring: &[[f64; 2]], lat: f64, lng: f64) -> bool {
+    let mut inside = false;
+    let mut previous = match ring.last() {
+        Some(point) => point,
+        None => return false,
+    };
+    for point in ring {
+        let ([x1, y1], [x2, y2]) = (previous, point);
+        if (y1 > &lat) != (y2 > &lat) && lng < (x2 - x1) * (lat - y1) / (y2 - y1) + x1 {
+            inside = !inside;
+        }
+        previous = point;
+    }
+    inside
+}
+
+/// Parses a GeoJSON `Polygon` or `MultiPolygon` geometry
+///
+/// # Returns
+///
+/// The geometry's polygons, or an empty list for other or malformed geometry types
+pub fn parse_geometry(geometry: &Value) -> Vec<Polygon> {
+    let coordinates = geometry.get("coordinates");
+    match geometry.get("type").and_then(|t| t.as_str()) {
+        Some("Polygon") => coordinates.map(parse_polygon).into_iter().collect(),
+        Some("MultiPolygon") => coordinates
+            .and_then(|c| c.as_array())
+            .map(|polygons| polygons.iter().map(parse_polygon).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_polygon(rings: &Value) -> Polygon {
+    let rings = rings.as_array().map(Vec::as_slice).unwrap_or_default();
+    Polygon {
+        rings: rings
+            .iter()
+            .map(|ring| {
+                ring.as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|point| Some([point.get(0)?.as_f64()?, point.get(1)?.as_f64()?]))
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+/// Named neighborhood boundaries for reverse geocoding
+#[derive(Debug, Clone, Default)]
+pub struct ZoneBoundaries {
+    zones: Vec<(String, Vec<Polygon>)>,
+}
+
+impl ZoneBoundaries {
+    pub fn new(zones: Vec<(String, Vec<Polygon>)>) -> Self {
+        Self { zones }
+    }
+
+    /// Returns the boundaries of only the named zones
+    pub fn only(&self, names: &[String]) -> Self {
+        Self::new(
+            self.zones
+                .iter()
+                .filter(|(name, _)| names.contains(name))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Returns the name of the neighborhood containing the point, if any
+    pub fn locate(&self, lat: f64, lng: f64) -> Option<&str> {
+        self.zones
+            .iter()
+            .find(|(_, polygons)| polygons.iter().any(|p| p.contains(lat, lng)))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Moves a point that lies outside `zone` to the zone's centroid
+    ///
+    /// For multi-part zones the nearest part's centroid is used.
+    ///
+    /// # Returns
+    ///
+    /// The snapped `(lat, lng)`, or `None` if the point is already inside the zone
+    /// or the zone has no known boundary
+    pub fn snap_into(&self, zone: &str, lat: f64, lng: f64) -> Option<(f64, f64)> {
+        let (_, polygons) = self.zones.iter().find(|(name, _)| name == zone)?;
+        if polygons.iter().any(|p| p.contains(lat, lng)) {
+            return None;
+        }
+        polygons
+            .iter()
+            .filter_map(Polygon::centroid)
+            .min_by(|a, b| {
+                distance_meters(lat, lng, a.0, a.1).total_cmp(&distance_meters(lat, lng, b.0, b.1))
+            })
+    }
+
+    /// Places a marker near `zone`'s centroid, offset by a jitter derived from `seed`
+    ///
+    /// The centroid is that of the zone's largest part. The same seed always gives
+    /// the same point, and different seeds spread markers 25-125 m around the
+    /// centroid so events in one zone do not overlap. The offset shrinks if it would
+    /// leave the zone.
+    ///
+    /// # Returns
+    ///
+    /// The `(lat, lng)` to plot, or `None` if the zone has no known boundary
+    pub fn jittered_centroid(&self, zone: &str, seed: &str) -> Option<(f64, f64)> {
+        let (_, polygons) = self.zones.iter().find(|(name, _)| name == zone)?;
+        let (lat, lng) = polygons
+            .iter()
+            .max_by(|a, b| a.area().total_cmp(&b.area()))?
+            .centroid()?;
+
+        let hash = stable_hash(seed);
+        let angle = (hash & 0xffff) as f64 / 65_536.0 * TAU;
+        let spread = ((hash >> 16) & 0xffff) as f64 / 65_535.0;
+        let mut radius = MIN_JITTER_METERS + spread * (MAX_JITTER_METERS - MIN_JITTER_METERS);
+
+        while radius >= 1.0 {
+            let jittered_lat = lat + radius * angle.cos() / METERS_PER_DEGREE;
+            let jittered_lng =
+                lng + radius * angle.sin() / (METERS_PER_DEGREE * lat.to_radians().cos());
+            if polygons
+                .iter()
+                .any(|p| p.contains(jittered_lat, jittered_lng))
+            {
+                return Some((jittered_lat, jittered_lng));
+            }
+            radius /= 2.0;
+        }
+        Some((lat, lng))
+    }
+}
@@ -4,9 +4,152 @@
 //! Handlers receive requests, call the appropriate business logic, and return responses.
 
 use crate::azure;
+use crate::breaker::CircuitBreaker;
+use crate::cache::Phase1Cache;
+use crate::config::SimulationConfig;
+use crate::constituents::{self, EventRequest, NamedPersonaRequest};
+use crate::diff::{self, NeighborhoodDiffRequest};
+use crate::embeddings::{self, EmbedRequest, EmbeddingCache};
+use crate::error::SimulationError;
+use crate::export;
+use crate::limiter::{self, SimulationLimiter};
+use crate::logging;
 use crate::neighborhoods::NeighborhoodDatabase;
-use crate::types::SimulationRequest;
-use actix_web::{HttpResponse, Result, web};
+use crate::query;
+use crate::sse;
+use crate::store::SimulationStore;
+use crate::telemetry::ParseTelemetry;
+use crate::types::{MetricsFormat, PromptProfile, SCHEMA_VERSION, SimulationRequest};
+use crate::utils;
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::http::{StatusCode, header};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, ResponseError, Result, web};
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Maps domain errors to HTTP responses at the handler boundary
+///
+/// Invalid request values are reported as a 400, unknown simulation ids and
+/// persona names as a 404, and a full simulation limit or an open Azure circuit as
+/// a 503 with `Retry-After`; every other simulation failure is a 500. The error message is returned as a
+/// plain-text body.
+impl ResponseError for SimulationError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            SimulationError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            SimulationError::Unauthorized => StatusCode::UNAUTHORIZED,
+            SimulationError::SimulationNotFound(_) | SimulationError::PersonaNotFound(_) => {
+                StatusCode::NOT_FOUND
+            }
+            SimulationError::TooManySimulations | SimulationError::AzureUnavailable { .. } => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        match self {
+            SimulationError::TooManySimulations => {
+                response
+                    .insert_header((header::RETRY_AFTER, limiter::RETRY_AFTER_SECS.to_string()));
+            }
+            SimulationError::AzureUnavailable { retry_after_secs } => {
+                response.insert_header((header::RETRY_AFTER, retry_after_secs.to_string()));
+            }
+            _ => {}
+        }
+        response
+            .content_type("text/plain; charset=utf-8")
+            .body(self.to_string())
+    }
+}
+
+/// Structured error body for request bodies that could not be parsed
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub error: ErrorDetail,
+}
+
+/// What was wrong with a request body, and where
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    /// Machine-readable kind: `invalid_json`, `unsupported_content_type`, or
+    /// `payload_too_large`
+    pub code: String,
+    pub message: String,
+    /// 1-based line of a JSON syntax or type error
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub line: Option<usize>,
+    /// 1-based column of a JSON syntax or type error
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub column: Option<usize>,
+}
+
+/// JSON body extractor settings shared by every endpoint
+///
+/// Replaces Actix's terse plain-text rejection with an [`ErrorBody`], e.g.
+/// `{ "error": { "code": "invalid_json", "message": "...", "line": 1, "column": 12 } }`
+/// for malformed or truncated JSON. The status stays Actix's own: 400 for bad JSON,
+/// 413 for oversized bodies, and 415 for a non-JSON content type.
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _request| {
+        let (code, location) = match &err {
+            JsonPayloadError::Deserialize(e) => ("invalid_json", Some((e.line(), e.column()))),
+            JsonPayloadError::ContentType => ("unsupported_content_type", None),
+            JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => {
+                ("payload_too_large", None)
+            }
+            _ => ("invalid_json", None),
+        };
+        let body = ErrorBody {
+            error: ErrorDetail {
+                code: code.to_string(),
+                message: err.to_string(),
+                line: location.map(|(line, _)| line),
+                column: location.map(|(_, column)| column),
+            },
+        };
+        let response = HttpResponse::build(err.status_code()).json(body);
+        InternalError::from_response(err, response).into()
+    })
+}
+
+/// Query parameters accepted by the simulate endpoint
+///
+/// These override the matching fields of the request body when present.
+#[derive(Debug, Deserialize)]
+pub struct SimulateQuery {
+    #[serde(rename = "minPositivity")]
+    pub min_positivity: Option<f64>,
+    #[serde(rename = "maxPositivity")]
+    pub max_positivity: Option<f64>,
+    #[serde(rename = "minSeverity")]
+    pub min_severity: Option<f64>,
+    #[serde(rename = "metricsFormat", default)]
+    pub metrics_format: MetricsFormat,
+}
+
+/// Query parameters accepted by the resume endpoint
+#[derive(Debug, Deserialize)]
+pub struct ResumeQuery {
+    #[serde(rename = "metricsFormat", default)]
+    pub metrics_format: MetricsFormat,
+}
+
+/// Applies the requested `metricsFormat` to a stream of stored simulation frames
+fn format_frames(
+    frames: impl Stream<Item = Result<Bytes, std::io::Error>>,
+    format: MetricsFormat,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+    frames.map(move |frame| match format {
+        MetricsFormat::Nested => frame,
+        MetricsFormat::Flat => frame.map(sse::flatten_frame_metrics),
+    })
+}
 
 /// Simulates the impact of a city policy proposal using a two-phase approach
 ///
@@ -18,18 +161,77 @@ use actix_web::{HttpResponse, Result, web};
 /// ## Request
 ///
 /// The request includes:
-/// - `prompt`: The policy proposal text (e.g., "Build a new light rail line")
+/// - `prompt`: The policy proposal text (e.g., "Build a new light rail line"); 400 if
+///   blank or shorter than 10 characters
+/// - `prompts`: Several policies enacted together, instead of `prompt`; they are
+///   combined into one numbered proposal and each event lists the policies behind it
+///   in `drivenBy`
 /// - `selectedZones`: Optional list of specific neighborhood names to focus on
+/// - `strictZones`: If true, only neighborhoods in `selectedZones` receive events
 /// - `neighborhoodContext`: Minimal context (name + contextual fields) for Phase 1
-/// - `neighborhoodProperties`: Full properties for Phase 2 lookup
+/// - `neighborhoodProperties`: Full properties for Phase 2 lookup; required (400 if
+///   empty) while the neighborhood database is unavailable
+/// - `summaryOnly`: Stream only the `complete` chunk (events are still generated, so
+///   this does not save tokens)
+/// - `baselineOverrides`: Optional map of neighborhood name to partial metrics, applied
+///   to the baseline before Phase 2 for "what-if" simulations (400 if out of range)
+/// - `presencePenalty` / `frequencyPenalty`: Optional Phase 2 repetition penalties,
+///   clamped to -2.0..=2.0
+/// - `allowedEventTypes`: Optional list of event types to generate; other types are
+///   dropped
+/// - `promptProfile`: `atlanta` (default) for the Atlanta-grounded prompts, or
+///   `generic` for a city-agnostic planner naming `CITY_NAME`
+/// - `autoCompleteMetrics`: If false, stream the model's metrics without recomputing
+///   derived values (default true)
+/// - `minEvents` / `maxEvents`: Event count stated in the prompt (default 3 to 13).
+///   With `minEvents` set, Phase 2 is retried once if too few arrive; events past
+///   `maxEvents` (capped at `MAX_TOTAL_EVENTS`) are dropped
+///
+/// ## Query Parameters
+///
+/// - `minPositivity` / `maxPositivity`: Only stream events whose positivity falls in
+///   this range (e.g. `maxPositivity=0` for a risks-only view). Hidden events are still
+///   generated and counted in the fallback summary.
+/// - `minSeverity`: Only stream events at least this severe (e.g. `minSeverity=0.3`
+///   to hide minor impacts); defaults to `MIN_SEVERITY`, 0 unless configured. Combines
+///   with the positivity range.
+/// - `metricsFormat`: `nested` (the default) or `flat`, which writes each event's
+///   `metrics` as one level of dotted paths, e.g.
+///   `{"zoneId": "Downtown", "population_total": 4800, "derived.density_index": 12.5}`
 ///
 /// ## Response
 ///
 /// Returns a Server-Sent Events (SSE) stream of simulation chunks:
+/// - `update`: The expected number of events, sent after Phase 1
+/// - `targets`: The neighborhoods Phase 1 selected, split into those loaded from the
+///   request, from the database, and missing
+/// - `baseline`: The full properties Phase 2 was grounded on, before any event
 /// - `event`: Individual events that occur in affected neighborhoods (transportation,
 ///   housing, economic, etc.). Each event includes optional partial metrics updates
 ///   showing how the neighborhood changes as a result of the event.
-/// - `complete`: Final summary of the simulation results
+/// - `summary`: Pieces of the model's summary as it is written, for live display
+/// - `warning`: With `DEBUG_PARSE_ERRORS` set, each model chunk that failed to parse,
+///   with the serde error and the chunk verbatim; also sent first while the
+///   neighborhood database is unavailable
+/// - `final_state`: Each changed neighborhood's properties after all of its events
+/// - `stable`: With `markStableZones`, each target neighborhood that produced no events
+/// - `complete`: Final summary of the simulation results, with structured highlights
+///   (`affected_neighborhoods`, `net_population_change`, `dominant_event_type`,
+///   `overall_positivity`), a `diagnostics` object
+///   counting events dropped as off-target, sub-threshold, duplicate, near-duplicate, out of bounds, or
+///   past `MAX_TOTAL_EVENTS`, hidden by filters, and chunks that failed to parse, and `fallback_model` when
+///   `FALLBACK_MODEL` generated the events because the primary model failed
+///
+/// The `X-Simulation-Id` header identifies the run for later retrieval, e.g.
+/// `GET /api/simulate/{id}/events.csv`. `X-Sim-Schema-Version` gives the chunk
+/// format version, also reported as `schema_version` in the `complete` chunk. Each
+/// frame has an SSE `id`; after a dropped connection, `GET /api/simulate/{id}/stream`
+/// with `Last-Event-ID` resumes the stream, since generation continues without the
+/// client.
+///
+/// Returns 503 with a `Retry-After` header when `MAX_CONCURRENT_SIMULATIONS`
+/// streams are already running, or while the Azure circuit breaker is open after
+/// `AZURE_BREAKER_FAILURES` upstream failures.
 ///
 /// ## Example
 ///
@@ -38,11 +240,31 @@ use actix_web::{HttpResponse, Result, web};
 ///   -H "Content-Type: application/json" \
 ///   -d '{"prompt": "Build light rail connecting downtown to midtown", "selectedZones": ["Downtown", "Midtown"]}'
 /// ```
+#[allow(clippy::too_many_arguments)]
 pub async fn simulate_policy(
     body: web::Json<SimulationRequest>,
+    query: web::Query<SimulateQuery>,
     db: web::Data<NeighborhoodDatabase>,
+    config: web::Data<SimulationConfig>,
+    phase1_cache: web::Data<Phase1Cache>,
+    store: web::Data<SimulationStore>,
+    limiter: web::Data<SimulationLimiter>,
+    breaker: web::Data<CircuitBreaker>,
+    parse_telemetry: web::Data<ParseTelemetry>,
 ) -> Result<HttpResponse> {
-    let request = body.into_inner();
+    breaker.check()?;
+    let permit = limiter.try_acquire()?;
+    let mut request = body.into_inner();
+    request.min_positivity = query.min_positivity.or(request.min_positivity);
+    request.max_positivity = query.max_positivity.or(request.max_positivity);
+    request.min_severity = query.min_severity.or(request.min_severity);
+    let metrics_format = query.metrics_format;
+
+    let policy = if request.prompts.is_empty() {
+        request.prompt.clone()
+    } else {
+        utils::combine_policy_prompts(&request.prompts)
+    };
 
     let zones_text = if request.selected_zones.is_empty() {
         "All".to_string()
@@ -50,25 +272,507 @@ pub async fn simulate_policy(
         format!("{} zones", request.selected_zones.len())
     };
 
-    eprintln!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    eprintln!("📥 Simulation Request");
-    eprintln!("   Policy: {}", request.prompt);
-    eprintln!("   Selected Zones: {}", zones_text);
-    eprintln!(
+    logln!("\n{}", logging::rule());
+    logln!("📥 Simulation Request");
+    logln!("   Policy: {}", policy);
+    logln!("   Selected Zones: {}", zones_text);
+    logln!(
         "   Context: {} neighborhoods",
         request.neighborhood_context.len()
     );
-    eprintln!(
+    logln!(
         "   Properties: {} neighborhoods",
         request.neighborhood_properties.len()
     );
-    eprintln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    logln!("{}", logging::rule());
 
-    let stream = azure::generate_simulation(request, std::sync::Arc::new(db.get_ref().clone())).await?;
+    let simulation_id = store.create(&policy);
+    request.request_id = Some(simulation_id.clone());
+    logln!("   Simulation ID: {}", simulation_id);
 
-    Ok(HttpResponse::Ok()
+    let breaker = breaker.into_inner();
+    let stream = azure::generate_simulation(
+        request,
+        std::sync::Arc::new(db.get_ref().clone()),
+        config.into_inner(),
+        phase1_cache.into_inner(),
+        parse_telemetry.into_inner(),
+        breaker.clone(),
+    )
+    .await;
+    let stream = match stream {
+        Ok(stream) => stream,
+        Err(e) => {
+            if matches!(e, SimulationError::Upstream(_)) {
+                breaker.record_failure();
+            }
+            store.finish(&simulation_id);
+            return Err(e.into());
+        }
+    };
+
+    let store = store.into_inner();
+    let task_store = store.clone();
+    let recorded_id = simulation_id.clone();
+    actix_web::rt::spawn(async move {
+        let stream = permit.hold(stream);
+        futures_util::pin_mut!(stream);
+        while let Some(item) = stream.next().await {
+            match item {
+                Ok(bytes) => task_store.record_frame(&recorded_id, &bytes),
+                Err(e) => {
+                    logln!("   ✗ Simulation stream failed: {}", e);
+                    breaker.record_failure();
+                    break;
+                }
+            }
+        }
+        task_store.finish(&recorded_id);
+    });
+
+    let stream = store
+        .subscribe(&simulation_id, 0)
+        .ok_or_else(|| SimulationError::SimulationNotFound(simulation_id.clone()))?;
+    Ok(sse_response(&simulation_id).streaming(format_frames(stream, metrics_format)))
+}
+
+/// Starts an SSE response with the headers shared by live and resumed streams
+///
+/// Compression buffers output, which would delay SSE frames, so an explicit
+/// `identity` encoding makes the `Compress` middleware leave the stream alone.
+fn sse_response(simulation_id: &str) -> actix_web::HttpResponseBuilder {
+    let mut response = HttpResponse::Ok();
+    response
         .content_type("text/event-stream")
         .append_header(("Cache-Control", "no-cache"))
         .append_header(("Connection", "keep-alive"))
-        .streaming(stream))
+        .append_header(("Content-Encoding", "identity"))
+        .append_header(("X-Simulation-Id", simulation_id.to_string()))
+        .append_header(("X-Sim-Schema-Version", SCHEMA_VERSION.to_string()));
+    response
+}
+
+/// Resumes a simulation's SSE stream after a dropped connection
+///
+/// Every frame of the simulate stream carries an SSE `id` counting up from 1. Send
+/// the last one received as the `Last-Event-ID` header (as `EventSource` does when it
+/// reconnects) to replay only the later frames; omit it to replay from the start.
+/// The stream then follows the simulation live if it is still generating.
+///
+/// ## Response
+///
+/// The same SSE stream as `POST /api/simulate`, in the `metricsFormat` given as a
+/// query parameter. Returns 404 if no simulation with the given id has been run
+/// since startup, and 400 for a non-numeric `Last-Event-ID`.
+pub async fn resume_simulation(
+    path: web::Path<String>,
+    query: web::Query<ResumeQuery>,
+    http_request: HttpRequest,
+    store: web::Data<SimulationStore>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let after = match http_request.headers().get("Last-Event-ID") {
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .ok_or_else(|| {
+                SimulationError::InvalidRequest("Last-Event-ID must be a number".to_string())
+            })?,
+        None => 0,
+    };
+
+    let stream = store
+        .into_inner()
+        .subscribe(&id, after)
+        .ok_or_else(|| SimulationError::SimulationNotFound(id.clone()))?;
+    Ok(sse_response(&id).streaming(format_frames(stream, query.metrics_format)))
+}
+
+/// Exports the events of a stored simulation as CSV
+///
+/// ## Response
+///
+/// A streamed `text/csv` body with one row per event. Columns are the event `id`,
+/// `zone`, `type`, `title`, `severity`, `positivity`, `lat`, `lng`, followed by every
+/// metric an event can change (nested metrics use dotted names such as
+/// `commute.avg_minutes`). Metrics an event did not change are left empty.
+///
+/// Returns 404 if no simulation with the given id has been run since startup.
+pub async fn export_events_csv(
+    path: web::Path<String>,
+    store: web::Data<SimulationStore>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let simulation = store
+        .get(&id)
+        .ok_or_else(|| SimulationError::SimulationNotFound(id.clone()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv")
+        .append_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"simulation-{}-events.csv\"", id),
+        ))
+        .streaming(export::events_csv_stream(simulation.events)))
+}
+
+/// Generates constituent responses to a city event
+///
+/// Selects the personas whose embeddings are most similar to the event and
+/// returns a short in-character message from each. Two personas respond to a
+/// low-severity event, scaling up to `MAX_MESSAGE_PERSONAS` at severity 1.0.
+///
+/// ## Request
+///
+/// - `title`, `description`, `zone`: The event being reacted to (400 if the title or
+///   description is blank)
+/// - `positivity`, `severity`: Event scores passed through to the persona prompt
+/// - `exclusions`: Optional persona names to skip
+/// - `minSimilarity`: Optional minimum persona similarity (default
+///   `MIN_PERSONA_SIMILARITY`); weaker matches are skipped even if they rank highly
+/// - `history`: Optional prior `{ "role", "content" }` turns (`user`/`assistant`) for a
+///   follow-up in an ongoing conversation; the most recent 10 are used
+/// - `maxSentences`: Optional reply length in sentences, up to 10 (default 2-3; 400
+///   if 0)
+/// - `tone`: Optional `angry`, `supportive`, or `neutral` tone layered over each
+///   persona
+/// - `customPersona`: Optional inline `{ "name", "agent_prompt" }` persona that
+///   responds alone, skipping similarity selection (400 if either field is blank or
+///   too long)
+///
+/// ## Response
+///
+/// A JSON array of `{ "name", "message" }` objects. The array is empty when no
+/// persona matches the event strongly enough.
+pub async fn handle_messages(
+    event: web::Json<EventRequest>,
+    config: web::Data<SimulationConfig>,
+) -> Result<HttpResponse> {
+    let responses = constituents::generate_constituent_messages(&event, &config).await?;
+
+    Ok(HttpResponse::Ok().json(responses))
+}
+
+/// Predicts the city-wide mood toward an event across every persona
+///
+/// Cheaper than `/api/messages`: the event is embedded once and each persona's
+/// stance is predicted from the event's positivity and severity, weighted by the
+/// persona's similarity to it, without generating any text.
+///
+/// ## Request
+///
+/// The same event body as `/api/messages`; `exclusions` are skipped, and the
+/// message-only fields are ignored.
+///
+/// ## Response
+///
+/// `{ "supportive", "neutral", "opposed", "meanStance", "personas" }`, where the three
+/// percentages sum to 100 and `meanStance` runs from -1 (opposed) to 1 (supportive).
+pub async fn handle_sentiment(
+    event: web::Json<EventRequest>,
+    config: web::Data<SimulationConfig>,
+) -> Result<HttpResponse> {
+    let distribution = constituents::public_sentiment(&event, &config).await?;
+
+    Ok(HttpResponse::Ok().json(distribution))
+}
+
+/// Generates a response to a city event from one chosen persona
+///
+/// Unlike `/api/messages`, the persona is picked by the caller rather than by
+/// similarity, for "ask this resident" style interactions.
+///
+/// ## Request
+///
+/// - `persona`: Persona name (case-insensitive)
+/// - `event`: The event being reacted to, as for `/api/messages`
+///
+/// ## Response
+///
+/// A single `{ "name", "message" }` object, or 404 if the persona is unknown.
+pub async fn handle_persona_message(
+    request: web::Json<NamedPersonaRequest>,
+    config: web::Data<SimulationConfig>,
+) -> Result<HttpResponse> {
+    let response =
+        constituents::generate_named_persona_message(&request.persona, &request.event, &config)
+            .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Returns the pairwise cosine similarity of the personas' embeddings
+///
+/// For tuning the persona set: pairs with a high similarity tend to be selected
+/// together and may be redundant.
+///
+/// ## Response
+///
+/// A JSON object keyed by persona name whose values map every persona name to its
+/// similarity, e.g. `{ "Derek Chen": { "Derek Chen": 1.0, "Imani Rodriguez": 0.82 } }`.
+pub async fn persona_similarity() -> Result<HttpResponse> {
+    let matrix = constituents::persona_similarity().await?;
+
+    Ok(HttpResponse::Ok().json(matrix))
+}
+
+/// Checks a candidate `personas.json` before it is deployed
+///
+/// Nothing is loaded or changed; the running persona set is unaffected.
+///
+/// ## Request
+///
+/// The persona JSON array, exactly as it would be saved to `personas.json`.
+///
+/// ## Response
+///
+/// A [`PersonaValidation`](constituents::PersonaValidation) listing each persona with a
+/// missing name or prompt, missing or wrongly sized embeddings, or a duplicate name.
+/// The status is 200 even when issues are found; a body that is not a JSON array of
+/// objects gets the usual 400.
+pub async fn validate_personas(
+    entries: web::Json<Vec<constituents::PersonaEntry>>,
+    config: web::Data<SimulationConfig>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(constituents::validate_personas(
+        &entries,
+        &config.azure_embedding_model,
+    ))
+}
+
+/// Exports the events of a stored simulation as a GeoJSON FeatureCollection
+///
+/// ## Response
+///
+/// An `application/geo+json` body with one Point feature per event, positioned at
+/// the event's coordinates in GeoJSON `[lng, lat]` order. Feature properties carry
+/// the event `id`, `zone`, `title`, `type`, `severity`, and `positivity`.
+///
+/// Returns 404 if no simulation with the given id has been run since startup.
+pub async fn export_events_geojson(
+    path: web::Path<String>,
+    store: web::Data<SimulationStore>,
+) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let simulation = store
+        .get(&id)
+        .ok_or(SimulationError::SimulationNotFound(id))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/geo+json")
+        .json(export::events_geojson(&simulation.events)))
+}
+
+/// Compares two snapshots of neighborhood properties
+///
+/// ## Request
+///
+/// - `baseline`: Neighborhood properties before the change
+/// - `modified`: Neighborhood properties after the change, matched to the baseline by
+///   `name`
+///
+/// ## Response
+///
+/// A JSON array with one `{ "name", "changes" }` entry per changed neighborhood.
+/// Each change is `{ "field", "baseline", "modified", "delta", "percentChange" }`,
+/// with nested metrics named by dotted path (e.g. `commute.avg_minutes`). Unchanged
+/// fields are omitted, and derived metrics are recomputed when their inputs change.
+pub async fn diff_neighborhoods(
+    request: web::Json<NeighborhoodDiffRequest>,
+) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(diff::diff_neighborhoods(
+        &request.baseline,
+        &request.modified,
+    )))
+}
+
+/// Lists the neighborhoods whose metrics fall within the given ranges
+///
+/// ## Query Parameters
+///
+/// Any number of `<field>_min` / `<field>_max` bounds (inclusive) over the numeric
+/// neighborhood properties, with dotted paths for nested ones, e.g.
+/// `GET /api/neighborhoods/query?median_income_max=40000&vacancy_rate_min=15` or
+/// `commute.avg_minutes_min=30`. Without parameters every neighborhood matches.
+///
+/// ## Response
+///
+/// A JSON array of the matching neighborhoods' full properties, sorted by name.
+/// Returns 400 for an unknown field, a parameter without a `_min`/`_max` suffix, or a
+/// non-numeric bound.
+pub async fn query_neighborhoods(
+    params: web::Query<HashMap<String, String>>,
+    db: web::Data<NeighborhoodDatabase>,
+) -> Result<HttpResponse> {
+    let filters = query::parse_range_filters(&params)?;
+
+    Ok(HttpResponse::Ok().json(query::filter_neighborhoods(db.all(), &filters)))
+}
+
+/// Returns city-wide aggregates over the neighborhood database
+///
+/// ## Response
+///
+/// A JSON object with `neighborhoods`, `totalPopulation`, `meanIncome` (weighted by
+/// households), `medianIncome` (the median neighborhood), `totalHousingUnits`,
+/// `vacancyRate` (percent of all units), `averageLivability` (weighted by
+/// population), and `neighborhoodsByNpu`. It is computed when the neighborhoods are
+/// loaded, so the figures are all 0 while the database is degraded.
+pub async fn neighborhood_stats(db: web::Data<NeighborhoodDatabase>) -> HttpResponse {
+    HttpResponse::Ok().json(db.stats())
+}
+
+/// Server health reported by `GET /api/health`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthStatus {
+    /// `ok`, or `degraded` when no neighborhoods are loaded
+    pub status: String,
+    /// Whether simulations must supply `neighborhoodProperties` because the
+    /// neighborhood database failed to load
+    pub degraded: bool,
+    /// Neighborhoods loaded from GeoJSON
+    pub neighborhoods: usize,
+}
+
+/// Reports whether the server is fully operational
+///
+/// ## Response
+///
+/// A [`HealthStatus`]. The server still answers while degraded, so monitoring should
+/// check `degraded` rather than the status code.
+pub async fn health(db: web::Data<NeighborhoodDatabase>) -> HttpResponse {
+    let degraded = db.is_degraded();
+    HttpResponse::Ok().json(HealthStatus {
+        status: if degraded { "degraded" } else { "ok" }.to_string(),
+        degraded,
+        neighborhoods: db.count(),
+    })
+}
+
+/// Reports the rolling Phase 2 parse-error rate of each model
+///
+/// ## Response
+///
+/// A JSON object keyed by model name, each value a
+/// `{ "streams", "chunks", "parseErrors", "errorRate" }` summary of that model's most
+/// recent 100 Phase 2 streams since the server started. A fallback model is counted
+/// under its own name.
+pub async fn parse_telemetry(telemetry: web::Data<ParseTelemetry>) -> HttpResponse {
+    HttpResponse::Ok().json(telemetry.snapshot())
+}
+
+/// Query parameters accepted by the prompts endpoint
+#[derive(Debug, Deserialize)]
+pub struct PromptsQuery {
+    /// Profile to render the prompts with (default `atlanta`)
+    #[serde(default)]
+    pub profile: PromptProfile,
+}
+
+/// Returns the Phase 1 and Phase 2 system prompts for inspection
+///
+/// ## Query Parameters
+///
+/// - `profile`: `atlanta` (default) or `generic`, as in the simulate request's
+///   `promptProfile`
+///
+/// ## Response
+///
+/// `{ "phase1_system": "...", "phase2_system": "..." }`, rendered against a fictional
+/// sample neighborhood in place of the real context. Returns 404 when
+/// `EXPOSE_PROMPTS` is disabled.
+pub async fn get_prompts(
+    query: web::Query<PromptsQuery>,
+    config: web::Data<SimulationConfig>,
+) -> HttpResponse {
+    if !config.expose_prompts {
+        return HttpResponse::NotFound().finish();
+    }
+
+    HttpResponse::Ok().json(azure::sample_system_prompts(
+        query.profile,
+        &config.city_name,
+    ))
+}
+
+/// Returns embedding vectors for arbitrary texts
+///
+/// Requires an `Authorization: Bearer <API_TOKEN>` header since every uncached text
+/// costs Azure tokens.
+///
+/// ## Request
+///
+/// - `texts`: 1 to [`embeddings::MAX_EMBED_TEXTS`] non-empty strings
+///
+/// ## Response
+///
+/// `{ "embeddings": [[...], ...], "cached": <n> }`, one vector per text in request
+/// order, where `cached` counts those served from `EMBEDDING_CACHE_DIR`. Returns 404
+/// when `API_TOKEN` is unset and 401 when the token is missing or wrong.
+pub async fn embed(
+    http_request: HttpRequest,
+    request: web::Json<EmbedRequest>,
+    config: web::Data<SimulationConfig>,
+    cache: web::Data<EmbeddingCache>,
+) -> Result<HttpResponse> {
+    let Some(token) = &config.api_token else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let provided = http_request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !provided.is_some_and(|provided| tokens_match(provided.trim(), token)) {
+        return Err(SimulationError::Unauthorized.into());
+    }
+
+    let response = embeddings::embed_texts(&request, &config, &cache).await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Compares tokens in time independent of where they first differ
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Registers every API route relative to the scope it is mounted under
+///
+/// `main.rs` mounts the routes under both `/api/v1` and the unversioned `/api`
+/// alias, so handlers must not assume either prefix. `/api/v1` must be registered
+/// first, since the `/api` scope's prefix would otherwise claim its requests.
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/simulate", web::post().to(simulate_policy))
+        .route("/simulate/{id}/stream", web::get().to(resume_simulation))
+        .route(
+            "/simulate/{id}/events.csv",
+            web::get().to(export_events_csv),
+        )
+        .route(
+            "/simulate/{id}/events.geojson",
+            web::get().to(export_events_geojson),
+        )
+        .route("/messages", web::post().to(handle_messages))
+        .route("/messages/persona", web::post().to(handle_persona_message))
+        .route("/messages/sentiment", web::post().to(handle_sentiment))
+        .route("/personas/similarity", web::get().to(persona_similarity))
+        .service(
+            web::resource("/personas/validate")
+                .app_data(json_config().limit(constituents::MAX_PERSONAS_BYTES as usize))
+                .route(web::post().to(validate_personas)),
+        )
+        .route("/neighborhoods/diff", web::post().to(diff_neighborhoods))
+        .route("/neighborhoods/query", web::get().to(query_neighborhoods))
+        .route("/neighborhoods/stats", web::get().to(neighborhood_stats))
+        .route("/prompts", web::get().to(get_prompts))
+        .route("/embed", web::post().to(embed))
+        .route("/health", web::get().to(health))
+        .route("/telemetry/parse-errors", web::get().to(parse_telemetry));
 }
@@ -0,0 +1,79 @@
+//! City Simulation Library
+//!
+//! This crate provides an AI-powered city policy simulation service. It receives policy
+//! proposals and uses Azure AI to generate realistic simulation results showing the
+//! impact of those policies on neighborhoods, zones, and city-wide metrics.
+//!
+//! The simulation logic is usable without the HTTP server: call [`generate_simulation`]
+//! to stream simulation chunks, or [`generate_constituent_messages`] to hear from the
+//! personas most relevant to an event. The `backend` binary is a thin Actix-web wrapper
+//! around these functions.
+//!
+//! ## Architecture
+//!
+//! - `handlers.rs`: HTTP request handlers for API endpoints
+//! - `geo.rs`: City bounding box and coordinate validation
+//! - `azure.rs`: Azure AI integration for generating simulations
+//! - `breaker.rs`: Circuit breaker that fast-fails simulations during Azure outages
+//! - `cache.rs`: LRU cache of Phase 1 target neighborhoods
+//! - `config.rs`: Operator-tunable settings loaded from the environment
+//! - `constituents.rs`: Persona matching and constituent message generation
+//! - `diff.rs`: Field-level diffs between two neighborhood snapshots
+//! - `embeddings.rs`: Cached text embeddings exposed as a reusable primitive
+//! - `error.rs`: Domain error type shared by the library API
+//! - `events.rs`: Validation and filtering of events parsed from Phase 2
+//! - `export.rs`: CSV and GeoJSON exports of stored simulation events
+//! - `limiter.rs`: Cap on concurrently running simulation streams
+//! - `logging.rs`: ASCII-safe rendering of server log output; declared first so
+//!   `logln!` is in scope in every other module
+//! - `metrics.rs`: Pure formulas linking interdependent neighborhood metrics
+//! - `neighborhoods.rs`: Neighborhood data loaded from GeoJSON
+//! - `prompt_log.rs`: Optional on-disk log of each phase's request and response
+//! - `query.rs`: Neighborhood filtering by metric ranges
+//! - `sse.rs`: Formatting and consuming the SSE simulation stream
+//! - `stats.rs`: City-wide aggregates over the neighborhood database
+//! - `store.rs`: In-memory store of simulation results for later retrieval
+//! - `telemetry.rs`: Rolling Phase 2 parse-error counts per model
+//! - `types.rs`: Data structures for requests, responses, and city data
+//! - `utils.rs`: Context builders, metric completion, and stream parsing
+
+#[macro_use]
+pub mod logging;
+
+pub mod azure;
+pub mod breaker;
+pub mod cache;
+pub mod config;
+pub mod constituents;
+pub mod diff;
+pub mod embeddings;
+pub mod error;
+pub mod events;
+pub mod export;
+pub mod geo;
+pub mod handlers;
+pub mod limiter;
+pub mod metrics;
+pub mod neighborhoods;
+pub mod prompt_log;
+pub mod query;
+pub mod sse;
+pub mod stats;
+pub mod store;
+pub mod telemetry;
+pub mod types;
+pub mod utils;
+
+pub use azure::generate_simulation;
+pub use cache::Phase1Cache;
+pub use config::SimulationConfig;
+pub use constituents::{generate_constituent_messages, load_personas, rank_personas};
+pub use error::SimulationError;
+pub use neighborhoods::NeighborhoodDatabase;
+pub use sse::collect_chunks;
+pub use store::SimulationStore;
+pub use utils::{
+    ContextPrecision, build_minimal_context, build_minimal_context_with_fields,
+    build_minimal_context_with_hints, build_neighborhoods_context,
+    build_neighborhoods_context_with_precision,
+};
@@ -0,0 +1,68 @@
+//! Simulation Concurrency Limit
+//!
+//! Each active simulation stream holds an upstream Azure connection for its whole
+//! duration. This module caps how many run at once so a burst of clients cannot
+//! exhaust file descriptors or the Azure quota.
+
+use crate::config::SimulationConfig;
+use crate::error::SimulationError;
+use futures_util::{Stream, StreamExt};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Seconds a rejected client is asked to wait before retrying
+pub const RETRY_AFTER_SECS: u64 = 5;
+
+/// Global limit on concurrently running simulations
+pub struct SimulationLimiter {
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+/// A reserved simulation slot, released when dropped
+pub struct SimulationPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl SimulationLimiter {
+    /// Creates a limiter allowing `max` concurrent simulations (0 means unlimited)
+    pub fn new(max: usize) -> Self {
+        Self {
+            semaphore: (max > 0).then(|| Arc::new(Semaphore::new(max))),
+        }
+    }
+
+    pub fn from_config(config: &SimulationConfig) -> Self {
+        Self::new(config.max_concurrent_simulations)
+    }
+
+    /// Reserves a slot for a new simulation without waiting
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SimulationError::TooManySimulations`] if every slot is in use
+    pub fn try_acquire(&self) -> Result<SimulationPermit, SimulationError> {
+        let permit = match &self.semaphore {
+            Some(semaphore) => Some(
+                semaphore
+                    .clone()
+                    .try_acquire_owned()
+                    .map_err(|_| SimulationError::TooManySimulations)?,
+            ),
+            None => None,
+        };
+        Ok(SimulationPermit { _permit: permit })
+    }
+}
+
+impl SimulationPermit {
+    /// Holds the permit until `stream` finishes or is dropped
+    ///
+    /// The simulate handler drives the stream in a background task, so the slot stays
+    /// taken until generation ends even if the client disconnects.
+    pub fn hold<S: Stream>(self, stream: S) -> impl Stream<Item = S::Item> {
+        stream.map(move |item| {
+            let _ = &self;
+            item
+        })
+    }
+}
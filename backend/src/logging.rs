@@ -0,0 +1,91 @@
+//! Log Rendering
+//!
+//! Server logs use box-drawing banners and emoji status markers, which some terminals
+//! and log aggregators render as mojibake. Setting `ASCII_LOGS=1` makes [`logln!`]
+//! replace them with plain ASCII equivalents so logs stay readable everywhere.
+//!
+//! [`logln!`]: crate::logln
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+/// Width of the banner's interior, in characters
+const BANNER_WIDTH: usize = 60;
+
+/// Width of the rules between log sections, in characters
+const RULE_WIDTH: usize = 58;
+
+/// Invisible variation selector that follows emoji such as ⚠️
+const EMOJI_VARIATION_SELECTOR: char = '\u{fe0f}';
+
+/// Writes a line to stderr, converted to ASCII when `ASCII_LOGS` is enabled
+///
+/// Takes the same arguments as `eprintln!`.
+#[macro_export]
+macro_rules! logln {
+    () => {
+        eprintln!()
+    };
+    ($($arg:tt)*) => {
+        eprintln!("{}", $crate::logging::render(&format!($($arg)*)))
+    };
+}
+
+/// Whether logs are converted to ASCII, read once from `ASCII_LOGS` (default false)
+pub fn ascii_logs() -> bool {
+    static ASCII_LOGS: OnceLock<bool> = OnceLock::new();
+    *ASCII_LOGS.get_or_init(|| crate::config::env_flag("ASCII_LOGS", false))
+}
+
+/// Returns `text` as it should be logged under the current `ASCII_LOGS` setting
+pub fn render(text: &str) -> Cow<'_, str> {
+    if ascii_logs() {
+        to_ascii(text)
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Replaces box-drawing characters and emoji with ASCII equivalents
+///
+/// Characters without an equivalent become `?`, so the result is always ASCII.
+pub fn to_ascii(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+
+    let mut ascii = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            c if c.is_ascii() => ascii.push(c),
+            EMOJI_VARIATION_SELECTOR => {}
+            '━' | '─' => ascii.push('-'),
+            '═' => ascii.push('='),
+            '║' | '│' => ascii.push('|'),
+            '╔' | '╗' | '╚' | '╝' => ascii.push('+'),
+            '→' => ascii.push_str("->"),
+            '✓' | '✅' => ascii.push_str("[ok]"),
+            '✗' => ascii.push_str("[x]"),
+            '⚠' => ascii.push_str("[!]"),
+            '🚀' | '📡' | '📊' | '📝' | '🔄' | '🔍' | '📋' | '📥' | '🔑' | '🧬' | '✂' => {
+                ascii.push('*')
+            }
+            _ => ascii.push('?'),
+        }
+    }
+    Cow::Owned(ascii)
+}
+
+/// Boxed startup banner around `title`, as three lines
+pub fn banner(title: &str) -> String {
+    format!(
+        "╔{border}╗\n║   {title:<width$}║\n╚{border}╝",
+        border = "═".repeat(BANNER_WIDTH),
+        width = BANNER_WIDTH - 3,
+    )
+}
+
+/// Horizontal rule separating sections of the log
+pub fn rule() -> String {
+    "━".repeat(RULE_WIDTH)
+}
@@ -1,28 +1,48 @@
 //! City Simulation Backend API
 //!
-//! This backend provides an AI-powered city policy simulation service. It receives policy
-//! proposals from the frontend and uses Azure AI to generate realistic simulation results
-//! showing the impact of those policies on neighborhoods, zones, and city-wide metrics.
-//!
-//! ## Architecture
-//!
-//! - `handlers.rs`: HTTP request handlers for API endpoints
-//! - `azure.rs`: Azure AI integration for generating simulations
-//! - `types.rs`: Data structures for requests, responses, and city data
+//! This binary serves the simulation library over HTTP. It receives policy
+//! proposals from the frontend and streams back the results of the AI-powered
+//! simulation; see the library crate docs for the architecture.
 //!
 //! ## API Endpoints
 //!
 //! - `POST /api/simulate`: Streams simulation results for a given policy proposal
-
-mod azure;
-mod constituents;
-mod handlers;
-mod neighborhoods;
-mod types;
-mod utils;
+//! - `GET /api/simulate/{id}/stream`: Resumes a simulation stream after `Last-Event-ID`
+//! - `GET /api/simulate/{id}/events.csv`: Exports a finished simulation's events as CSV
+//! - `GET /api/simulate/{id}/events.geojson`: Exports the events as GeoJSON points
+//! - `POST /api/messages`: Generates constituent responses to an event
+//! - `POST /api/messages/persona`: Generates a response from one named persona
+//! - `POST /api/messages/sentiment`: Predicts the mood toward an event across all personas
+//! - `GET /api/personas/similarity`: Returns the pairwise similarity of persona embeddings
+//! - `POST /api/personas/validate`: Checks a candidate `personas.json` for problems
+//! - `POST /api/neighborhoods/diff`: Compares two neighborhood property snapshots
+//! - `GET /api/neighborhoods/query`: Lists neighborhoods within metric ranges
+//! - `GET /api/neighborhoods/stats`: Returns city-wide population, income, and housing totals
+//! - `GET /api/prompts`: Returns the system prompts rendered against a sample context
+//! - `GET /api/health`: Reports whether the neighborhood database loaded
+//! - `GET /api/telemetry/parse-errors`: Reports the rolling Phase 2 parse-error rate per model
+//! - `POST /api/embed`: Returns embedding vectors for arbitrary texts (needs `API_TOKEN`)
+//!
+//! Every endpoint is served under `/api/v1`, e.g. `POST /api/v1/simulate`. The
+//! unversioned `/api` paths above are an alias for v1 so existing clients keep
+//! working; breaking changes will be introduced under `/api/v2`.
+//!
+//! Every endpoint except the `POST /api/simulate` SSE stream is gzip/brotli
+//! compressed for clients that send `Accept-Encoding` (disable with
+//! `RESPONSE_COMPRESSION=false`).
 
 use actix_cors::Cors;
+use actix_web::middleware::{Compress, Condition};
 use actix_web::{App, HttpServer, web};
+use backend::breaker::CircuitBreaker;
+use backend::embeddings::EmbeddingCache;
+use backend::limiter::SimulationLimiter;
+use backend::logging;
+use backend::logln;
+use backend::telemetry::ParseTelemetry;
+use backend::{
+    Phase1Cache, SimulationConfig, SimulationStore, constituents, handlers, neighborhoods,
+};
 use std::path::PathBuf;
 
 /// Loads environment variables from .env files
@@ -56,47 +76,101 @@ fn load_env() {
 async fn main() -> std::io::Result<()> {
     load_env();
 
-    eprintln!("\n╔════════════════════════════════════════════════════════════╗");
-    eprintln!("║   City Simulation Backend API                              ║");
-    eprintln!("╚════════════════════════════════════════════════════════════╝");
-    eprintln!();
-    eprintln!("🚀 Server starting on http://localhost:8080");
-    eprintln!();
-    eprintln!("📡 Available endpoints:");
-    eprintln!("   POST /api/simulate - Simulate city policy impacts");
-    eprintln!("   POST /api/messages  - Generate constituent responses to events");
-    eprintln!();
-    eprintln!("🔑 Environment check:");
+    logln!("\n{}", logging::banner("City Simulation Backend API"));
+    logln!();
+    logln!("🚀 Server starting on http://localhost:8080");
+    logln!();
+    logln!("📡 Available endpoints:");
+    logln!("   POST /api/simulate - Simulate city policy impacts");
+    logln!("   GET  /api/simulate/{{id}}/stream - Resume a dropped simulation stream");
+    logln!("   GET  /api/simulate/{{id}}/events.csv - Export simulation events");
+    logln!("   GET  /api/simulate/{{id}}/events.geojson - Export events for mapping tools");
+    logln!("   POST /api/messages  - Generate constituent responses to events");
+    logln!("   POST /api/messages/persona - Hear from one named constituent");
+    logln!("   POST /api/messages/sentiment - Gauge public sentiment toward an event");
+    logln!("   GET  /api/personas/similarity - Compare persona embeddings");
+    logln!("   POST /api/personas/validate - Check a personas.json before deploying it");
+    logln!("   POST /api/neighborhoods/diff - Compare two neighborhood snapshots");
+    logln!("   GET  /api/neighborhoods/query - Find neighborhoods by metric ranges");
+    logln!("   GET  /api/neighborhoods/stats - City-wide neighborhood aggregates");
+    logln!("   GET  /api/prompts - Inspect the system prompts");
+    logln!("   GET  /api/health - Check server and neighborhood data status");
+    logln!("   GET  /api/telemetry/parse-errors - Compare Phase 2 parse errors by model");
+    logln!("   POST /api/embed - Embed texts (requires API_TOKEN)");
+    logln!();
+    logln!("🔑 Environment check:");
     match std::env::var("AZURE_API_KEY") {
-        Ok(_) => eprintln!("   ✓ AZURE_API_KEY is set"),
-        Err(_) => eprintln!("   ✗ AZURE_API_KEY is NOT set (required for AI features)"),
+        Ok(_) => logln!("   ✓ AZURE_API_KEY is set"),
+        Err(_) => logln!("   ✗ AZURE_API_KEY is NOT set (required for AI features)"),
     }
-    eprintln!();
-    eprintln!("📊 Loading neighborhood database...");
-    let neighborhood_db = neighborhoods::NeighborhoodDatabase::new();
+    logln!();
+    let config = SimulationConfig::from_env();
+    logln!("📊 Loading neighborhood database...");
+    let neighborhood_db =
+        neighborhoods::NeighborhoodDatabase::load(config.neighborhoods_url.as_deref()).await;
     match &neighborhood_db {
-        Ok(db) => eprintln!("   ✓ Loaded {} neighborhoods from GeoJSON", db.count()),
-        Err(e) => eprintln!("   ⚠️  Warning: {}", e),
+        Ok(db) => logln!(
+            "   ✓ Loaded {} neighborhoods from {}",
+            db.count(),
+            config.neighborhoods_url.as_deref().unwrap_or("GeoJSON")
+        ),
+        Err(e) => {
+            logln!("   ⚠️  Warning: {}", e);
+            logln!("   ⚠️  Degraded mode: simulations must supply neighborhoodProperties");
+        }
+    }
+    logln!();
+    logln!("🧬 Checking persona embeddings...");
+    match constituents::load_personas() {
+        Ok(personas) => {
+            if let Err(e) = constituents::check_persona_embedding_dimensions(
+                &personas,
+                &config.azure_embedding_model,
+            ) {
+                logln!("   ✗ {}", e);
+                return Err(std::io::Error::other(e.to_string()));
+            }
+            logln!(
+                "   ✓ {} personas match {}",
+                personas.len(),
+                config.azure_embedding_model
+            );
+        }
+        Err(e) => logln!("   ⚠️  Warning: {}", e),
     }
-    eprintln!();
-    eprintln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    eprintln!("Waiting for requests...\n");
+    logln!();
+    logln!("{}", logging::rule());
+    logln!("Waiting for requests...\n");
 
     let neighborhood_db = neighborhood_db.unwrap_or_default();
 
     let db = std::sync::Arc::new(neighborhood_db);
+    let compress = config.response_compression;
+    let phase1_cache = web::Data::new(Phase1Cache::from_config(&config));
+    let limiter = web::Data::new(SimulationLimiter::from_config(&config));
+    let breaker = web::Data::new(CircuitBreaker::from_config(&config));
+    let parse_telemetry = web::Data::new(ParseTelemetry::new());
+    let embedding_cache = web::Data::new(EmbeddingCache::from_config(&config));
+    let config = web::Data::new(config);
+    let store = web::Data::new(SimulationStore::from_config(&config));
     HttpServer::new(move || {
         let cors = Cors::permissive();
         let db = db.clone();
 
         App::new()
+            .app_data(handlers::json_config())
             .app_data(web::Data::from(db.clone()))
+            .app_data(config.clone())
+            .app_data(phase1_cache.clone())
+            .app_data(store.clone())
+            .app_data(limiter.clone())
+            .app_data(breaker.clone())
+            .app_data(parse_telemetry.clone())
+            .app_data(embedding_cache.clone())
+            .wrap(Condition::new(compress, Compress::default()))
             .wrap(cors)
-            .service(
-                web::scope("/api")
-                    .route("/simulate", web::post().to(handlers::simulate_policy))
-                    .route("/messages", web::post().to(constituents::handle_messages)),
-            )
+            .service(web::scope("/api/v1").configure(handlers::configure_routes))
+            .service(web::scope("/api").configure(handlers::configure_routes))
     })
     .bind(("127.0.0.1", 8080))?
     .run()
@@ -0,0 +1,47 @@
+//! Metric Formulas
+//!
+//! This module holds the formulas linking interdependent neighborhood metrics as
+//! small pure functions, independent of how the metrics are stored. Event-time
+//! metric completion uses these so every caller shares one definition.
+
+use crate::types::{EducationDistribution, RaceDistribution};
+
+/// Percentage of residents with a bachelor's or graduate degree
+pub fn higher_ed(education: &EducationDistribution) -> f64 {
+    education.bachelors + education.graduate
+}
+
+/// Diversity index of a race distribution given in percentages
+///
+/// Computed as the Gini-Simpson index `1 - Σp²` over the group shares, so 0 means a
+/// single group and values approach 1 as groups become more evenly mixed.
+pub fn diversity(race: &RaceDistribution) -> f64 {
+    1.0 - [
+        race.white,
+        race.black,
+        race.asian,
+        race.mixed,
+        race.hispanic,
+    ]
+    .iter()
+    .map(|&p| (p / 100.0).powi(2))
+    .sum::<f64>()
+}
+
+/// Residents per acre
+///
+/// Returns 0 if the area is not positive.
+pub fn density(population: f64, acres: f64) -> f64 {
+    if acres > 0.0 { population / acres } else { 0.0 }
+}
+
+/// Percentage of housing units that are vacant
+///
+/// Returns 0 if there are no housing units.
+pub fn vacancy(vacant_units: f64, housing_units: f64) -> f64 {
+    if housing_units > 0.0 {
+        vacant_units / housing_units * 100.0
+    } else {
+        0.0
+    }
+}
@@ -2,15 +2,38 @@
 //!
 //! This module handles loading and searching neighborhood data from the GeoJSON file.
 //! The data is loaded once on server startup and kept in memory for fast lookups.
+//!
+//! The GeoJSON is read from `data/neighborhoods.geojson` by default, fetched from
+//! `NEIGHBORHOODS_URL` when that is set, or taken from a copy compiled into the
+//! binary when built with the `embedded-neighborhoods` feature and no file is found.
 
+use crate::geo::{Polygon, ZoneBoundaries, parse_geometry};
+use crate::stats::{CityStats, city_stats};
 use crate::types::NeighborhoodProperties;
+use crate::utils::read_to_string_bounded;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Largest neighborhoods GeoJSON file that will be loaded
+pub const MAX_GEOJSON_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Longest wait to connect to `NEIGHBORHOODS_URL`
+pub const GEOJSON_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest the whole `NEIGHBORHOODS_URL` download may take
+pub const GEOJSON_FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Neighborhoods GeoJSON compiled into the binary, used when no file is found
+#[cfg(feature = "embedded-neighborhoods")]
+const EMBEDDED_GEOJSON: &[u8] = include_bytes!("../data/neighborhoods.geojson");
 
 #[derive(Clone)]
 pub struct NeighborhoodDatabase {
     neighborhoods: Arc<HashMap<String, NeighborhoodProperties>>,
+    boundaries: Arc<HashMap<String, Vec<Polygon>>>,
+    stats: Arc<CityStats>,
 }
 
 impl NeighborhoodDatabase {
@@ -23,28 +46,96 @@ impl NeighborhoodDatabase {
         } else if alt_path.exists() {
             alt_path
         } else {
-            return Err("neighborhoods.geojson not found".into());
+            return Self::embedded();
         };
 
-        let content = std::fs::read_to_string(path)?;
-        let geojson: Value = serde_json::from_str(&content)?;
+        let content = read_to_string_bounded(path, MAX_GEOJSON_BYTES)?;
+        Self::from_geojson(&content)
+    }
+
+    /// Fetches the GeoJSON from `url` and loads it
+    ///
+    /// Responses larger than `MAX_GEOJSON_BYTES`, or taking longer than
+    /// `GEOJSON_FETCH_TIMEOUT`, are rejected.
+    pub async fn from_url(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_url_bounded(url, MAX_GEOJSON_BYTES, GEOJSON_FETCH_TIMEOUT).await
+    }
+
+    /// Like [`Self::from_url`] with an explicit size and total time limit
+    ///
+    /// The body is read in chunks and the download is abandoned as soon as it
+    /// passes `max_bytes`, whether or not the server sent a `Content-Length`.
+    pub async fn from_url_bounded(
+        url: &str,
+        max_bytes: u64,
+        timeout: Duration,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(GEOJSON_CONNECT_TIMEOUT.min(timeout))
+            .timeout(timeout)
+            .build()?;
+        let mut response = client.get(url).send().await?.error_for_status()?;
+        let too_large = || format!("{} is larger than {} bytes", url, max_bytes);
+        if response.content_length().is_some_and(|len| len > max_bytes) {
+            return Err(too_large().into());
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (body.len() + chunk.len()) as u64 > max_bytes {
+                return Err(too_large().into());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Self::from_geojson(std::str::from_utf8(&body)?)
+    }
+
+    /// Loads from `url` when set, otherwise from the GeoJSON file (or the embedded copy)
+    pub async fn load(url: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        match url {
+            Some(url) => Self::from_url(url).await,
+            None => Self::new(),
+        }
+    }
+
+    #[cfg(feature = "embedded-neighborhoods")]
+    fn embedded() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_geojson(std::str::from_utf8(EMBEDDED_GEOJSON)?)
+    }
+
+    #[cfg(not(feature = "embedded-neighborhoods"))]
+    fn embedded() -> Result<Self, Box<dyn std::error::Error>> {
+        Err("neighborhoods.geojson not found".into())
+    }
+
+    /// Parses a GeoJSON feature collection of neighborhoods
+    ///
+    /// Features whose properties do not describe a neighborhood are skipped.
+    pub fn from_geojson(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let geojson: Value = serde_json::from_str(content)?;
 
         let mut neighborhoods = HashMap::new();
+        let mut boundaries = HashMap::new();
 
         if let Some(features) = geojson.get("features").and_then(|f| f.as_array()) {
             for feature in features {
-                if let Some(properties) = feature.get("properties") {
-                    if let Ok(neighborhood) =
+                if let Some(properties) = feature.get("properties")
+                    && let Ok(neighborhood) =
                         serde_json::from_value::<NeighborhoodProperties>(properties.clone())
-                    {
-                        neighborhoods.insert(neighborhood.name.clone(), neighborhood);
+                {
+                    if let Some(geometry) = feature.get("geometry") {
+                        boundaries.insert(neighborhood.name.clone(), parse_geometry(geometry));
                     }
+                    neighborhoods.insert(neighborhood.name.clone(), neighborhood);
                 }
             }
         }
 
+        let stats = city_stats(&neighborhoods.values().cloned().collect::<Vec<_>>());
         Ok(Self {
             neighborhoods: Arc::new(neighborhoods),
+            boundaries: Arc::new(boundaries),
+            stats: Arc::new(stats),
         })
     }
 
@@ -63,19 +154,54 @@ impl NeighborhoodDatabase {
         result
     }
 
+    /// Returns the boundary polygons of the named neighborhoods
+    ///
+    /// Names without a known boundary are skipped.
+    pub fn boundaries_for(&self, names: &[String]) -> ZoneBoundaries {
+        ZoneBoundaries::new(
+            names
+                .iter()
+                .filter_map(|name| Some((name.clone(), self.boundaries.get(name)?.clone())))
+                .collect(),
+        )
+    }
+
+    /// Returns every loaded neighborhood
+    pub fn all(&self) -> Vec<NeighborhoodProperties> {
+        self.neighborhoods.values().cloned().collect()
+    }
+
+    /// City-wide aggregates over every loaded neighborhood, computed at load
+    pub fn stats(&self) -> &CityStats {
+        &self.stats
+    }
+
     pub fn count(&self) -> usize {
         self.neighborhoods.len()
     }
+
+    /// Creates a database with no neighborhoods, as when the GeoJSON fails to load
+    pub fn empty() -> Self {
+        Self {
+            neighborhoods: Arc::new(HashMap::new()),
+            boundaries: Arc::new(HashMap::new()),
+            stats: Arc::new(CityStats::default()),
+        }
+    }
+
+    /// Whether no neighborhoods are loaded, so simulations can only use the
+    /// properties supplied with each request
+    pub fn is_degraded(&self) -> bool {
+        self.neighborhoods.is_empty()
+    }
 }
 
 impl Default for NeighborhoodDatabase {
     fn default() -> Self {
         Self::new().unwrap_or_else(|e| {
-            eprintln!("⚠️  Warning: Failed to load neighborhoods.geojson: {}", e);
-            eprintln!("   Neighborhood lookups will be limited to provided data");
-            Self {
-                neighborhoods: Arc::new(HashMap::new()),
-            }
+            logln!("⚠️  Warning: Failed to load neighborhoods.geojson: {}", e);
+            logln!("   Neighborhood lookups will be limited to provided data");
+            Self::empty()
         })
     }
 }
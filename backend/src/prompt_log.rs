@@ -0,0 +1,128 @@
+//! Prompt Exchange Logging
+//!
+//! When `PROMPT_LOG_DIR` is set, the full request body and raw response of each
+//! phase are written to timestamped files so prompt changes can be regression-tested
+//! and model misbehavior reproduced. Logging is off unless the variable is set, and
+//! the API key is redacted from everything written.
+
+use actix_web::web::Bytes;
+use futures_util::{Stream, StreamExt};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Writes one simulation's prompt exchanges to `PROMPT_LOG_DIR`
+///
+/// Files are named `<unix millis>-<request id>-<phase>-<request|response>.<ext>`.
+/// A disabled log ignores every write.
+#[derive(Debug, Clone)]
+pub struct PromptLog {
+    dir: Option<PathBuf>,
+    request_id: String,
+    api_key: String,
+}
+
+impl PromptLog {
+    /// Creates a log for one simulation, writing into `dir` if set
+    pub fn new(dir: Option<PathBuf>, request_id: &str, api_key: &str) -> Self {
+        Self {
+            dir,
+            request_id: request_id.to_string(),
+            api_key: api_key.to_string(),
+        }
+    }
+
+    /// Creates a log that never writes
+    pub fn disabled() -> Self {
+        Self::new(None, "", "")
+    }
+
+    /// Whether exchanges are being written
+    pub fn is_enabled(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    /// Writes the JSON request body sent for a phase
+    pub fn log_request<T: Serialize>(&self, phase: &str, body: &T) {
+        if !self.is_enabled() {
+            return;
+        }
+        match serde_json::to_string_pretty(body) {
+            Ok(json) => self.write(phase, "request.json", &json),
+            Err(e) => logln!(
+                "   ⚠️  Could not serialize {} request for prompt log: {}",
+                phase,
+                e
+            ),
+        }
+    }
+
+    /// Writes the raw response body received for a phase
+    pub fn log_response(&self, phase: &str, raw: &str) {
+        self.write(phase, "response.txt", raw);
+    }
+
+    /// Passes a response byte stream through unchanged, logging the full body once it ends
+    pub fn tee_response<S, E>(
+        self,
+        phase: &str,
+        stream: S,
+    ) -> impl Stream<Item = Result<Bytes, E>> + use<S, E>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+    {
+        let mut raw = self.is_enabled().then(Vec::new);
+        let log = self;
+        let phase = phase.to_string();
+
+        stream
+            .map(Some)
+            .chain(futures_util::stream::once(async { None }))
+            .filter_map(move |item| {
+                let output = match item {
+                    Some(Ok(bytes)) => {
+                        if let Some(raw) = raw.as_mut() {
+                            raw.extend_from_slice(&bytes);
+                        }
+                        Some(Ok(bytes))
+                    }
+                    Some(Err(e)) => Some(Err(e)),
+                    None => {
+                        if let Some(raw) = raw.take() {
+                            log.log_response(&phase, &String::from_utf8_lossy(&raw));
+                        }
+                        None
+                    }
+                };
+                async move { output }
+            })
+    }
+
+    fn write(&self, phase: &str, kind: &str, contents: &str) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let path = dir.join(format!("{}-{}-{}-{}", millis, self.request_id, phase, kind));
+
+        let contents = if self.api_key.is_empty() {
+            contents.to_string()
+        } else {
+            contents.replace(&self.api_key, REDACTED)
+        };
+
+        if let Err(e) = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, contents)) {
+            logln!(
+                "   ⚠️  Failed to write prompt log {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Neighborhood Metric Queries
+//!
+//! This module filters neighborhoods by ranges over their numeric metrics, so
+//! analysts can find e.g. low-income, high-vacancy areas before simulating a policy.
+//!
+//! Filters are named `<field>_min` and `<field>_max`, where `<field>` is any
+//! numeric property, using dotted paths for nested ones (e.g. `median_income_max`,
+//! `commute.avg_minutes_min`). Both bounds are inclusive.
+
+use crate::error::SimulationError;
+use crate::export::CSV_METRIC_COLUMNS;
+use crate::types::NeighborhoodProperties;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Inclusive bounds on one numeric neighborhood field
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeFilter {
+    /// Dotted path of the field, as in [`CSV_METRIC_COLUMNS`]
+    pub field: String,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Whether `field` names a numeric neighborhood property that can be filtered
+pub fn is_queryable_field(field: &str) -> bool {
+    field == "area_acres" || CSV_METRIC_COLUMNS.contains(&field)
+}
+
+/// Parses `<field>_min` / `<field>_max` query parameters into range filters
+///
+/// # Returns
+///
+/// One filter per field, ordered by field name
+///
+/// # Errors
+///
+/// Returns [`SimulationError::InvalidRequest`] for a parameter without a `_min` or
+/// `_max` suffix, an unknown field, or a value that is not a finite number
+pub fn parse_range_filters(
+    params: &HashMap<String, String>,
+) -> Result<Vec<RangeFilter>, SimulationError> {
+    let mut filters: HashMap<&str, RangeFilter> = HashMap::new();
+
+    for (param, value) in params {
+        let (field, is_min) = if let Some(field) = param.strip_suffix("_min") {
+            (field, true)
+        } else if let Some(field) = param.strip_suffix("_max") {
+            (field, false)
+        } else {
+            return Err(SimulationError::InvalidRequest(format!(
+                "query parameter {} must end in _min or _max",
+                param
+            )));
+        };
+        if !is_queryable_field(field) {
+            return Err(SimulationError::InvalidRequest(format!(
+                "{} is not a numeric neighborhood field",
+                field
+            )));
+        }
+        let bound = value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|bound| bound.is_finite())
+            .ok_or_else(|| {
+                SimulationError::InvalidRequest(format!(
+                    "{} must be a number, got {}",
+                    param, value
+                ))
+            })?;
+
+        let filter = filters.entry(field).or_insert_with(|| RangeFilter {
+            field: field.to_string(),
+            min: None,
+            max: None,
+        });
+        if is_min {
+            filter.min = Some(bound);
+        } else {
+            filter.max = Some(bound);
+        }
+    }
+
+    let mut filters: Vec<RangeFilter> = filters.into_values().collect();
+    filters.sort_by(|a, b| a.field.cmp(&b.field));
+    Ok(filters)
+}
+
+/// Returns the neighborhoods matching every filter, sorted by name
+///
+/// A neighborhood missing a filtered field does not match.
+pub fn filter_neighborhoods(
+    neighborhoods: impl IntoIterator<Item = NeighborhoodProperties>,
+    filters: &[RangeFilter],
+) -> Vec<NeighborhoodProperties> {
+    let mut matches: Vec<NeighborhoodProperties> = neighborhoods
+        .into_iter()
+        .filter(|neighborhood| {
+            let value = serde_json::to_value(neighborhood).unwrap_or(Value::Null);
+            filters.iter().all(|filter| {
+                field_value(&value, &filter.field).is_some_and(|v| {
+                    filter.min.is_none_or(|min| v >= min) && filter.max.is_none_or(|max| v <= max)
+                })
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| a.name.cmp(&b.name));
+    matches
+}
+
+fn field_value(value: &Value, path: &str) -> Option<f64> {
+    path.split('.')
+        .try_fold(value, |value, key| value.get(key))
+        .and_then(Value::as_f64)
+}
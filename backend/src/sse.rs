@@ -0,0 +1,121 @@
+//! Server-Sent Events Helpers
+//!
+//! The simulation is delivered as a stream of SSE `data:` frames, each holding one
+//! JSON-encoded [`SimulationChunk`]. This module contains the frame formatter used
+//! on the sending side and helpers for consuming those frames on the receiving side.
+
+use crate::types::{NeighborhoodMetrics, SimulationChunk};
+use actix_web::web::Bytes;
+use futures_util::{Stream, StreamExt};
+
+/// Serializes a simulation chunk as a single SSE `data:` frame
+///
+/// Every chunk the server streams goes through here, so the framing lives in one
+/// place. A chunk that fails to serialize becomes an SSE comment frame, which
+/// clients ignore.
+///
+/// # Returns
+///
+/// `data: <json>` followed by the blank-line delimiter
+pub fn sse_frame(chunk: &SimulationChunk) -> Bytes {
+    match serde_json::to_string(chunk) {
+        Ok(json) => Bytes::from(format!("data: {}\n\n", json)),
+        Err(e) => {
+            logln!("   ✗ Failed to serialize simulation chunk: {}", e);
+            Bytes::from_static(b": serialization failed\n\n")
+        }
+    }
+}
+
+/// Rewrites the metrics of an `event` frame in the flat form of
+/// [`NeighborhoodMetrics::to_flat`]
+///
+/// Frames that are not events, or whose event has no metrics, are returned as-is,
+/// as are any other lines of the frame such as its `id`.
+pub fn flatten_frame_metrics(frame: Bytes) -> Bytes {
+    let text = String::from_utf8_lossy(&frame);
+    let mut flattened = false;
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            let Some(data) = line.strip_prefix("data: ") else {
+                return line.to_string();
+            };
+            let Ok(SimulationChunk::Event { data: event }) = serde_json::from_str(data) else {
+                return line.to_string();
+            };
+            let Some(metrics) = event.metrics.as_ref().map(NeighborhoodMetrics::to_flat) else {
+                return line.to_string();
+            };
+            let Ok(mut chunk) = serde_json::to_value(SimulationChunk::Event { data: event }) else {
+                return line.to_string();
+            };
+            chunk["data"]["metrics"] = serde_json::Value::Object(metrics);
+            flattened = true;
+            format!("data: {}", chunk)
+        })
+        .collect();
+
+    if flattened {
+        Bytes::from(lines.join("\n"))
+    } else {
+        frame
+    }
+}
+
+/// Drives an SSE simulation stream to completion and parses every `data:` frame
+///
+/// Frames may be split across or combined within stream items; they are reassembled
+/// on the blank-line delimiter before parsing. Frames that are not valid simulation
+/// chunks are skipped, and collection stops at the first stream error.
+///
+/// This is primarily useful in tests and for library callers that want the whole
+/// simulation result at once rather than incrementally.
+///
+/// # Arguments
+///
+/// * `stream` - A stream of SSE-formatted bytes, as returned by `generate_simulation`
+///
+/// # Returns
+///
+/// Every simulation chunk in the order it was emitted
+pub async fn collect_chunks<S>(stream: S) -> Vec<SimulationChunk>
+where
+    S: Stream<Item = Result<Bytes, std::io::Error>>,
+{
+    futures_util::pin_mut!(stream);
+
+    let mut buffer = String::new();
+    let mut chunks = Vec::new();
+
+    while let Some(Ok(bytes)) = stream.next().await {
+        buffer.push_str(&String::from_utf8_lossy(&bytes));
+
+        while let Some(frame_end) = buffer.find("\n\n") {
+            let frame: String = buffer.drain(..frame_end + 2).collect();
+            chunks.extend(parse_frame(&frame));
+        }
+    }
+
+    chunks.extend(parse_frame(&buffer));
+    chunks
+}
+
+/// Parses a single SSE frame into a simulation chunk
+///
+/// Multiple `data:` lines are joined as the SSE spec describes. Returns `None` for
+/// frames without data or whose data is not a valid simulation chunk.
+pub fn parse_frame(frame: &str) -> Option<SimulationChunk> {
+    let data = frame
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if data.is_empty() {
+        return None;
+    }
+
+    serde_json::from_str(&data).ok()
+}
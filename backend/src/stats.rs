@@ -0,0 +1,89 @@
+//! City-Wide Neighborhood Statistics
+//!
+//! This module aggregates every loaded neighborhood into one city profile, so
+//! dashboards can show totals and averages without fetching and crunching all the
+//! neighborhoods themselves. The profile is computed once when the neighborhood
+//! database is built.
+
+use crate::types::NeighborhoodProperties;
+use crate::utils::{population_weighted_average, weighted_average};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Aggregates over every neighborhood in the database
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CityStats {
+    /// Neighborhoods aggregated
+    pub neighborhoods: usize,
+    pub total_population: i64,
+    /// Mean of the neighborhoods' median incomes, weighted by households
+    pub mean_income: f64,
+    /// Median of the neighborhoods' median incomes
+    pub median_income: f64,
+    pub total_housing_units: i64,
+    /// Percent of all housing units that are vacant
+    pub vacancy_rate: f64,
+    /// Livability index averaged by population
+    pub average_livability: f64,
+    /// Neighborhood count in each Neighborhood Planning Unit
+    pub neighborhoods_by_npu: BTreeMap<String, usize>,
+}
+
+/// Computes the city profile of `neighborhoods`
+///
+/// Every figure is 0 for an empty slice.
+pub fn city_stats(neighborhoods: &[NeighborhoodProperties]) -> CityStats {
+    let total_housing_units: i64 = neighborhoods
+        .iter()
+        .map(|n| i64::from(n.housing_units))
+        .sum();
+    let vacant_units: i64 = neighborhoods
+        .iter()
+        .map(|n| i64::from(n.vacant_units))
+        .sum();
+
+    let mut neighborhoods_by_npu = BTreeMap::new();
+    for neighborhood in neighborhoods {
+        *neighborhoods_by_npu
+            .entry(neighborhood.npu.clone())
+            .or_insert(0) += 1;
+    }
+
+    CityStats {
+        neighborhoods: neighborhoods.len(),
+        total_population: neighborhoods
+            .iter()
+            .map(|n| i64::from(n.population_total))
+            .sum(),
+        mean_income: weighted_average(
+            neighborhoods,
+            |n| n.households as f64,
+            |n| n.median_income as f64,
+        ),
+        median_income: median(neighborhoods.iter().map(|n| n.median_income as f64)),
+        total_housing_units,
+        vacancy_rate: if total_housing_units > 0 {
+            vacant_units as f64 / total_housing_units as f64 * 100.0
+        } else {
+            0.0
+        },
+        average_livability: population_weighted_average(neighborhoods, |n| n.livability_index),
+        neighborhoods_by_npu,
+    }
+}
+
+/// Median of `values`, averaging the middle two for an even count; 0 if empty
+fn median(values: impl Iterator<Item = f64>) -> f64 {
+    let mut values: Vec<f64> = values.collect();
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
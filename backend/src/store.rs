@@ -0,0 +1,244 @@
+//! Simulation Result Store
+//!
+//! This module keeps the chunks of each simulation run so they can be retrieved
+//! after the SSE stream has finished (e.g. for exports). Every SSE frame is also kept
+//! with a sequence number, so a client whose connection drops can resume the stream
+//! with `Last-Event-ID`. Results are held in memory, bounded by an LRU capacity and a
+//! retention period for finished runs, and are lost when the server restarts.
+
+use crate::config::SimulationConfig;
+use crate::sse::parse_frame;
+use crate::types::{EventNotification, SimulationChunk};
+use actix_web::web::Bytes;
+use async_stream::stream;
+use futures_util::Stream;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Simulations kept by [`SimulationStore::new`]
+pub const DEFAULT_STORE_CAPACITY: usize = 256;
+/// How long [`SimulationStore::new`] keeps finished simulations
+pub const DEFAULT_STORE_RETENTION: Duration = Duration::from_secs(3600);
+
+/// The recorded output of one simulation run
+#[derive(Debug, Clone)]
+pub struct StoredSimulation {
+    /// The policy prompt that was simulated
+    pub prompt: String,
+    /// Every event streamed to the client, in order
+    pub events: Vec<EventNotification>,
+    /// The completion summary, once the stream has finished
+    pub summary: Option<String>,
+    /// Every SSE frame streamed so far; the frame at index `i` carries `id: i + 1`
+    pub frames: Vec<Bytes>,
+    /// Whether generation has ended, successfully or not
+    pub finished: bool,
+}
+
+/// A stored simulation and when its generation ended
+struct StoreEntry {
+    simulation: StoredSimulation,
+    finished_at: Option<Instant>,
+}
+
+impl StoreEntry {
+    fn expired(&self, retention: Duration) -> bool {
+        self.finished_at
+            .is_some_and(|finished_at| finished_at.elapsed() >= retention)
+    }
+}
+
+/// In-memory store of simulation runs keyed by simulation id
+///
+/// Holds at most `capacity` runs, evicting the least recently used one when full,
+/// and forgets finished runs once the retention period has passed.
+pub struct SimulationStore {
+    simulations: Mutex<LruCache<String, StoreEntry>>,
+    retention: Duration,
+    /// Wakes subscribers whenever any simulation records a frame or finishes
+    updated: Notify,
+}
+
+impl Default for SimulationStore {
+    fn default() -> Self {
+        Self::with_limits(DEFAULT_STORE_CAPACITY, DEFAULT_STORE_RETENTION)
+    }
+}
+
+impl SimulationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a store holding up to `capacity` runs (at least one), each kept for
+    /// `retention` after it finishes
+    pub fn with_limits(capacity: usize, retention: Duration) -> Self {
+        Self {
+            simulations: Mutex::new(LruCache::new(
+                NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN),
+            )),
+            retention,
+            updated: Notify::new(),
+        }
+    }
+
+    /// Creates a store from the `simulation_store_*` settings
+    pub fn from_config(config: &SimulationConfig) -> Self {
+        Self::with_limits(
+            config.simulation_store_capacity,
+            Duration::from_secs(config.simulation_store_retention_secs),
+        )
+    }
+
+    /// Registers a new simulation run and returns its id
+    ///
+    /// Expired runs are dropped first, so a full store only evicts a live run when
+    /// every slot is still in use.
+    pub fn create(&self, prompt: &str) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        if let Ok(mut simulations) = self.simulations.lock() {
+            let expired: Vec<String> = simulations
+                .iter()
+                .filter(|(_, entry)| entry.expired(self.retention))
+                .map(|(id, _)| id.clone())
+                .collect();
+            for id in expired {
+                simulations.pop(&id);
+            }
+            simulations.put(
+                id.clone(),
+                StoreEntry {
+                    simulation: StoredSimulation {
+                        prompt: prompt.to_string(),
+                        events: Vec::new(),
+                        summary: None,
+                        frames: Vec::new(),
+                        finished: false,
+                    },
+                    finished_at: None,
+                },
+            );
+        }
+        id
+    }
+
+    /// Records one chunk of a simulation run
+    ///
+    /// Update, targets, and summary-delta chunks carry no result data and are ignored.
+    pub fn record(&self, id: &str, chunk: SimulationChunk) {
+        let Ok(mut simulations) = self.simulations.lock() else {
+            return;
+        };
+        let Some(StoreEntry { simulation, .. }) = simulations.get_mut(id) else {
+            return;
+        };
+
+        match chunk {
+            SimulationChunk::Event { data } => simulation.events.push(data),
+            SimulationChunk::Complete { data } => simulation.summary = Some(data.summary),
+            SimulationChunk::Update { .. }
+            | SimulationChunk::Targets { .. }
+            | SimulationChunk::Baseline { .. }
+            | SimulationChunk::FinalState { .. }
+            | SimulationChunk::Stable { .. }
+            | SimulationChunk::Summary { .. }
+            | SimulationChunk::Warning { .. } => {}
+        }
+    }
+
+    /// Records every simulation chunk contained in an SSE-formatted stream item
+    ///
+    /// Each frame is also kept for replay with the next sequence number as its SSE
+    /// `id`, and subscribers are woken.
+    pub fn record_frame(&self, id: &str, bytes: &Bytes) {
+        let text = String::from_utf8_lossy(bytes);
+        for frame in text.split("\n\n").filter(|frame| !frame.trim().is_empty()) {
+            if let Some(chunk) = parse_frame(frame) {
+                self.record(id, chunk);
+            }
+            if let Ok(mut simulations) = self.simulations.lock()
+                && let Some(StoreEntry { simulation, .. }) = simulations.get_mut(id)
+            {
+                let seq = simulation.frames.len() + 1;
+                simulation
+                    .frames
+                    .push(Bytes::from(format!("id: {}\n{}\n\n", seq, frame)));
+            }
+        }
+        self.updated.notify_waiters();
+    }
+
+    /// Marks a simulation's generation as ended, so subscribers stop after the last
+    /// recorded frame
+    pub fn finish(&self, id: &str) {
+        if let Ok(mut simulations) = self.simulations.lock()
+            && let Some(entry) = simulations.get_mut(id)
+        {
+            entry.simulation.finished = true;
+            entry.finished_at.get_or_insert_with(Instant::now);
+        }
+        self.updated.notify_waiters();
+    }
+
+    /// Streams a simulation's frames numbered above `after`, then follows new frames
+    /// live until generation finishes
+    ///
+    /// Pass 0 to receive the whole stream, or the last SSE `id` a client saw to resume
+    /// where it left off. Returns `None` if no simulation has the given id or it has
+    /// expired.
+    ///
+    /// The subscriber registers for the next update before checking for frames, so a
+    /// frame recorded in between still wakes it.
+    pub fn subscribe(
+        self: Arc<Self>,
+        id: &str,
+        after: usize,
+    ) -> Option<impl Stream<Item = Result<Bytes, std::io::Error>> + use<>> {
+        self.get(id)?;
+        let id = id.to_string();
+        Some(stream! {
+            let mut cursor = after;
+            loop {
+                let notified = self.updated.notified();
+                futures_util::pin_mut!(notified);
+                notified.as_mut().enable();
+
+                let Some((frames, finished)) = self.frames_after(&id, cursor) else {
+                    break;
+                };
+                if frames.is_empty() {
+                    if finished {
+                        break;
+                    }
+                    notified.await;
+                    continue;
+                }
+                cursor += frames.len();
+                for frame in frames {
+                    yield Ok(frame);
+                }
+            }
+        })
+    }
+
+    fn frames_after(&self, id: &str, after: usize) -> Option<(Vec<Bytes>, bool)> {
+        let simulations = self.simulations.lock().ok()?;
+        let simulation = &simulations.peek(id)?.simulation;
+        let frames = simulation.frames.iter().skip(after).cloned().collect();
+        Some((frames, simulation.finished))
+    }
+
+    /// Returns a copy of the recorded simulation run, unless it has expired
+    pub fn get(&self, id: &str) -> Option<StoredSimulation> {
+        let mut simulations = self.simulations.lock().ok()?;
+        let entry = simulations.get(id)?;
+        if entry.expired(self.retention) {
+            simulations.pop(id);
+            return None;
+        }
+        Some(entry.simulation.clone())
+    }
+}
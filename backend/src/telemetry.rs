@@ -0,0 +1,96 @@
+//! Parse Telemetry
+//!
+//! Phase 2 output quality varies by model: some wrap the array in prose or emit
+//! malformed chunks more often than others. This module keeps a rolling count of
+//! parsed chunks and parse errors per model, shared across request handlers, so
+//! operators can compare models on real traffic before switching.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Phase 2 streams per model that the rolling counts cover
+pub const ROLLING_WINDOW_STREAMS: usize = 100;
+
+/// Parser counts for one Phase 2 stream
+#[derive(Debug, Clone, Copy)]
+struct StreamCounts {
+    chunks: u32,
+    parse_errors: u32,
+}
+
+/// A model's parse statistics over its most recent streams
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelParseStats {
+    /// Streams counted, at most [`ROLLING_WINDOW_STREAMS`]
+    pub streams: usize,
+    /// Chunks the parser extracted from the model output
+    pub chunks: u64,
+    /// Chunks that failed to parse
+    pub parse_errors: u64,
+    /// `parse_errors / chunks`, or 0 before any chunk was seen
+    pub error_rate: f64,
+}
+
+/// Concurrency-safe rolling Phase 2 parse counts, keyed by model name
+#[derive(Debug, Default)]
+pub struct ParseTelemetry {
+    models: Mutex<HashMap<String, VecDeque<StreamCounts>>>,
+}
+
+impl ParseTelemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one finished Phase 2 stream generated by `model`
+    ///
+    /// The model's oldest stream is forgotten once it has more than
+    /// [`ROLLING_WINDOW_STREAMS`].
+    pub fn record(&self, model: &str, chunks: u32, parse_errors: u32) {
+        let mut models = self.lock();
+        let window = models.entry(model.to_string()).or_default();
+        window.push_back(StreamCounts {
+            chunks,
+            parse_errors,
+        });
+        if window.len() > ROLLING_WINDOW_STREAMS {
+            window.pop_front();
+        }
+    }
+
+    /// Rolling statistics for one model, or `None` if it has no recorded streams
+    pub fn model(&self, model: &str) -> Option<ModelParseStats> {
+        self.lock().get(model).map(stats)
+    }
+
+    /// Rolling statistics for every model seen since the server started
+    pub fn snapshot(&self) -> BTreeMap<String, ModelParseStats> {
+        self.lock()
+            .iter()
+            .map(|(model, window)| (model.clone(), stats(window)))
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, VecDeque<StreamCounts>>> {
+        self.models
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn stats(window: &VecDeque<StreamCounts>) -> ModelParseStats {
+    let chunks: u64 = window.iter().map(|s| u64::from(s.chunks)).sum();
+    let parse_errors: u64 = window.iter().map(|s| u64::from(s.parse_errors)).sum();
+    ModelParseStats {
+        streams: window.len(),
+        chunks,
+        parse_errors,
+        error_rate: if chunks == 0 {
+            0.0
+        } else {
+            parse_errors as f64 / chunks as f64
+        },
+    }
+}
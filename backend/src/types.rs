@@ -7,6 +7,7 @@
 //! - Request/response structures for the API
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Distribution of education levels in a neighborhood
 ///
@@ -49,6 +50,32 @@ pub struct Commute {
     pub transit_usage: f64,
 }
 
+/// A commute update carried by event metrics
+///
+/// Events may change any subset of the commute fields (e.g. only `avg_minutes`);
+/// missing fields keep their baseline value.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PartialCommute {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_minutes: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub car_dependence: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transit_usage: Option<f64>,
+}
+
+impl PartialCommute {
+    /// Applies the update to `baseline`, keeping its values for missing fields
+    pub fn resolve(&self, baseline: &Commute) -> Commute {
+        Commute {
+            avg_minutes: self.avg_minutes.unwrap_or(baseline.avg_minutes),
+            car_dependence: self.car_dependence.unwrap_or(baseline.car_dependence),
+            transit_usage: self.transit_usage.unwrap_or(baseline.transit_usage),
+        }
+    }
+}
+
 /// Derived metrics calculated from other neighborhood data
 ///
 /// These values are computed automatically from other fields:
@@ -167,7 +194,7 @@ pub struct NeighborhoodProperties {
 /// This represents a partial update to neighborhood properties.
 /// Events only include the fields that change, not the complete state.
 /// All fields are optional since events may affect different aspects of a neighborhood.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 #[serde(default)]
 pub struct NeighborhoodMetrics {
     #[serde(rename = "zoneId")]
@@ -207,38 +234,92 @@ pub struct NeighborhoodMetrics {
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub livability_index: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub commute: Option<Commute>,
+    pub commute: Option<PartialCommute>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub derived: Option<Derived>,
 }
 
-impl Default for NeighborhoodMetrics {
-    fn default() -> Self {
-        NeighborhoodMetrics {
-            zone_id: String::new(),
-            zone_name: String::new(),
-            population_total: None,
-            median_age: None,
-            population_density: None,
-            median_income: None,
-            median_home_value: None,
-            affordability_index: None,
-            housing_units: None,
-            households: None,
-            vacant_units: None,
-            vacancy_rate: None,
-            owner_occupancy: None,
-            housing_density: None,
-            education_distribution: None,
-            race_distribution: None,
-            diversity_index: None,
-            livability_index: None,
-            commute: None,
-            derived: None,
+impl NeighborhoodMetrics {
+    /// Flattens the changed fields into one level, naming nested ones by dotted path
+    ///
+    /// `{"commute": {"avg_minutes": 31.5}}` becomes `{"commute.avg_minutes": 31.5}`;
+    /// `zoneId` and `zoneName` are kept as they are.
+    pub fn to_flat(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut flat = serde_json::Map::new();
+        if let Ok(serde_json::Value::Object(nested)) = serde_json::to_value(self) {
+            flatten_into(&mut flat, "", nested);
         }
+        flat
+    }
+
+    /// Rebuilds metrics from the form written by [`NeighborhoodMetrics::to_flat`]
+    ///
+    /// # Errors
+    ///
+    /// Returns the serde error if a field has the wrong type or a path conflicts
+    /// with a plain field
+    pub fn from_flat(
+        flat: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Self, serde_json::Error> {
+        let mut nested = serde_json::Map::new();
+        for (path, value) in flat {
+            let mut keys: Vec<&str> = path.split('.').collect();
+            let last = keys.pop().unwrap_or_default();
+            let mut object = &mut nested;
+            for key in keys {
+                let entry = object
+                    .entry(key)
+                    .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+                object = match entry {
+                    serde_json::Value::Object(inner) => inner,
+                    _ => {
+                        return Err(serde::de::Error::custom(format!(
+                            "{} is not an object",
+                            key
+                        )));
+                    }
+                };
+            }
+            object.insert(last.to_string(), value.clone());
+        }
+        serde_json::from_value(serde_json::Value::Object(nested))
     }
 }
 
+fn flatten_into(
+    flat: &mut serde_json::Map<String, serde_json::Value>,
+    prefix: &str,
+    nested: serde_json::Map<String, serde_json::Value>,
+) {
+    for (key, value) in nested {
+        let path = if prefix.is_empty() {
+            key
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            serde_json::Value::Object(inner) => flatten_into(flat, &path, inner),
+            value => {
+                flat.insert(path, value);
+            }
+        }
+    }
+}
+
+/// How event `metrics` are written in the simulation stream
+///
+/// Chosen with the `metricsFormat` query parameter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsFormat {
+    /// The [`NeighborhoodMetrics`] object with `commute`, `derived`, and the
+    /// distributions as sub-objects
+    #[default]
+    Nested,
+    /// One level of dotted paths, as written by [`NeighborhoodMetrics::to_flat`]
+    Flat,
+}
+
 /// An event that occurs as a result of a policy implementation
 ///
 /// Events represent specific occurrences like construction starting, traffic changes,
@@ -252,10 +333,15 @@ impl Default for NeighborhoodMetrics {
 /// Each event includes a partial neighborhood metrics object that contains only the fields
 /// that change as a result of this event. The client applies these partial updates incrementally
 /// to build up the simulated neighborhood state.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct EventNotification {
+    /// Server-assigned id following `EVENT_ID_TEMPLATE` (`event-<n>` by default),
+    /// unique within a simulation stream
     pub id: String,
+    /// The id the model gave this event, if it differed from the assigned `id`
+    #[serde(rename = "sourceId", skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<String>,
     #[serde(rename = "zoneId")]
     pub zone_id: String,
     #[serde(rename = "zoneName")]
@@ -266,15 +352,38 @@ pub struct EventNotification {
     pub description: String,
     pub severity: f64,
     pub positivity: f64,
+    /// Event location as `[latitude, longitude]`
+    ///
+    /// This is the app's map convention and is the reverse of GeoJSON's `[lng, lat]`
+    /// order; transposed pairs from the model are corrected before streaming.
     pub coordinates: Vec<f64>,
     #[serde(rename = "metrics", skip_serializing_if = "Option::is_none")]
     pub metrics: Option<NeighborhoodMetrics>,
+    /// The model's explanation of how the event narrative produces its metric changes
+    ///
+    /// Optional unless the request sets `requireRationale`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rationale: Option<String>,
+    /// Indices into the request's `prompts` of the policies that drove this event
+    ///
+    /// Only set for multi-policy requests; empty (and omitted) when the model gave no
+    /// valid attribution.
+    #[serde(rename = "drivenBy", skip_serializing_if = "Vec::is_empty", default)]
+    pub driven_by: Vec<usize>,
+    /// Neighborhoods adjacent to the event's zone that are likely to feel its effects
+    ///
+    /// Computed by the server from the zone's neighbor list for events with a
+    /// severity of at least [`crate::events::SPILLOVER_MIN_SEVERITY`]; empty (and
+    /// omitted) otherwise.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub spillover: Vec<String>,
 }
 
 impl Default for EventNotification {
     fn default() -> Self {
         EventNotification {
             id: String::new(),
+            source_id: None,
             zone_id: String::new(),
             zone_name: String::new(),
             event_type: String::new(),
@@ -284,6 +393,9 @@ impl Default for EventNotification {
             positivity: 0.0,
             coordinates: vec![],
             metrics: None,
+            rationale: None,
+            driven_by: Vec::new(),
+            spillover: Vec::new(),
         }
     }
 }
@@ -295,14 +407,28 @@ impl Default for EventNotification {
 /// the client to track how neighborhoods change incrementally as events occur.
 ///
 /// The `#[serde(tag = "type")]` attribute means the JSON includes a "type" field
-/// that determines which variant to deserialize ("event", "update", or "complete").
+/// that determines which variant to deserialize ("event", "update", "targets",
+/// "baseline", "summary", "warning", "final_state", "stable", or "complete").
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(tag = "type")]
+#[allow(clippy::large_enum_variant)]
 pub enum SimulationChunk {
     #[serde(rename = "event")]
     Event { data: EventNotification },
     #[serde(rename = "update")]
     Update { data: SimulationUpdate },
+    #[serde(rename = "targets")]
+    Targets { data: SimulationTargets },
+    #[serde(rename = "baseline")]
+    Baseline { data: SimulationBaseline },
+    #[serde(rename = "summary")]
+    Summary { data: SummaryDelta },
+    #[serde(rename = "warning")]
+    Warning { data: SimulationWarning },
+    #[serde(rename = "final_state")]
+    FinalState { data: NeighborhoodFinalState },
+    #[serde(rename = "stable")]
+    Stable { data: StableZone },
     #[serde(rename = "complete")]
     Complete { data: SimulationComplete },
 }
@@ -313,6 +439,87 @@ pub struct SimulationUpdate {
     pub total: u32,
 }
 
+/// The target neighborhoods resolved after Phase 1
+///
+/// Sent right after the update chunk so the client can highlight affected zones
+/// before any events arrive.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SimulationTargets {
+    /// Every neighborhood Phase 1 selected, in the order it returned them
+    pub neighborhoods: Vec<String>,
+    /// Targets whose properties were supplied in the request
+    #[serde(rename = "fromRequest")]
+    pub from_request: Vec<String>,
+    /// Targets whose properties were loaded from the neighborhood database
+    #[serde(rename = "fromDatabase")]
+    pub from_database: Vec<String>,
+    /// Targets with no known properties; Phase 2 cannot generate events for them
+    pub missing: Vec<String>,
+}
+
+/// Starting properties of the target neighborhoods
+///
+/// Sent before any events with exactly the properties Phase 2 was grounded on,
+/// including `baselineOverrides`, so clients compute event deltas against the same
+/// baseline as the server.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SimulationBaseline {
+    /// Properties of each target neighborhood with known data, in target order
+    pub neighborhoods: Vec<NeighborhoodProperties>,
+}
+
+/// A neighborhood's properties after every event in the simulation
+///
+/// Sent just before the `complete` chunk for each neighborhood that had at least one
+/// event, so thin clients need not accumulate partial metric updates themselves.
+/// Events hidden by request filters are included, since they still happened.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NeighborhoodFinalState {
+    #[serde(rename = "zoneId")]
+    pub zone_id: String,
+    /// The baseline with each event's metrics applied in order, derived values
+    /// recomputed after each
+    pub properties: NeighborhoodProperties,
+}
+
+/// A target neighborhood that was analyzed but produced no events
+///
+/// Sent just before the `complete` chunk when the request sets `markStableZones`, so
+/// the client can tell "unaffected" apart from "not analyzed".
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StableZone {
+    #[serde(rename = "zoneId")]
+    pub zone_id: String,
+}
+
+/// A piece of the completion summary, streamed while the model writes it
+///
+/// Concatenating every `summary` chunk's `delta` gives the summary text; the final
+/// `complete` chunk still carries it in full.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SummaryDelta {
+    pub delta: String,
+}
+
+/// A non-fatal problem with a simulation
+///
+/// Streamed for each unparseable model chunk in `DEBUG_PARSE_ERRORS` mode, and at the
+/// start of every simulation while the neighborhood database is unavailable.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SimulationWarning {
+    pub message: String,
+    /// The offending chunk JSON, verbatim
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub chunk: Option<String>,
+}
+
+/// Version of the simulation chunk format
+///
+/// Sent as the `X-Sim-Schema-Version` header and in the `complete` chunk. Bump it
+/// whenever `SimulationChunk` or `EventNotification` change shape, so clients can
+/// branch on it during migrations.
+pub const SCHEMA_VERSION: u32 = 10;
+
 /// Completion message sent at the end of a simulation stream
 ///
 /// This chunk is always the last one in a simulation stream and provides
@@ -321,6 +528,99 @@ pub struct SimulationUpdate {
 pub struct SimulationComplete {
     /// Human-readable summary of the simulation results
     pub summary: String,
+    /// The [`SCHEMA_VERSION`] the stream was produced with
+    #[serde(default)]
+    pub schema_version: u32,
+    /// Model that generated the events when the primary model failed
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fallback_model: Option<String>,
+    /// Counts of generated events the server dropped or hid, and why
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub diagnostics: Option<SimulationDiagnostics>,
+    /// Structured highlights, serialized as fields of the chunk itself
+    #[serde(flatten)]
+    pub highlights: SummaryHighlights,
+}
+
+/// Machine-readable highlights of a simulation, computed from its events
+///
+/// Sent in the `complete` chunk alongside the prose `summary` so dashboards need not
+/// parse it. Events hidden by request filters are included, since they still happened.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct SummaryHighlights {
+    /// Neighborhoods with at least one event, in the order their first events arrived
+    #[serde(default)]
+    pub affected_neighborhoods: Vec<String>,
+    /// Total change in population across every neighborhood with events
+    #[serde(default)]
+    pub net_population_change: i32,
+    /// Most frequent event type (lowercased), the earliest seen on ties; absent
+    /// without events
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub dominant_event_type: Option<String>,
+    /// Mean event positivity, from -1.0 to 1.0; absent without events
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub overall_positivity: Option<f64>,
+}
+
+/// Counts of Phase 2 output that did not reach the client
+///
+/// Reported in the `complete` chunk so a thinner-than-expected simulation is
+/// visible to the user rather than only in server logs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SimulationDiagnostics {
+    /// Chunks that could not be parsed
+    pub parse_errors: u32,
+    /// Events for neighborhoods outside the Phase 1 targets
+    pub dropped_off_target: u32,
+    /// Events where no metric changed meaningfully
+    pub dropped_sub_threshold: u32,
+    /// Events repeating an earlier event's zone and title
+    pub dropped_duplicate: u32,
+    /// Events worded almost like an earlier event, past `NEAR_DUPLICATE_THRESHOLD`
+    #[serde(default)]
+    pub dropped_near_duplicate: u32,
+    /// Events without a valid `[lat, lng]` location
+    pub dropped_out_of_bounds: u32,
+    /// Events whose type is not in the request's `allowedEventTypes`
+    #[serde(default)]
+    pub dropped_disallowed_type: u32,
+    /// Events after `MAX_TOTAL_EVENTS` valid ones had been generated
+    #[serde(default)]
+    pub dropped_over_limit: u32,
+    /// Events with metric changes but no `rationale` when the request required one
+    #[serde(default)]
+    pub dropped_missing_rationale: u32,
+    /// Valid events hidden by request filters such as `minPositivity` or `minSeverity`
+    pub hidden_by_filter: u32,
+    /// Whether the model output was cut off before the event array closed
+    #[serde(default)]
+    pub truncated: bool,
+    /// Whether a partial event from a cut-off stream was recovered
+    #[serde(default)]
+    pub recovered_partial: bool,
+    /// Why the model output stopped, for the last Phase 2 attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub termination: Option<StreamTermination>,
+}
+
+/// Why reading a Phase 2 model stream stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamTermination {
+    /// The model finished and sent `[DONE]`
+    Done,
+    /// The model stopped at its `max_tokens` cap
+    TokenLimit,
+    /// The connection closed before `[DONE]`
+    ConnectionClosed,
+    /// Reading the response failed
+    Error,
+    /// No data arrived within `PHASE2_IDLE_TIMEOUT_SECS`
+    Timeout,
+    /// `MAX_TOTAL_EVENTS` events were accepted, so the rest of the output was
+    /// skipped, including the model's own summary
+    CapReached,
 }
 
 /// Request payload for the simulation endpoint
@@ -333,11 +633,26 @@ pub struct SimulationComplete {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SimulationRequest {
     /// The policy proposal text describing what to simulate
+    ///
+    /// Leave empty when `prompts` is set.
+    #[serde(default)]
     pub prompt: String,
+    /// Several policies enacted together, as an alternative to a single `prompt`
+    ///
+    /// The policies are numbered from 0 and combined into one proposal (see
+    /// [`crate::utils::combine_policy_prompts`]), so Phase 1 picks targets for the
+    /// whole scenario and Phase 2 tags each event with the policies behind it in
+    /// `drivenBy`.
+    #[serde(default)]
+    pub prompts: Vec<String>,
     /// Optional list of neighborhood names to focus the simulation on
     /// If empty, the AI will analyze which neighborhoods would be affected
     #[serde(rename = "selectedZones", default)]
     pub selected_zones: Vec<String>,
+    /// When true, Phase 1 results outside `selected_zones` are dropped before Phase 2,
+    /// so the selection acts as a hard boundary rather than a hint
+    #[serde(rename = "strictZones", default)]
+    pub strict_zones: bool,
     /// Minimal neighborhood context for Phase 1 (identifying target neighborhoods)
     /// Contains only: name, baseline_description, current_events, neighboring_neighborhoods
     #[serde(rename = "neighborhoodContext", default)]
@@ -346,4 +661,215 @@ pub struct SimulationRequest {
     /// Used as a lookup table keyed by neighborhood name
     #[serde(rename = "neighborhoodProperties", default)]
     pub neighborhood_properties: Vec<NeighborhoodProperties>,
+    /// Events with a lower positivity are generated but not streamed
+    /// Also accepted as the `minPositivity` query parameter on the simulate endpoint
+    #[serde(rename = "minPositivity", default)]
+    pub min_positivity: Option<f64>,
+    /// Events with a higher positivity are generated but not streamed
+    /// Also accepted as the `maxPositivity` query parameter on the simulate endpoint
+    #[serde(rename = "maxPositivity", default)]
+    pub max_positivity: Option<f64>,
+    /// Events with a lower severity are generated but not streamed, overriding
+    /// `MIN_SEVERITY`
+    /// Also accepted as the `minSeverity` query parameter on the simulate endpoint
+    #[serde(rename = "minSeverity", default)]
+    pub min_severity: Option<f64>,
+    /// Stream only the final `complete` chunk instead of individual events
+    ///
+    /// The `warning` chunk sent when the neighborhood database is unavailable is
+    /// still streamed first, since the summary is built from degraded data.
+    ///
+    /// Phase 2 still generates every event so the summary stays grounded in them,
+    /// so this reduces client-side work but not token usage.
+    #[serde(rename = "summaryOnly", default)]
+    pub summary_only: bool,
+    /// Hypothetical baseline changes keyed by neighborhood name
+    ///
+    /// Merged onto the neighborhood properties before the Phase 2 context is built,
+    /// for "what if this neighborhood already had..." simulations.
+    #[serde(rename = "baselineOverrides", default)]
+    pub baseline_overrides: HashMap<String, NeighborhoodMetrics>,
+    /// Phase 2 presence penalty, clamped to -2.0..=2.0 (default 0.0)
+    #[serde(rename = "presencePenalty", default)]
+    pub presence_penalty: Option<f32>,
+    /// Phase 2 frequency penalty, clamped to -2.0..=2.0 (default 0.0)
+    ///
+    /// Raising this reduces repetitive event descriptions.
+    #[serde(rename = "frequencyPenalty", default)]
+    pub frequency_penalty: Option<f32>,
+    /// When false, events are streamed with the model's metrics untouched instead of
+    /// having derived values (density, vacancy, diversity, ...) recomputed by the server
+    ///
+    /// Useful for evaluating raw model output.
+    #[serde(rename = "autoCompleteMetrics", default = "default_true")]
+    pub auto_complete_metrics: bool,
+    /// Event types Phase 2 may generate (e.g. `["housing", "economic"]`), compared
+    /// case-insensitively; empty allows any type
+    ///
+    /// The list is given to the model and enforced by dropping other events.
+    #[serde(rename = "allowedEventTypes", default)]
+    pub allowed_event_types: Vec<String>,
+    /// Which grounding the system prompts use (default `atlanta`)
+    #[serde(rename = "promptProfile", default)]
+    pub prompt_profile: PromptProfile,
+    /// Stream all of one target neighborhood's events together, in Phase 1 order,
+    /// instead of in the order the model interleaves them
+    ///
+    /// Events are held until Phase 2 finishes, so the first event arrives only after
+    /// the whole model output has been generated (typically 10-30 seconds later).
+    #[serde(rename = "groupByZone", default)]
+    pub group_by_zone: bool,
+    /// Stream events from most to least severe (within each zone, with
+    /// `groupByZone`); like grouping, this holds events until Phase 2 finishes
+    #[serde(rename = "sortBySeverity", default)]
+    pub sort_by_severity: bool,
+    /// Metric groups included in the Phase 2 neighborhood context (e.g.
+    /// `["housing"]`); empty includes every group
+    ///
+    /// Trimming groups irrelevant to the policy saves prompt tokens. Names, area,
+    /// livability, descriptions, current events, and neighbors are always included.
+    #[serde(rename = "contextFields", default)]
+    pub context_fields: Vec<ContextField>,
+    /// Ask the model to explain every metric change and drop events that change
+    /// metrics without a `rationale`
+    #[serde(rename = "requireRationale", default)]
+    pub require_rationale: bool,
+    /// Send a `stable` chunk for each target neighborhood that produced no events
+    #[serde(rename = "markStableZones", default)]
+    pub mark_stable_zones: bool,
+    /// Fewest events to generate (default 3); when set, Phase 2 is retried once if
+    /// the model returns fewer
+    #[serde(rename = "minEvents", default)]
+    pub min_events: Option<u32>,
+    /// Most events to stream (default 13, never more than `MAX_TOTAL_EVENTS`); later
+    /// events are dropped
+    #[serde(rename = "maxEvents", default)]
+    pub max_events: Option<u32>,
+    /// Server-assigned id of this run, used in event ids and prompt log file names
+    ///
+    /// The simulate endpoint sets it to the simulation id; a random one is generated
+    /// when unset. Never read from the request body.
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+/// System prompt grounding for a simulation
+///
+/// `generic` swaps the Atlanta-specific expertise for a city-agnostic planner role
+/// naming `CITY_NAME`, so prompt ablations can measure what the local grounding adds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptProfile {
+    #[default]
+    Atlanta,
+    Generic,
+}
+
+/// Group of related metrics in the Phase 2 neighborhood context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextField {
+    /// Population and diversity index
+    Demographics,
+    /// Home value, housing units, vacancy, and owner occupancy
+    Housing,
+    /// Median income
+    Economics,
+    /// Commute time, car dependence, and transit usage
+    Commute,
+    /// Education and race distributions
+    Distributions,
+}
+
+/// Free-text neighborhood fields a phase's context includes
+///
+/// Configured per phase with `PHASE1_CONTEXT_FIELDS` / `PHASE2_CONTEXT_FIELDS` as a
+/// comma-separated list of `baseline_description`, `current_events`, and
+/// `neighbors`, or `none`. Each field costs tokens in every request, and what helps
+/// Phase 1 pick targets differs from what grounds Phase 2 events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextualFields {
+    pub baseline_description: bool,
+    pub current_events: bool,
+    pub neighbors: bool,
+}
+
+impl ContextualFields {
+    /// Every contextual field, the default for both phases
+    pub const ALL: Self = Self {
+        baseline_description: true,
+        current_events: true,
+        neighbors: true,
+    };
+
+    /// No contextual fields, leaving only names and metrics
+    pub const NONE: Self = Self {
+        baseline_description: false,
+        current_events: false,
+        neighbors: false,
+    };
+}
+
+impl Default for ContextualFields {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl std::str::FromStr for ContextualFields {
+    type Err = String;
+
+    fn from_str(list: &str) -> Result<Self, Self::Err> {
+        let mut fields = Self::NONE;
+        for name in list
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            match name.to_lowercase().as_str() {
+                "baseline_description" => fields.baseline_description = true,
+                "current_events" => fields.current_events = true,
+                "neighbors" => fields.neighbors = true,
+                "none" => {}
+                other => return Err(format!("unknown contextual field '{}'", other)),
+            }
+        }
+        Ok(fields)
+    }
+}
+
+impl Default for SimulationRequest {
+    fn default() -> Self {
+        Self {
+            prompt: String::new(),
+            prompts: Vec::new(),
+            selected_zones: Vec::new(),
+            strict_zones: false,
+            neighborhood_context: Vec::new(),
+            neighborhood_properties: Vec::new(),
+            min_positivity: None,
+            max_positivity: None,
+            min_severity: None,
+            summary_only: false,
+            baseline_overrides: HashMap::new(),
+            presence_penalty: None,
+            frequency_penalty: None,
+            auto_complete_metrics: true,
+            allowed_event_types: Vec::new(),
+            prompt_profile: PromptProfile::default(),
+            group_by_zone: false,
+            sort_by_severity: false,
+            context_fields: Vec::new(),
+            require_rationale: false,
+            mark_stable_zones: false,
+            min_events: None,
+            max_events: None,
+            request_id: None,
+        }
+    }
+}
+
+/// Helper function for serde to default boolean flags to true
+fn default_true() -> bool {
+    true
 }
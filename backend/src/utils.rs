@@ -2,20 +2,34 @@
 //!
 //! This module contains utility functions used across the application:
 //! - Metric calculation and completion logic
+//! - Metric change validation against minimum meaningful thresholds
+//! - Weighted aggregation across neighborhoods
 //! - Data formatting and transformation
 //! - JSON parsing utilities
+//! - Size-limited data file reads
 
-use crate::types::{MinimalNeighborhoodContext, NeighborhoodMetrics, NeighborhoodProperties};
+use crate::config::SimulationConfig;
+use crate::metrics;
+use crate::neighborhoods::NeighborhoodDatabase;
+use crate::types::{
+    ContextField, ContextualFields, MinimalNeighborhoodContext, NeighborhoodMetrics,
+    NeighborhoodProperties, PartialCommute, SimulationRequest, SimulationTargets,
+};
 
 /// Completes interdependent metric calculations for partial neighborhood updates
 ///
 /// When the AI generates partial metric updates, some fields depend on others:
 /// - `higher_ed_percent` is derived from `education_distribution` (bachelors + graduate)
-/// - `diversity_index` is calculated from `race_distribution` using Shannon diversity
+/// - `diversity_index` is calculated from `race_distribution` (Gini-Simpson, `1 - Σp²`)
 /// - `density_index` is calculated from `population_total` and `area_acres`
+/// - `housing_units`, `households`, and `vacant_units` are reconciled when only some
+///   of them change (see [`reconcile_housing_counts`])
+/// - `vacancy_rate` is filled in from `vacant_units` and `housing_units` when omitted
+/// - `commute` fields the update leaves out are filled in from the baseline
 ///
 /// This function ensures these derived fields are automatically computed when
-/// their dependencies are present in the partial update.
+/// their dependencies are present in the partial update. The formulas themselves
+/// live in [`crate::metrics`].
 ///
 /// # Arguments
 ///
@@ -28,7 +42,7 @@ pub fn complete_interdependent_metrics(
     use crate::types::Derived;
 
     if let Some(ref edu_dist) = metrics.education_distribution {
-        let higher_ed_percent = edu_dist.bachelors + edu_dist.graduate;
+        let higher_ed_percent = metrics::higher_ed(edu_dist);
         match &mut metrics.derived {
             Some(derived) => derived.higher_ed_percent = higher_ed_percent,
             None => {
@@ -41,23 +55,12 @@ pub fn complete_interdependent_metrics(
     }
 
     if let Some(ref race_dist) = metrics.race_distribution {
-        let diversity_index = 1.0
-            - [
-                race_dist.white,
-                race_dist.black,
-                race_dist.asian,
-                race_dist.mixed,
-                race_dist.hispanic,
-            ]
-            .iter()
-            .map(|&p| (p / 100.0).powi(2))
-            .sum::<f64>();
-
-        metrics.diversity_index = Some(diversity_index);
+        metrics.diversity_index = Some(metrics::diversity(race_dist));
     }
 
     if let Some(population_total) = metrics.population_total {
-        let density_index = population_total as f64 / original_neighborhood.area_acres;
+        let density_index =
+            metrics::density(population_total as f64, original_neighborhood.area_acres);
         match &mut metrics.derived {
             Some(derived) => derived.density_index = density_index,
             None => {
@@ -68,48 +71,992 @@ pub fn complete_interdependent_metrics(
             }
         }
     }
+
+    reconcile_housing_counts(metrics, original_neighborhood);
+
+    if metrics.vacancy_rate.is_none()
+        && (metrics.vacant_units.is_some() || metrics.housing_units.is_some())
+    {
+        let vacant_units = metrics
+            .vacant_units
+            .unwrap_or(original_neighborhood.vacant_units);
+        let housing_units = metrics
+            .housing_units
+            .unwrap_or(original_neighborhood.housing_units);
+        metrics.vacancy_rate = Some(metrics::vacancy(vacant_units as f64, housing_units as f64));
+    }
+
+    if let Some(commute) = metrics.commute.as_mut() {
+        let base = &original_neighborhood.commute;
+        commute.avg_minutes.get_or_insert(base.avg_minutes);
+        commute.car_dependence.get_or_insert(base.car_dependence);
+        commute.transit_usage.get_or_insert(base.transit_usage);
+    }
+}
+
+/// Keeps `housing_units`, `households`, and `vacant_units` consistent with each other
+///
+/// Each household occupies one unit, so `housing_units ≈ households + vacant_units`.
+/// Census baselines rarely match exactly, so the rule holds the baseline's gap
+/// (`housing_units - households - vacant_units`) constant rather than forcing it to
+/// zero. When an update sets one or two of the counts, the rest are solved from it:
+///
+/// - Only `housing_units`: new or removed units change `vacant_units`; removing more
+///   units than are vacant also removes households
+/// - Only `households`: households move into or out of vacant units; if there are not
+///   enough vacant units, `housing_units` grows
+/// - Only `vacant_units`: occupied units become vacant or vice versa, changing
+///   `households`; if there are not enough households, `housing_units` grows
+/// - Two of the three: the third is solved directly, floored at 0
+///
+/// Updates that set all three counts, or none, are left unchanged. Solved counts are
+/// added to the update only when they differ from the baseline.
+pub fn reconcile_housing_counts(
+    metrics: &mut NeighborhoodMetrics,
+    original_neighborhood: &NeighborhoodProperties,
+) {
+    let base = original_neighborhood;
+    let gap = base.housing_units - base.households - base.vacant_units;
+    let mut housing = metrics.housing_units.unwrap_or(base.housing_units);
+    let mut households = metrics.households.unwrap_or(base.households);
+    let mut vacant = metrics.vacant_units.unwrap_or(base.vacant_units);
+
+    match (
+        metrics.housing_units.is_some(),
+        metrics.households.is_some(),
+        metrics.vacant_units.is_some(),
+    ) {
+        (true, false, false) => {
+            vacant = housing - households - gap;
+            if vacant < 0 {
+                households = (households + vacant).max(0);
+                vacant = 0;
+            }
+        }
+        (false, true, false) => {
+            vacant = housing - households - gap;
+            if vacant < 0 {
+                housing -= vacant;
+                vacant = 0;
+            }
+        }
+        (false, false, true) => {
+            households = housing - vacant - gap;
+            if households < 0 {
+                housing -= households;
+                households = 0;
+            }
+        }
+        (true, true, false) => vacant = (housing - households - gap).max(0),
+        (true, false, true) => households = (housing - vacant - gap).max(0),
+        (false, true, true) => housing = (households + vacant + gap).max(0),
+        _ => return,
+    }
+
+    for (field, value, baseline) in [
+        (&mut metrics.housing_units, housing, base.housing_units),
+        (&mut metrics.households, households, base.households),
+        (&mut metrics.vacant_units, vacant, base.vacant_units),
+    ] {
+        if field.is_some() || value != baseline {
+            *field = Some(value);
+        }
+    }
+}
+
+/// Shortest policy prompt, in characters after trimming, worth simulating
+pub const MIN_PROMPT_CHARS: usize = 10;
+
+/// Checks that a policy prompt has enough text to simulate
+///
+/// Rejecting empty and trivially short prompts up front avoids spending two Azure
+/// round-trips on output that cannot be grounded in anything.
+///
+/// # Errors
+///
+/// Returns a description of the problem.
+pub fn validate_prompt(prompt: &str) -> Result<(), String> {
+    let length = prompt.trim().chars().count();
+    if length == 0 {
+        return Err("prompt must not be empty".to_string());
+    }
+    if length < MIN_PROMPT_CHARS {
+        return Err(format!(
+            "prompt must be at least {} characters (got {})",
+            MIN_PROMPT_CHARS, length
+        ));
+    }
+    Ok(())
+}
+
+/// Combines several policies into the single proposal given to both phases
+///
+/// Each policy is listed on its own line as `Policy <n>: <text>`, numbered from 0 in
+/// request order, under a heading saying they are enacted together. Phase 2 refers
+/// to the same numbers when attributing events. A single policy is returned as is.
+pub fn combine_policy_prompts(prompts: &[String]) -> String {
+    if let [prompt] = prompts {
+        return prompt.trim().to_string();
+    }
+    let mut combined = format!("{} policies enacted together:", prompts.len());
+    for (index, prompt) in prompts.iter().enumerate() {
+        combined.push_str(&format!("\nPolicy {}: {}", index, prompt.trim()));
+    }
+    combined
+}
+
+/// Returns the proposal text for a request, combining `prompts` when set
+///
+/// # Errors
+///
+/// Returns a description of the problem if both `prompt` and `prompts` are set, or
+/// if any prompt fails [`validate_prompt`].
+pub fn resolve_policy_prompt(request: &SimulationRequest) -> Result<String, String> {
+    if request.prompts.is_empty() {
+        validate_prompt(&request.prompt)?;
+        return Ok(request.prompt.clone());
+    }
+    if !request.prompt.trim().is_empty() {
+        return Err("set either prompt or prompts, not both".to_string());
+    }
+    for (index, prompt) in request.prompts.iter().enumerate() {
+        validate_prompt(prompt).map_err(|message| format!("prompts[{}]: {}", index, message))?;
+    }
+    Ok(combine_policy_prompts(&request.prompts))
+}
+
+/// Checks that manual metric overrides are within sensible ranges
+///
+/// Counts and currency values must be non-negative, percentages (rates, shares, and
+/// distribution entries) must lie in 0-100, and every number must be finite.
+///
+/// # Errors
+///
+/// Returns a description of the first invalid field.
+pub fn validate_metric_overrides(overrides: &NeighborhoodMetrics) -> Result<(), String> {
+    let mut non_negative: Vec<(&str, Option<f64>)> = vec![
+        (
+            "population_total",
+            overrides.population_total.map(f64::from),
+        ),
+        ("median_age", overrides.median_age),
+        ("population_density", overrides.population_density),
+        ("median_income", overrides.median_income.map(f64::from)),
+        (
+            "median_home_value",
+            overrides.median_home_value.map(f64::from),
+        ),
+        ("affordability_index", overrides.affordability_index),
+        ("housing_units", overrides.housing_units.map(f64::from)),
+        ("households", overrides.households.map(f64::from)),
+        ("vacant_units", overrides.vacant_units.map(f64::from)),
+        ("housing_density", overrides.housing_density),
+        ("diversity_index", overrides.diversity_index),
+        ("livability_index", overrides.livability_index),
+    ];
+    let mut percentages: Vec<(&str, Option<f64>)> = vec![
+        ("vacancy_rate", overrides.vacancy_rate),
+        ("owner_occupancy", overrides.owner_occupancy),
+    ];
+
+    if let Some(education) = &overrides.education_distribution {
+        percentages.extend([
+            (
+                "education_distribution.high_school_or_less",
+                Some(education.high_school_or_less),
+            ),
+            (
+                "education_distribution.some_college",
+                Some(education.some_college),
+            ),
+            (
+                "education_distribution.bachelors",
+                Some(education.bachelors),
+            ),
+            ("education_distribution.graduate", Some(education.graduate)),
+        ]);
+    }
+    if let Some(race) = &overrides.race_distribution {
+        percentages.extend([
+            ("race_distribution.white", Some(race.white)),
+            ("race_distribution.black", Some(race.black)),
+            ("race_distribution.asian", Some(race.asian)),
+            ("race_distribution.mixed", Some(race.mixed)),
+            ("race_distribution.hispanic", Some(race.hispanic)),
+        ]);
+    }
+    if let Some(commute) = &overrides.commute {
+        non_negative.push(("commute.avg_minutes", commute.avg_minutes));
+        percentages.extend([
+            ("commute.car_dependence", commute.car_dependence),
+            ("commute.transit_usage", commute.transit_usage),
+        ]);
+    }
+
+    for (name, value) in non_negative {
+        if let Some(value) = value
+            && !(value.is_finite() && value >= 0.0)
+        {
+            return Err(format!(
+                "{} must be a non-negative number, got {}",
+                name, value
+            ));
+        }
+    }
+    for (name, value) in percentages {
+        if let Some(value) = value
+            && !(0.0..=100.0).contains(&value)
+        {
+            return Err(format!("{} must be between 0 and 100, got {}", name, value));
+        }
+    }
+
+    Ok(())
+}
+
+/// Range a populated neighborhood's education or race shares must sum to
+///
+/// The census-derived source data rounds each share separately, so sums drift
+/// several points from 100.
+const DISTRIBUTION_SUM_RANGE: std::ops::RangeInclusive<f64> = 80.0..=120.0;
+
+/// Checks that client-supplied neighborhood properties are plausible baseline data
+///
+/// The name must be set, area must be positive, every metric must pass
+/// [`validate_metric_overrides`], and a populated neighborhood's education and race
+/// shares must each sum to roughly 100 (all zeros is accepted for unpopulated ones).
+///
+/// # Errors
+///
+/// Returns a description of the first problem.
+pub fn validate_neighborhood_properties(properties: &NeighborhoodProperties) -> Result<(), String> {
+    if properties.name.trim().is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+    if !(properties.area_acres.is_finite() && properties.area_acres > 0.0) {
+        return Err(format!(
+            "area_acres must be a positive number, got {}",
+            properties.area_acres
+        ));
+    }
+
+    validate_metric_overrides(&NeighborhoodMetrics {
+        zone_id: properties.name.clone(),
+        zone_name: properties.name.clone(),
+        population_total: Some(properties.population_total),
+        median_age: Some(properties.median_age),
+        population_density: Some(properties.population_density),
+        median_income: Some(properties.median_income),
+        median_home_value: Some(properties.median_home_value),
+        affordability_index: Some(properties.affordability_index),
+        housing_units: Some(properties.housing_units),
+        households: Some(properties.households),
+        vacant_units: Some(properties.vacant_units),
+        vacancy_rate: Some(properties.vacancy_rate),
+        owner_occupancy: Some(properties.owner_occupancy),
+        housing_density: Some(properties.housing_density),
+        education_distribution: Some(properties.education_distribution.clone()),
+        race_distribution: Some(properties.race_distribution.clone()),
+        diversity_index: Some(properties.diversity_index),
+        livability_index: Some(properties.livability_index),
+        commute: Some(PartialCommute {
+            avg_minutes: Some(properties.commute.avg_minutes),
+            car_dependence: Some(properties.commute.car_dependence),
+            transit_usage: Some(properties.commute.transit_usage),
+        }),
+        derived: None,
+    })?;
+
+    if properties.population_total > 0 {
+        let education = &properties.education_distribution;
+        let race = &properties.race_distribution;
+        for (name, sum) in [
+            (
+                "education_distribution",
+                education.high_school_or_less
+                    + education.some_college
+                    + education.bachelors
+                    + education.graduate,
+            ),
+            (
+                "race_distribution",
+                race.white + race.black + race.asian + race.mixed + race.hispanic,
+            ),
+        ] {
+            if !DISTRIBUTION_SUM_RANGE.contains(&sum) && sum != 0.0 {
+                return Err(format!(
+                    "{} shares must sum to about 100, got {:.1}",
+                    name, sum
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Merges manual metric overrides onto a neighborhood's baseline properties
+///
+/// Derived fields that depend on overridden metrics are recomputed first (see
+/// [`complete_interdependent_metrics`]), so the merged baseline stays consistent.
+///
+/// # Arguments
+///
+/// * `properties` - The baseline to update in place
+/// * `overrides` - Partial metrics to apply; `None` fields keep the baseline value
+pub fn apply_metric_overrides(
+    properties: &mut NeighborhoodProperties,
+    overrides: &NeighborhoodMetrics,
+) {
+    let mut overrides = overrides.clone();
+    complete_interdependent_metrics(&mut overrides, properties);
+
+    macro_rules! merge {
+        ($($field:ident),* $(,)?) => {
+            $(
+                if let Some(value) = overrides.$field.take() {
+                    properties.$field = value;
+                }
+            )*
+        };
+    }
+
+    merge!(
+        population_total,
+        median_age,
+        population_density,
+        median_income,
+        median_home_value,
+        affordability_index,
+        housing_units,
+        households,
+        vacant_units,
+        vacancy_rate,
+        owner_occupancy,
+        housing_density,
+        education_distribution,
+        race_distribution,
+        diversity_index,
+        livability_index,
+        derived,
+    );
+
+    if let Some(commute) = overrides.commute.take() {
+        properties.commute = commute.resolve(&properties.commute);
+    }
+}
+
+/// Averages a metric across neighborhoods, weighting each by `weight_fn`
+///
+/// Negative or non-finite weights count as zero. If every weight is zero the plain
+/// mean is returned instead, so neighborhoods without population data still aggregate.
+///
+/// # Arguments
+///
+/// * `neighborhoods` - The neighborhoods to aggregate
+/// * `weight_fn` - Weight of each neighborhood (e.g. its population)
+/// * `value_fn` - The metric to average
+///
+/// # Returns
+///
+/// The weighted average, or 0.0 if `neighborhoods` is empty
+pub fn weighted_average<W, V>(
+    neighborhoods: &[NeighborhoodProperties],
+    weight_fn: W,
+    value_fn: V,
+) -> f64
+where
+    W: Fn(&NeighborhoodProperties) -> f64,
+    V: Fn(&NeighborhoodProperties) -> f64,
+{
+    if neighborhoods.is_empty() {
+        return 0.0;
+    }
+
+    let (weighted_sum, total_weight) =
+        neighborhoods
+            .iter()
+            .fold((0.0, 0.0), |(sum, total), neighborhood| {
+                let weight = weight_fn(neighborhood);
+                let weight = if weight.is_finite() && weight > 0.0 {
+                    weight
+                } else {
+                    0.0
+                };
+                (sum + weight * value_fn(neighborhood), total + weight)
+            });
+
+    if total_weight > 0.0 {
+        weighted_sum / total_weight
+    } else {
+        neighborhoods.iter().map(&value_fn).sum::<f64>() / neighborhoods.len() as f64
+    }
+}
+
+/// Averages a metric across neighborhoods, weighted by `population_total`
+///
+/// See [`weighted_average`] for the zero-population fallback.
+pub fn population_weighted_average<V>(neighborhoods: &[NeighborhoodProperties], value_fn: V) -> f64
+where
+    V: Fn(&NeighborhoodProperties) -> f64,
+{
+    weighted_average(neighborhoods, |n| n.population_total as f64, value_fn)
+}
+
+/// Minimum relative change for counts (population, households, housing units)
+const MIN_COUNT_CHANGE_RATIO: f64 = 0.005;
+/// Minimum absolute change for counts, regardless of baseline size
+const MIN_COUNT_CHANGE: f64 = 25.0;
+/// Minimum relative change for currency metrics (median income, home value)
+const MIN_CURRENCY_CHANGE_RATIO: f64 = 0.02;
+/// Minimum absolute change in dollars for currency metrics
+const MIN_CURRENCY_CHANGE: f64 = 500.0;
+/// Minimum change in percentage points for rates and distribution shares
+const MIN_RATE_CHANGE_POINTS: f64 = 1.0;
+/// Minimum change for commute minutes, median age, and similar scalars
+const MIN_SCALAR_CHANGE: f64 = 0.5;
+/// Minimum absolute change for densities (per acre), regardless of baseline size
+const MIN_DENSITY_CHANGE: f64 = 0.1;
+
+fn count_changed(updated: f64, baseline: f64) -> bool {
+    let threshold = (baseline.abs() * MIN_COUNT_CHANGE_RATIO).max(MIN_COUNT_CHANGE);
+    (updated - baseline).abs() >= threshold
+}
+
+fn currency_changed(updated: f64, baseline: f64) -> bool {
+    let threshold = (baseline.abs() * MIN_CURRENCY_CHANGE_RATIO).max(MIN_CURRENCY_CHANGE);
+    (updated - baseline).abs() >= threshold
+}
+
+fn density_changed(updated: f64, baseline: f64) -> bool {
+    let threshold = (baseline.abs() * MIN_COUNT_CHANGE_RATIO).max(MIN_DENSITY_CHANGE);
+    (updated - baseline).abs() >= threshold
+}
+
+fn rate_changed(updated: f64, baseline: f64) -> bool {
+    (updated - baseline).abs() >= MIN_RATE_CHANGE_POINTS
+}
+
+fn scalar_changed(updated: f64, baseline: f64) -> bool {
+    (updated - baseline).abs() >= MIN_SCALAR_CHANGE
+}
+
+/// Checks whether a partial metrics update contains at least one meaningful change
+///
+/// The Phase 2 prompt asks the model to move concrete metrics by a minimum amount
+/// and to skip neighborhoods with no measurable change. This enforces those rules:
+/// - Populations, households, housing and vacant units: ≥0.5% of baseline (minimum 25)
+/// - Currency (median income, median home value): ≥2% of baseline or ≥$500, whichever is greater
+/// - Rates and distribution shares: ≥1 percentage point
+/// - Commute minutes and median age: ≥0.5
+/// - Densities: ≥0.5% of baseline (minimum 0.1 per acre)
+///
+/// Abstract indices (livability, affordability, diversity) and `derived` values never
+/// count as meaningful on their own.
+///
+/// # Arguments
+///
+/// * `metrics` - The partial metrics update emitted by the model
+/// * `baseline` - The neighborhood data the update should be compared against
+///
+/// # Returns
+///
+/// `true` if any concrete metric changes by at least its threshold
+pub fn has_meaningful_change(
+    metrics: &NeighborhoodMetrics,
+    baseline: &NeighborhoodProperties,
+) -> bool {
+    let count_changes = [
+        (metrics.population_total, baseline.population_total),
+        (metrics.housing_units, baseline.housing_units),
+        (metrics.households, baseline.households),
+        (metrics.vacant_units, baseline.vacant_units),
+    ];
+    if count_changes
+        .iter()
+        .any(|&(updated, base)| updated.is_some_and(|v| count_changed(v as f64, base as f64)))
+    {
+        return true;
+    }
+
+    let currency_changes = [
+        (metrics.median_income, baseline.median_income),
+        (metrics.median_home_value, baseline.median_home_value),
+    ];
+    if currency_changes
+        .iter()
+        .any(|&(updated, base)| updated.is_some_and(|v| currency_changed(v as f64, base as f64)))
+    {
+        return true;
+    }
+
+    let density_changes = [
+        (metrics.population_density, baseline.population_density),
+        (metrics.housing_density, baseline.housing_density),
+    ];
+    if density_changes
+        .iter()
+        .any(|&(updated, base)| updated.is_some_and(|v| density_changed(v, base)))
+    {
+        return true;
+    }
+
+    let rate_changes = [
+        (metrics.vacancy_rate, baseline.vacancy_rate),
+        (metrics.owner_occupancy, baseline.owner_occupancy),
+    ];
+    if rate_changes
+        .iter()
+        .any(|&(updated, base)| updated.is_some_and(|v| rate_changed(v, base)))
+    {
+        return true;
+    }
+
+    if metrics
+        .median_age
+        .is_some_and(|v| scalar_changed(v, baseline.median_age))
+    {
+        return true;
+    }
+
+    if let Some(ref edu) = metrics.education_distribution {
+        let base = &baseline.education_distribution;
+        if rate_changed(edu.high_school_or_less, base.high_school_or_less)
+            || rate_changed(edu.some_college, base.some_college)
+            || rate_changed(edu.bachelors, base.bachelors)
+            || rate_changed(edu.graduate, base.graduate)
+        {
+            return true;
+        }
+    }
+
+    if let Some(ref race) = metrics.race_distribution {
+        let base = &baseline.race_distribution;
+        if rate_changed(race.white, base.white)
+            || rate_changed(race.black, base.black)
+            || rate_changed(race.asian, base.asian)
+            || rate_changed(race.mixed, base.mixed)
+            || rate_changed(race.hispanic, base.hispanic)
+        {
+            return true;
+        }
+    }
+
+    if let Some(ref commute) = metrics.commute {
+        let base = &baseline.commute;
+        if commute
+            .avg_minutes
+            .is_some_and(|v| scalar_changed(v, base.avg_minutes))
+            || commute
+                .car_dependence
+                .is_some_and(|v| rate_changed(v, base.car_dependence))
+            || commute
+                .transit_usage
+                .is_some_and(|v| rate_changed(v, base.transit_usage))
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Common words ignored when matching a policy against current events
+const KEYWORD_STOPWORDS: &[&str] = &[
+    "about",
+    "after",
+    "along",
+    "also",
+    "area",
+    "areas",
+    "city",
+    "from",
+    "have",
+    "into",
+    "more",
+    "near",
+    "neighborhood",
+    "neighborhoods",
+    "other",
+    "over",
+    "policy",
+    "such",
+    "than",
+    "that",
+    "their",
+    "them",
+    "these",
+    "this",
+    "through",
+    "under",
+    "with",
+    "would",
+];
+
+/// Lowercased words of at least four letters, excluding common filler words
+fn keywords(text: &str) -> std::collections::HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| word.chars().count() >= 4 && !KEYWORD_STOPWORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Returns the policy keywords that appear in a neighborhood's current events, sorted
+///
+/// A simple word overlap, used to point Phase 1 at neighborhoods whose ongoing
+/// situation relates to the policy (e.g. "ongoing transit construction" for a
+/// transit proposal).
+pub fn current_event_overlap(policy: &str, current_events: &[String]) -> Vec<String> {
+    let policy_keywords = keywords(policy);
+    let mut overlap: Vec<String> = keywords(&current_events.join(" "))
+        .into_iter()
+        .filter(|word| policy_keywords.contains(word))
+        .collect();
+    overlap.sort();
+    overlap
 }
 
 /// Formats minimal neighborhood context into a human-readable string for Phase 1
 ///
 /// Converts minimal neighborhood context (name + contextual fields) into a formatted
 /// text description for the LLM to identify which neighborhoods should have events.
+/// Neighborhoods whose current events share keywords with `policy` get a
+/// `Policy Relevance` line naming them (see [`current_event_overlap`]).
 ///
 /// # Arguments
 ///
 /// * `context` - Slice of minimal neighborhood context to format
+/// * `policy` - The policy prompt; empty adds no relevance lines
 ///
 /// # Returns
 ///
 /// A formatted string with minimal neighborhood data, or a fallback message
 /// if no context is provided
-pub fn build_minimal_context(context: &[MinimalNeighborhoodContext]) -> String {
+pub fn build_minimal_context(context: &[MinimalNeighborhoodContext], policy: &str) -> String {
+    build_minimal_context_with_fields(context, policy, ContextualFields::ALL)
+}
+
+/// Like [`build_minimal_context`], including only the `included` contextual fields
+///
+/// The `Policy Relevance` line is only added when current events are included.
+pub fn build_minimal_context_with_fields(
+    context: &[MinimalNeighborhoodContext],
+    policy: &str,
+    included: ContextualFields,
+) -> String {
+    build_minimal_context_with_hints(context, policy, included, &[])
+}
+
+/// Like [`build_minimal_context_with_fields`], with a `Scale` line for every
+/// neighborhood that has properties in `hints`
+///
+/// The line gives the population (to the nearest hundred) and a median income
+/// bracket, so Phase 1 can tell a small neighborhood from a large one when a
+/// policy's impact depends on scale, for a few tokens per neighborhood.
+pub fn build_minimal_context_with_hints(
+    context: &[MinimalNeighborhoodContext],
+    policy: &str,
+    included: ContextualFields,
+    hints: &[NeighborhoodProperties],
+) -> String {
     if context.is_empty() {
-        return "No specific neighborhood data provided. Use general Atlanta neighborhood characteristics.".to_string();
+        return NO_NEIGHBORHOOD_DATA.to_string();
     }
 
+    let hints: std::collections::HashMap<&str, &NeighborhoodProperties> =
+        hints.iter().map(|n| (n.name.as_str(), n)).collect();
     context
         .iter()
         .map(|n| {
-            let neighbors = n.neighboring_neighborhoods.as_ref()
-                .map(|v| v.join(", "))
-                .unwrap_or_else(|| "None specified".to_string());
-            let current_events = n.current_events.as_ref()
-                .map(|v| v.join("; "))
-                .unwrap_or_else(|| "None specified".to_string());
-            let baseline = n.baseline_description.as_ref()
-                .map(|s| s.as_str())
-                .unwrap_or("No baseline description available");
-
-            format!(
-                "Neighborhood: {}\nBaseline Description: {}\nCurrent Events: {}\nNeighboring Neighborhoods: {}",
-                n.name, baseline, current_events, neighbors
-            )
+            let mut lines = vec![neighborhood_line(&n.name)];
+            if let Some(properties) = hints.get(n.name.as_str()) {
+                lines.push(scale_hint_line(properties));
+            }
+            if included.baseline_description {
+                lines.push(baseline_description_line(n.baseline_description.as_deref()));
+            }
+            if included.current_events {
+                lines.push(current_events_line(n.current_events.as_deref()));
+            }
+            if included.neighbors {
+                lines.push(neighbors_line(n.neighboring_neighborhoods.as_deref()));
+            }
+            let overlap = n
+                .current_events
+                .as_deref()
+                .filter(|_| included.current_events)
+                .map(|events| current_event_overlap(policy, events))
+                .unwrap_or_default();
+            if !overlap.is_empty() {
+                lines.push(format!(
+                    "Policy Relevance: current events mention {}",
+                    overlap.join(", ")
+                ));
+            }
+            lines.join("\n")
         })
         .collect::<Vec<_>>()
         .join("\n\n---\n\n")
 }
 
+/// Upper bounds (exclusive) and labels of the median income brackets in `Scale` lines
+const INCOME_BRACKETS: [(i32, &str); 5] = [
+    (25_000, "under $25k"),
+    (50_000, "$25k-$50k"),
+    (75_000, "$50k-$75k"),
+    (100_000, "$75k-$100k"),
+    (150_000, "$100k-$150k"),
+];
+
+/// Formats the `Scale` line of Phase 1 numeric hints for one neighborhood
+fn scale_hint_line(properties: &NeighborhoodProperties) -> String {
+    let income = INCOME_BRACKETS
+        .iter()
+        .find(|(below, _)| properties.median_income < *below)
+        .map_or("$150k+", |(_, label)| label);
+    let population = (properties.population_total + 50) / 100 * 100;
+    format!(
+        "Scale: population ~{}, median income {}",
+        population, income
+    )
+}
+
+/// How decimal metrics are written in the neighborhood context
+///
+/// Dollar amounts and counts are whole numbers and always written in full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContextPrecision {
+    /// Rounded to one or two decimals
+    #[default]
+    Compact,
+    /// Every stored digit, so the model sees the exact baseline
+    Full,
+}
+
+impl ContextPrecision {
+    /// Returns [`ContextPrecision::Full`] when `CONTEXT_FULL_PRECISION` is enabled
+    pub fn from_config(config: &SimulationConfig) -> Self {
+        if config.context_full_precision {
+            Self::Full
+        } else {
+            Self::Compact
+        }
+    }
+
+    /// Formats `value`, rounded to `decimals` places in compact mode
+    fn format(self, value: f64, decimals: usize) -> String {
+        match self {
+            Self::Compact => format!("{:.*}", decimals, value),
+            Self::Full => value.to_string(),
+        }
+    }
+}
+
+/// Optional parts of the Phase 2 neighborhood context that can be dropped to save tokens
+///
+/// Names and core housing and economic metrics are always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContextTrim {
+    CurrentEvents,
+    Distributions,
+    BaselineDescription,
+    CommuteAndNeighbors,
+}
+
+impl ContextTrim {
+    fn label(self) -> &'static str {
+        match self {
+            ContextTrim::CurrentEvents => "current events",
+            ContextTrim::Distributions => "education and race distributions",
+            ContextTrim::BaselineDescription => "baseline descriptions",
+            ContextTrim::CommuteAndNeighbors => "commute and neighboring neighborhoods",
+        }
+    }
+}
+
+/// Order in which context parts are dropped when the context exceeds its budget
+const CONTEXT_TRIM_ORDER: [ContextTrim; 4] = [
+    ContextTrim::CurrentEvents,
+    ContextTrim::Distributions,
+    ContextTrim::BaselineDescription,
+    ContextTrim::CommuteAndNeighbors,
+];
+
+/// Opens a block of client-supplied text in a prompt; see [`quote_client_data`]
+pub const CLIENT_DATA_OPEN: &str = "<client_data>";
+
+/// Closes a block opened by [`CLIENT_DATA_OPEN`]
+pub const CLIENT_DATA_CLOSE: &str = "</client_data>";
+
+/// Wraps client-supplied text in delimiters the system prompts mark as data only
+///
+/// Names, baseline descriptions, and current events come straight from the request, so a
+/// client could otherwise smuggle instructions into the system prompt. The text is
+/// written as an escaped JSON string, so it cannot break out onto its own line or
+/// close the block early: quotes, backslashes, and control characters are escaped,
+/// and so are `<` and `>`.
+pub fn quote_client_data(text: &str) -> String {
+    let escaped = serde_json::to_string(text)
+        .unwrap_or_default()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e");
+    format!("{}{}{}", CLIENT_DATA_OPEN, escaped, CLIENT_DATA_CLOSE)
+}
+
+/// Formats the `Neighborhood` line, quoting the name since clients can supply it
+fn neighborhood_line(name: &str) -> String {
+    format!("Neighborhood: {}", quote_client_data(name))
+}
+
+/// Formats the `Neighboring Neighborhoods` line, quoting each of the client's names
+fn neighbors_line(neighbors: Option<&[String]>) -> String {
+    let neighbors = neighbors.map_or_else(
+        || "None specified".to_string(),
+        |neighbors| {
+            neighbors
+                .iter()
+                .map(|name| quote_client_data(name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        },
+    );
+    format!("Neighboring Neighborhoods: {}", neighbors)
+}
+
+/// Formats the `Baseline Description` line, quoting the client's description
+fn baseline_description_line(description: Option<&str>) -> String {
+    let baseline = description.map_or_else(
+        || "No baseline description available".to_string(),
+        quote_client_data,
+    );
+    format!("Baseline Description: {}", baseline)
+}
+
+/// Formats the `Current Events` line, quoting each of the client's events
+fn current_events_line(events: Option<&[String]>) -> String {
+    let current_events = events.map_or_else(
+        || "None specified".to_string(),
+        |events| {
+            events
+                .iter()
+                .map(|event| quote_client_data(event))
+                .collect::<Vec<_>>()
+                .join("; ")
+        },
+    );
+    format!("Current Events: {}", current_events)
+}
+
+const NO_NEIGHBORHOOD_DATA: &str =
+    "No specific neighborhood data provided. Use general Atlanta neighborhood characteristics.";
+
+/// Formats one neighborhood's properties, keeping only the selected `fields` groups
+/// (all of them if empty) and `included` contextual fields, and leaving out the
+/// `trimmed` parts
+fn format_neighborhood_context(
+    n: &NeighborhoodProperties,
+    fields: &[ContextField],
+    included: ContextualFields,
+    trimmed: &[ContextTrim],
+    precision: ContextPrecision,
+) -> String {
+    let includes = |field: ContextField| fields.is_empty() || fields.contains(&field);
+    let area_sq_miles = n.area_acres / 640.0;
+    let mut lines = vec![
+        neighborhood_line(&n.name),
+        format!("Area: {} sq miles", precision.format(area_sq_miles, 2)),
+    ];
+
+    if includes(ContextField::Demographics) {
+        lines.push(format!("Population: {}", n.population_total));
+    }
+    if includes(ContextField::Economics) {
+        lines.push(format!("Median Income: ${}", n.median_income));
+    }
+    if includes(ContextField::Housing) {
+        lines.push(format!("Median Home Value: ${}", n.median_home_value));
+        lines.push(format!("Housing Units: {}", n.housing_units));
+        lines.push(format!(
+            "Vacancy Rate: {}%",
+            precision.format(n.vacancy_rate, 1)
+        ));
+        lines.push(format!(
+            "Owner Occupancy: {}%",
+            precision.format(n.owner_occupancy, 1)
+        ));
+    }
+    if includes(ContextField::Demographics) {
+        lines.push(format!(
+            "Diversity Index: {}",
+            precision.format(n.diversity_index, 2)
+        ));
+    }
+    lines.push(format!(
+        "Livability Index: {}",
+        precision.format(n.livability_index, 1)
+    ));
+
+    if includes(ContextField::Commute) && !trimmed.contains(&ContextTrim::CommuteAndNeighbors) {
+        lines.push(format!(
+            "Average Commute: {} minutes",
+            precision.format(n.commute.avg_minutes, 1)
+        ));
+        lines.push(format!(
+            "Car Dependence: {}%",
+            precision.format(n.commute.car_dependence, 1)
+        ));
+        lines.push(format!(
+            "Transit Usage: {}%",
+            precision.format(n.commute.transit_usage, 1)
+        ));
+    }
+
+    if includes(ContextField::Distributions) && !trimmed.contains(&ContextTrim::Distributions) {
+        lines.push(format!(
+            "Education: {}% Bachelor's+, {}% Graduate",
+            precision.format(n.derived.higher_ed_percent, 1),
+            precision.format(n.education_distribution.graduate, 1)
+        ));
+        let race = &n.race_distribution;
+        lines.push(format!(
+            "Race Distribution: White {}%, Black {}%, Asian {}%, Mixed {}%, Hispanic {}%",
+            precision.format(race.white, 1),
+            precision.format(race.black, 1),
+            precision.format(race.asian, 1),
+            precision.format(race.mixed, 1),
+            precision.format(race.hispanic, 1)
+        ));
+    }
+
+    if included.baseline_description && !trimmed.contains(&ContextTrim::BaselineDescription) {
+        lines.push(baseline_description_line(n.baseline_description.as_deref()));
+    }
+
+    if included.current_events && !trimmed.contains(&ContextTrim::CurrentEvents) {
+        lines.push(current_events_line(n.current_events.as_deref()));
+    }
+
+    if included.neighbors && !trimmed.contains(&ContextTrim::CommuteAndNeighbors) {
+        lines.push(neighbors_line(n.neighboring_neighborhoods.as_deref()));
+    }
+
+    lines.join("\n")
+}
+
+fn join_neighborhood_contexts(
+    properties: &[NeighborhoodProperties],
+    fields: &[ContextField],
+    included: ContextualFields,
+    trimmed: &[ContextTrim],
+    precision: ContextPrecision,
+) -> String {
+    properties
+        .iter()
+        .map(|n| format_neighborhood_context(n, fields, included, trimmed, precision))
+        .collect::<Vec<_>>()
+        .join("\n\n---\n\n")
+}
+
 /// Formats neighborhood properties into a human-readable context string
 ///
 /// Converts the structured neighborhood data into a formatted text description
@@ -119,48 +1066,115 @@ pub fn build_minimal_context(context: &[MinimalNeighborhoodContext]) -> String {
 /// # Arguments
 ///
 /// * `properties` - Slice of neighborhood properties to format
+/// * `fields` - Metric groups to include; empty includes every group
 ///
 /// # Returns
 ///
-/// A formatted string with all neighborhood data, or a fallback message
+/// A formatted string with the selected neighborhood data, or a fallback message
 /// if no properties are provided
-pub fn build_neighborhoods_context(properties: &[NeighborhoodProperties]) -> String {
+pub fn build_neighborhoods_context(
+    properties: &[NeighborhoodProperties],
+    fields: &[ContextField],
+) -> String {
+    build_neighborhoods_context_with_precision(properties, fields, ContextPrecision::Compact)
+}
+
+/// Like [`build_neighborhoods_context`], writing decimal metrics at `precision`
+pub fn build_neighborhoods_context_with_precision(
+    properties: &[NeighborhoodProperties],
+    fields: &[ContextField],
+    precision: ContextPrecision,
+) -> String {
     if properties.is_empty() {
-        return "No specific neighborhood data provided. Use general Atlanta neighborhood characteristics.".to_string();
+        return NO_NEIGHBORHOOD_DATA.to_string();
     }
 
-    properties
-        .iter()
-        .map(|n| {
-            let area_sq_miles = n.area_acres / 640.0;
-            let neighbors = n.neighboring_neighborhoods.as_ref()
-                .map(|v| v.join(", "))
-                .unwrap_or_else(|| "None specified".to_string());
-            let current_events = n.current_events.as_ref()
-                .map(|v| v.join("; "))
-                .unwrap_or_else(|| "None specified".to_string());
-            let baseline = n.baseline_description.as_ref()
-                .map(|s| s.as_str())
-                .unwrap_or("No baseline description available");
-
-            format!(
-                "Neighborhood: {}\nArea: {:.2} sq miles\nPopulation: {}\nMedian Income: ${}\n\
-                 Median Home Value: ${}\nHousing Units: {}\nVacancy Rate: {:.1}%\n\
-                 Owner Occupancy: {:.1}%\nDiversity Index: {:.2}\nLivability Index: {:.1}\n\
-                 Average Commute: {:.1} minutes\nCar Dependence: {:.1}%\nTransit Usage: {:.1}%\n\
-                 Education: {:.1}% Bachelor's+, {:.1}% Graduate\n\
-                 Race Distribution: White {:.1}%, Black {:.1}%, Asian {:.1}%, Mixed {:.1}%, Hispanic {:.1}%\n\
-                 Baseline Description: {}\nCurrent Events: {}\nNeighboring Neighborhoods: {}",
-                n.name, area_sq_miles, n.population_total, n.median_income, n.median_home_value,
-                n.housing_units, n.vacancy_rate, n.owner_occupancy, n.diversity_index,
-                n.livability_index, n.commute.avg_minutes, n.commute.car_dependence,
-                n.commute.transit_usage, n.derived.higher_ed_percent, n.education_distribution.graduate,
-                n.race_distribution.white, n.race_distribution.black, n.race_distribution.asian,
-                n.race_distribution.mixed, n.race_distribution.hispanic, baseline, current_events, neighbors
-            )
-        })
-        .collect::<Vec<_>>()
-        .join("\n\n---\n\n")
+    join_neighborhood_contexts(properties, fields, ContextualFields::ALL, &[], precision)
+}
+
+/// Roughly estimates how many LLM tokens a piece of text will use
+///
+/// Uses the common approximation of four characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Formats neighborhood properties into a context string that fits a token budget
+///
+/// Starts from the full [`build_neighborhoods_context`] output and, while the
+/// estimated size exceeds `max_tokens`, progressively drops the least relevant
+/// parts: current events, then distributions, then baseline descriptions, then
+/// commute and neighbor lists. Names and core metrics are always kept, so the
+/// result may still exceed the budget for very large neighborhood sets.
+///
+/// # Arguments
+///
+/// * `properties` - Slice of neighborhood properties to format
+/// * `fields` - Metric groups to include; empty includes every group
+/// * `included` - Contextual fields to include before any trimming
+/// * `max_tokens` - Estimated token budget for the formatted context
+/// * `precision` - How decimal metrics are written
+///
+/// # Returns
+///
+/// The most detailed context string that fits within the budget
+pub fn build_neighborhoods_context_within_budget(
+    properties: &[NeighborhoodProperties],
+    fields: &[ContextField],
+    included: ContextualFields,
+    max_tokens: usize,
+    precision: ContextPrecision,
+) -> String {
+    if properties.is_empty() {
+        return NO_NEIGHBORHOOD_DATA.to_string();
+    }
+    let mut context = join_neighborhood_contexts(properties, fields, included, &[], precision);
+    if estimate_tokens(&context) <= max_tokens {
+        return context;
+    }
+
+    for trim_count in 1..=CONTEXT_TRIM_ORDER.len() {
+        let trimmed = &CONTEXT_TRIM_ORDER[..trim_count];
+        context = join_neighborhood_contexts(properties, fields, included, trimmed, precision);
+
+        let dropped = trimmed
+            .iter()
+            .map(|t| t.label())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let estimated = estimate_tokens(&context);
+
+        if estimated <= max_tokens {
+            logln!(
+                "   ✂️  Trimmed neighborhood context to ~{} tokens (budget {}): dropped {}",
+                estimated,
+                max_tokens,
+                dropped
+            );
+            return context;
+        }
+
+        if trim_count == CONTEXT_TRIM_ORDER.len() {
+            logln!(
+                "   ⚠️  Neighborhood context is ~{} tokens after dropping {} (budget {})",
+                estimated,
+                dropped,
+                max_tokens
+            );
+        }
+    }
+
+    context
+}
+
+/// Removes a surrounding markdown code fence (```` ```json ```` or ```` ``` ````) from model output
+pub fn strip_markdown_fences(content: &str) -> &str {
+    let content = content.trim();
+    let content = content
+        .strip_prefix("```json")
+        .or_else(|| content.strip_prefix("```"))
+        .unwrap_or(content);
+    content.trim_end_matches("```").trim()
 }
 
 /// State machine for parsing JSON array chunks from a streaming response
@@ -168,6 +1182,10 @@ pub fn build_neighborhoods_context(properties: &[NeighborhoodProperties]) -> Str
 /// This parser tracks bracket depth to extract complete JSON objects from
 /// a streaming JSON array. It handles string escaping and maintains state
 /// across character-by-character parsing.
+///
+/// Text around the array is ignored, so markdown fences and prose before or
+/// after it are harmless. A `[` in leading prose that is not followed by an
+/// object (e.g. "see [1]") is treated as a false start and skipped.
 pub struct JsonArrayChunkParser {
     chunk_buffer: String,
     depth: i32,
@@ -175,6 +1193,8 @@ pub struct JsonArrayChunkParser {
     in_string: bool,
     escape_next: bool,
     collecting_chunk: bool,
+    chunk_found: bool,
+    array_closed: bool,
 }
 
 impl JsonArrayChunkParser {
@@ -187,6 +1207,8 @@ impl JsonArrayChunkParser {
             in_string: false,
             escape_next: false,
             collecting_chunk: false,
+            chunk_found: false,
+            array_closed: false,
         }
     }
 
@@ -201,6 +1223,10 @@ impl JsonArrayChunkParser {
     /// `Some(String)` if a complete JSON chunk was found, `None` otherwise.
     /// The returned string is the complete JSON object that can be parsed.
     pub fn process_char(&mut self, ch: char) -> Option<String> {
+        if self.array_closed {
+            return None;
+        }
+
         if !self.json_started {
             if ch == '[' {
                 self.json_started = true;
@@ -209,6 +1235,17 @@ impl JsonArrayChunkParser {
             return None;
         }
 
+        if self.depth == 1
+            && !self.chunk_found
+            && !self.collecting_chunk
+            && !matches!(ch, '{' | ']' | ',')
+            && !ch.is_whitespace()
+        {
+            self.json_started = false;
+            self.depth = 0;
+            return self.process_char(ch);
+        }
+
         let mut should_push = self.collecting_chunk;
         let mut finalize_chunk = false;
 
@@ -249,9 +1286,10 @@ impl JsonArrayChunkParser {
                         self.chunk_buffer.clear();
                     }
                 }
-                ']' => {
-                    if self.depth > 0 {
-                        self.depth -= 1;
+                ']' if self.depth > 0 => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        self.array_closed = true;
                     }
                 }
                 '}' => {
@@ -274,11 +1312,83 @@ impl JsonArrayChunkParser {
             let chunk_json = self.chunk_buffer.clone();
             self.chunk_buffer.clear();
             self.collecting_chunk = false;
+            self.chunk_found = true;
             return Some(chunk_json);
         }
 
         None
     }
+
+    /// Whether a chunk object is currently being collected
+    pub fn is_collecting(&self) -> bool {
+        self.collecting_chunk
+    }
+
+    /// Whether the array was opened but never closed
+    ///
+    /// Call this after the input ends to detect a truncated stream.
+    pub fn is_incomplete(&self) -> bool {
+        self.json_started && self.depth > 0
+    }
+
+    /// Attempts to recover the chunk being collected when the input was cut off
+    ///
+    /// Closes any open string and nesting on the partial buffer. If that is not
+    /// valid JSON (e.g. the cut fell between a key and its value), the buffer is
+    /// shortened to each earlier `,` in turn until a valid object remains.
+    ///
+    /// # Returns
+    ///
+    /// The repaired JSON object, or `None` if nothing was being collected or no
+    /// valid prefix exists
+    pub fn salvage_partial_chunk(&self) -> Option<String> {
+        if !self.collecting_chunk || self.chunk_buffer.is_empty() {
+            return None;
+        }
+
+        let mut open: Vec<char> = Vec::new();
+        let mut in_string = false;
+        let mut escape_next = false;
+        let mut cut_points: Vec<(usize, Vec<char>)> = Vec::new();
+
+        for (index, ch) in self.chunk_buffer.char_indices() {
+            if escape_next {
+                escape_next = false;
+                continue;
+            }
+            match ch {
+                '\\' if in_string => escape_next = true,
+                '"' => in_string = !in_string,
+                '{' | '[' if !in_string => open.push(ch),
+                '}' | ']' if !in_string => {
+                    open.pop();
+                }
+                ',' if !in_string => cut_points.push((index, open.clone())),
+                _ => {}
+            }
+        }
+
+        let close = |prefix: &str, open: &[char], close_string: bool| {
+            let mut repaired = prefix.trim_end().to_string();
+            if close_string {
+                repaired.push('"');
+            }
+            repaired.extend(open.iter().rev().map(|&c| if c == '{' { '}' } else { ']' }));
+            repaired
+        };
+        let is_valid = |json: &str| serde_json::from_str::<serde_json::Value>(json).is_ok();
+
+        let whole = close(&self.chunk_buffer, &open, in_string && !escape_next);
+        if is_valid(&whole) {
+            return Some(whole);
+        }
+
+        cut_points
+            .iter()
+            .rev()
+            .map(|(index, open)| close(&self.chunk_buffer[..*index], open, false))
+            .find(|candidate| is_valid(candidate))
+    }
 }
 
 impl Default for JsonArrayChunkParser {
@@ -287,6 +1397,114 @@ impl Default for JsonArrayChunkParser {
     }
 }
 
+/// Progress of a [`SummaryStreamer`] through the current chunk
+enum SummaryState {
+    /// Looking for a `"summary": "` key
+    Searching,
+    /// Inside the summary string; holds a pending escape sequence, if any
+    InValue(Option<String>),
+    /// The summary string has closed
+    Done,
+}
+
+/// Extracts the completion summary text while its chunk is still streaming
+///
+/// Feed it the characters of each chunk the [`JsonArrayChunkParser`] is collecting
+/// and call [`reset`](SummaryStreamer::reset) when the chunk closes. Once a
+/// `"summary": "` key is seen, the decoded string contents are buffered for
+/// [`take_pending`](SummaryStreamer::take_pending) so the summary can be forwarded
+/// as it is generated rather than after the whole object arrives.
+pub struct SummaryStreamer {
+    chunk_text: String,
+    state: SummaryState,
+    pending: String,
+}
+
+impl SummaryStreamer {
+    pub fn new() -> Self {
+        Self {
+            chunk_text: String::new(),
+            state: SummaryState::Searching,
+            pending: String::new(),
+        }
+    }
+
+    /// Processes one character of the chunk being collected
+    pub fn process_char(&mut self, ch: char) {
+        match &mut self.state {
+            SummaryState::Searching => {
+                if ch == '"' && is_summary_value_start(&self.chunk_text) {
+                    self.state = SummaryState::InValue(None);
+                }
+                self.chunk_text.push(ch);
+            }
+            SummaryState::InValue(Some(escape)) => {
+                escape.push(ch);
+                if let Some(decoded) = decode_escape(escape) {
+                    self.pending.extend(decoded);
+                    self.state = SummaryState::InValue(None);
+                }
+            }
+            SummaryState::InValue(None) => match ch {
+                '\\' => self.state = SummaryState::InValue(Some(String::new())),
+                '"' => self.state = SummaryState::Done,
+                _ => self.pending.push(ch),
+            },
+            SummaryState::Done => {}
+        }
+    }
+
+    /// Returns the summary text decoded since the last call, if any
+    pub fn take_pending(&mut self) -> Option<String> {
+        (!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending))
+    }
+
+    /// Prepares for the next chunk
+    pub fn reset(&mut self) {
+        self.chunk_text.clear();
+        self.state = SummaryState::Searching;
+    }
+}
+
+impl Default for SummaryStreamer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether `text` ends with a `"summary":` key, so a following quote opens its value
+fn is_summary_value_start(text: &str) -> bool {
+    text.trim_end()
+        .strip_suffix(':')
+        .is_some_and(|key| key.trim_end().ends_with("\"summary\""))
+}
+
+/// Decodes a JSON string escape (the characters after the backslash)
+///
+/// # Returns
+///
+/// `None` while more characters are needed, otherwise the decoded character
+/// (`Some(None)` for an invalid escape or half of a surrogate pair, which are dropped)
+fn decode_escape(escape: &str) -> Option<Option<char>> {
+    let decoded = match escape {
+        "n" => '\n',
+        "t" => '\t',
+        "r" => '\r',
+        "b" => '\u{8}',
+        "f" => '\u{c}',
+        _ if escape.starts_with('u') && escape.len() < 5 => return None,
+        _ if escape.starts_with('u') => {
+            return Some(
+                u32::from_str_radix(&escape[1..], 16)
+                    .ok()
+                    .and_then(char::from_u32),
+            );
+        }
+        other => return Some(other.chars().next()),
+    };
+    Some(Some(decoded))
+}
+
 /// Looks up full neighborhood properties by name
 ///
 /// Creates a HashMap from neighborhood names to their full properties
@@ -307,3 +1525,185 @@ pub fn lookup_neighborhoods_by_names(
         .map(|n| (n.name.clone(), n.clone()))
         .collect()
 }
+
+/// Loads the full properties of each Phase 1 target neighborhood
+///
+/// Properties supplied in the request take precedence; the rest are looked up in
+/// the neighborhood database. Request-supplied properties that fail
+/// [`validate_neighborhood_properties`] are ignored with a warning, so those targets
+/// fall back to the database (or are reported missing if it has no such name).
+/// The targets' neighbors are also loaded from the database, so spillover events can
+/// be completed against their baselines (see [`neighbor_properties`]).
+///
+/// # Arguments
+///
+/// * `targets` - Neighborhood names returned by Phase 1
+/// * `request_properties` - Full neighborhood properties sent with the request
+/// * `db` - Neighborhood database used as a fallback
+///
+/// # Returns
+///
+/// The lookup table for Phase 2 (including every request-supplied neighborhood and
+/// the targets' neighbors) and where each target's properties came from
+pub fn resolve_target_neighborhoods(
+    targets: &[String],
+    request_properties: &[NeighborhoodProperties],
+    db: &NeighborhoodDatabase,
+) -> (
+    std::collections::HashMap<String, NeighborhoodProperties>,
+    SimulationTargets,
+) {
+    let mut lookup = std::collections::HashMap::new();
+    for properties in request_properties {
+        match validate_neighborhood_properties(properties) {
+            Ok(()) => {
+                lookup.insert(properties.name.clone(), properties.clone());
+            }
+            Err(reason) if db.find_by_name(&properties.name).is_some() => logln!(
+                "   ⚠️  Request properties for {} are invalid ({}); using database values",
+                properties.name,
+                reason
+            ),
+            Err(reason) => logln!(
+                "   ⚠️  Ignoring invalid request properties for {}: {}",
+                properties.name,
+                reason
+            ),
+        }
+    }
+    let mut resolved = SimulationTargets {
+        neighborhoods: targets.to_vec(),
+        ..SimulationTargets::default()
+    };
+
+    for name in targets {
+        if lookup.contains_key(name) {
+            resolved.from_request.push(name.clone());
+        } else if let Some(neighborhood) = db.find_by_name(name) {
+            lookup.insert(name.clone(), neighborhood);
+            resolved.from_database.push(name.clone());
+        } else {
+            resolved.missing.push(name.clone());
+        }
+    }
+
+    let neighbors: Vec<String> = targets
+        .iter()
+        .filter_map(|name| lookup.get(name)?.neighboring_neighborhoods.clone())
+        .flatten()
+        .collect();
+    for neighbor in neighbors {
+        if !lookup.contains_key(&neighbor)
+            && let Some(neighborhood) = db.find_by_name(&neighbor)
+        {
+            lookup.insert(neighbor, neighborhood);
+        }
+    }
+
+    (lookup, resolved)
+}
+
+/// Returns the known properties of the targets' neighbors that are not targets
+///
+/// Each neighbor appears once, in the order the targets list them.
+pub fn neighbor_properties(
+    targets: &[NeighborhoodProperties],
+    lookup: &std::collections::HashMap<String, NeighborhoodProperties>,
+) -> Vec<NeighborhoodProperties> {
+    let mut neighbors: Vec<NeighborhoodProperties> = Vec::new();
+    for name in targets
+        .iter()
+        .filter_map(|target| target.neighboring_neighborhoods.as_ref())
+        .flatten()
+    {
+        if targets.iter().any(|target| &target.name == name)
+            || neighbors.iter().any(|neighbor| &neighbor.name == name)
+        {
+            continue;
+        }
+        if let Some(neighbor) = lookup.get(name) {
+            neighbors.push(neighbor.clone());
+        }
+    }
+    neighbors
+}
+
+/// Restricts Phase 1 target neighborhoods to the user's selected zones
+///
+/// Names are compared case-insensitively. An empty selection means no restriction.
+///
+/// # Arguments
+///
+/// * `targets` - Neighborhood names returned by Phase 1
+/// * `selected_zones` - Neighborhood names explicitly selected by the user
+///
+/// # Returns
+///
+/// The targets that appear in the selection, in their original order
+pub fn restrict_to_selected_zones(targets: Vec<String>, selected_zones: &[String]) -> Vec<String> {
+    if selected_zones.is_empty() {
+        return targets;
+    }
+
+    targets
+        .into_iter()
+        .filter(|target| {
+            selected_zones
+                .iter()
+                .any(|zone| zone.eq_ignore_ascii_case(target))
+        })
+        .collect()
+}
+
+/// Reads a data file into a string, refusing anything but a regular file of at most
+/// `max_bytes`
+///
+/// Guards startup and request paths against a data file that was accidentally
+/// replaced by something huge, or by a named pipe that would block forever. The
+/// read itself is capped too, since the file may grow after its size is checked.
+///
+/// # Errors
+///
+/// Returns an [`std::io::ErrorKind::InvalidData`] error naming the file and limit if
+/// it is not a regular file or is too large, or the underlying I/O error
+pub fn read_to_string_bounded(path: &std::path::Path, max_bytes: u64) -> std::io::Result<String> {
+    use std::io::Read;
+
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+    let metadata = std::fs::metadata(path)?;
+    if !metadata.is_file() {
+        return Err(invalid(format!("{} is not a regular file", path.display())));
+    }
+    if metadata.len() > max_bytes {
+        return Err(invalid(format!(
+            "{} is {} bytes, over the {} byte limit",
+            path.display(),
+            metadata.len(),
+            max_bytes
+        )));
+    }
+
+    let mut content = String::new();
+    std::fs::File::open(path)?
+        .take(max_bytes + 1)
+        .read_to_string(&mut content)?;
+    if content.len() as u64 > max_bytes {
+        return Err(invalid(format!(
+            "{} grew past the {} byte limit while being read",
+            path.display(),
+            max_bytes
+        )));
+    }
+    Ok(content)
+}
+
+/// Stable 64-bit FNV-1a hash of `text`
+///
+/// Used rather than std's hasher, whose output may change between Rust releases,
+/// wherever a hash is persisted or must be reproducible.
+pub(crate) fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
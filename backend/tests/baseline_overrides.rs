@@ -0,0 +1,37 @@
+use backend::NeighborhoodDatabase;
+use backend::build_neighborhoods_context;
+use backend::types::NeighborhoodMetrics;
+use backend::utils::{apply_metric_overrides, validate_metric_overrides};
+
+#[test]
+fn override_changes_the_phase2_context() {
+    let mut downtown = NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load from the backend directory")
+        .find_by_name("Downtown")
+        .expect("Downtown should exist");
+    let before = build_neighborhoods_context(std::slice::from_ref(&downtown), &[]);
+    let boosted_units = downtown.housing_units + downtown.housing_units / 5;
+
+    let overrides = NeighborhoodMetrics {
+        housing_units: Some(boosted_units),
+        ..NeighborhoodMetrics::default()
+    };
+    assert!(validate_metric_overrides(&overrides).is_ok());
+    apply_metric_overrides(&mut downtown, &overrides);
+    let after = build_neighborhoods_context(std::slice::from_ref(&downtown), &[]);
+
+    assert_ne!(before, after);
+    assert!(after.contains(&format!("Housing Units: {}", boosted_units)));
+}
+
+#[test]
+fn out_of_range_overrides_are_rejected() {
+    let overrides = NeighborhoodMetrics {
+        vacancy_rate: Some(140.0),
+        ..NeighborhoodMetrics::default()
+    };
+
+    let error = validate_metric_overrides(&overrides).unwrap_err();
+
+    assert!(error.contains("vacancy_rate"));
+}
@@ -0,0 +1,23 @@
+use backend::utils::read_to_string_bounded;
+use std::io::ErrorKind;
+
+#[test]
+fn oversized_file_is_rejected_with_a_clear_error() {
+    let path = std::env::temp_dir().join(format!("oversized-{}.json", std::process::id()));
+    std::fs::write(&path, "x".repeat(2048)).unwrap();
+
+    let error = read_to_string_bounded(&path, 1024).unwrap_err();
+    let within_limit = read_to_string_bounded(&path, 4096);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.to_string().contains("over the 1024 byte limit"));
+    assert_eq!(within_limit.unwrap().len(), 2048);
+}
+
+#[test]
+fn directories_are_not_read() {
+    let error = read_to_string_bounded(&std::env::temp_dir(), 1024).unwrap_err();
+
+    assert!(error.to_string().contains("not a regular file"));
+}
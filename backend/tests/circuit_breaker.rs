@@ -0,0 +1,46 @@
+use actix_web::ResponseError;
+use actix_web::http::{StatusCode, header};
+use backend::SimulationError;
+use backend::breaker::{BreakerState, CircuitBreaker};
+use std::time::Duration;
+
+#[test]
+fn breaker_opens_after_repeated_failures_and_closes_after_a_good_trial() {
+    let breaker = CircuitBreaker::new(3, Duration::from_secs(60), Duration::from_millis(50));
+
+    for _ in 0..2 {
+        breaker.record_failure();
+    }
+    assert_eq!(breaker.state(), BreakerState::Closed);
+    assert!(breaker.check().is_ok());
+
+    breaker.record_failure();
+    assert_eq!(breaker.state(), BreakerState::Open);
+    let rejected = breaker.check().err().unwrap();
+    assert!(matches!(rejected, SimulationError::AzureUnavailable { .. }));
+    let response = rejected.error_response();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+    std::thread::sleep(Duration::from_millis(60));
+    assert!(breaker.check().is_ok());
+    assert_eq!(breaker.state(), BreakerState::HalfOpen);
+    assert!(breaker.check().is_err(), "only one trial is let through");
+
+    breaker.record_success();
+    assert_eq!(breaker.state(), BreakerState::Closed);
+    assert!(breaker.check().is_ok());
+}
+
+#[test]
+fn failed_trial_reopens_the_circuit() {
+    let breaker = CircuitBreaker::new(1, Duration::from_secs(60), Duration::from_millis(50));
+    breaker.record_failure();
+    std::thread::sleep(Duration::from_millis(60));
+    assert!(breaker.check().is_ok());
+
+    breaker.record_failure();
+
+    assert_eq!(breaker.state(), BreakerState::Open);
+    assert!(breaker.check().is_err());
+}
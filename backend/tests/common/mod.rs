@@ -0,0 +1,7 @@
+/// Sets `AZURE_API_KEY` to the fake key every mocked Azure test uses
+///
+/// Tests run concurrently, but since they all write the same value the
+/// unsynchronized environment writes cannot change what another test reads.
+pub fn use_test_api_key() {
+    unsafe { std::env::set_var("AZURE_API_KEY", "test-key") };
+}
@@ -0,0 +1,45 @@
+use actix_web::http::header;
+use actix_web::middleware::Compress;
+use actix_web::{App, test, web};
+use backend::SimulationStore;
+use backend::handlers::export_events_geojson;
+use backend::types::{EventNotification, SimulationChunk};
+
+#[actix_web::test]
+async fn gzip_accepting_clients_get_a_compressed_export() {
+    let store = web::Data::new(SimulationStore::new());
+    let id = store.create("Add protected bike lanes");
+    for seq in 1..=20 {
+        store.record(
+            &id,
+            SimulationChunk::Event {
+                data: EventNotification {
+                    id: format!("event-{}", seq),
+                    zone_id: "Midtown".to_string(),
+                    title: "Bike Lane Opens".to_string(),
+                    coordinates: vec![33.784, -84.384],
+                    ..EventNotification::default()
+                },
+            },
+        );
+    }
+    let app = test::init_service(App::new().app_data(store).wrap(Compress::default()).route(
+        "/api/simulate/{id}/events.geojson",
+        web::get().to(export_events_geojson),
+    ))
+    .await;
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/simulate/{}/events.geojson", id))
+        .insert_header((header::ACCEPT_ENCODING, "gzip"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+
+    assert!(response.status().is_success());
+    assert_eq!(
+        response.headers().get(header::CONTENT_ENCODING).unwrap(),
+        "gzip"
+    );
+    let body = test::read_body(response).await;
+    assert_eq!(&body[..2], &[0x1f, 0x8b]);
+}
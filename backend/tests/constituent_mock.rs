@@ -0,0 +1,70 @@
+use backend::constituents::{EventRequest, mock_embedding, public_sentiment};
+use backend::{SimulationConfig, generate_constituent_messages, load_personas, rank_personas};
+
+#[tokio::test]
+async fn mock_mode_selects_personas_without_azure() {
+    let config = SimulationConfig {
+        mock_azure: true,
+        max_message_personas: 3,
+        ..SimulationConfig::default()
+    };
+    let event = EventRequest {
+        title: "Protected Bike Lanes Approved".to_string(),
+        description: "The city approves protected lanes on Peachtree.".to_string(),
+        zone: "Midtown".to_string(),
+        positivity: 0.7,
+        severity: 1.0,
+        exclusions: vec![],
+        min_similarity: Some(-1.0),
+        history: vec![],
+        max_sentences: None,
+        tone: None,
+        custom_persona: None,
+    };
+
+    let responses = generate_constituent_messages(&event, &config)
+        .await
+        .unwrap();
+
+    let personas = load_personas().unwrap();
+    let embedding = mock_embedding(
+        &format!("{} {}", event.title, event.description),
+        personas[0].embeddings.len(),
+    );
+    let expected: Vec<&str> = rank_personas(&embedding, &personas, &[])
+        .iter()
+        .take(3)
+        .map(|(idx, _)| personas[*idx].name.as_str())
+        .collect();
+    let names: Vec<&str> = responses.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, expected);
+    assert!(responses.iter().all(|r| r.message.contains(&event.title)));
+}
+
+#[tokio::test]
+async fn public_sentiment_percentages_sum_to_100() {
+    let config = SimulationConfig {
+        mock_azure: true,
+        ..SimulationConfig::default()
+    };
+    let event = EventRequest {
+        title: "Protected Bike Lanes Approved".to_string(),
+        description: "The city approves protected lanes on Peachtree.".to_string(),
+        zone: "Midtown".to_string(),
+        positivity: 0.7,
+        severity: 0.6,
+        exclusions: vec![],
+        min_similarity: None,
+        history: vec![],
+        max_sentences: None,
+        tone: None,
+        custom_persona: None,
+    };
+
+    let sentiment = public_sentiment(&event, &config).await.unwrap();
+
+    assert_eq!(sentiment.personas, load_personas().unwrap().len());
+    let total = sentiment.supportive + sentiment.neutral + sentiment.opposed;
+    assert!((total - 100.0).abs() < 1e-9, "percentages sum to {}", total);
+    assert!((-1.0..=1.0).contains(&sentiment.mean_stance));
+}
@@ -0,0 +1,78 @@
+use backend::geo::{
+    ATLANTA_BOUNDS, CoordinateCheck, in_bounds, normalize_coordinates, parse_geometry,
+    round_coordinates,
+};
+
+#[test]
+fn lat_lng_pairs_in_the_service_area_are_kept() {
+    let mut coordinates = vec![33.755, -84.389];
+    assert_eq!(
+        normalize_coordinates(&mut coordinates),
+        CoordinateCheck::Valid
+    );
+    assert_eq!(coordinates, vec![33.755, -84.389]);
+}
+
+#[test]
+fn transposed_pairs_are_swapped_to_lat_lng() {
+    let mut coordinates = vec![-84.389, 33.755];
+    assert_eq!(
+        normalize_coordinates(&mut coordinates),
+        CoordinateCheck::Swapped
+    );
+    assert_eq!(coordinates, vec![33.755, -84.389]);
+
+    let mut outside_city = vec![151.2, -33.87];
+    assert_eq!(
+        normalize_coordinates(&mut outside_city),
+        CoordinateCheck::Swapped
+    );
+    assert_eq!(outside_city, vec![-33.87, 151.2]);
+}
+
+#[test]
+fn malformed_pairs_are_flagged() {
+    assert_eq!(
+        normalize_coordinates(&mut [200.0, 95.0]),
+        CoordinateCheck::Invalid
+    );
+    assert_eq!(normalize_coordinates(&mut [33.7]), CoordinateCheck::Invalid);
+    assert_eq!(
+        normalize_coordinates(&mut [f64::NAN, -84.3]),
+        CoordinateCheck::Invalid
+    );
+}
+
+#[test]
+fn bounds_include_the_city_and_exclude_the_ocean() {
+    assert!(in_bounds(33.755, -84.389));
+    assert!(in_bounds(ATLANTA_BOUNDS.min_lat, ATLANTA_BOUNDS.max_lng));
+    assert!(!in_bounds(-84.389, 33.755));
+    assert!(!in_bounds(0.0, 0.0));
+    assert!(!in_bounds(31.0, -75.0));
+}
+
+#[test]
+fn polygon_contains_points_inside_the_ring_but_not_in_holes() {
+    let geometry = serde_json::json!({
+        "type": "Polygon",
+        "coordinates": [
+            [[-84.4, 33.7], [-84.3, 33.7], [-84.3, 33.8], [-84.4, 33.8], [-84.4, 33.7]],
+            [[-84.36, 33.74], [-84.34, 33.74], [-84.34, 33.76], [-84.36, 33.76], [-84.36, 33.74]]
+        ]
+    });
+
+    let polygons = parse_geometry(&geometry);
+
+    assert_eq!(polygons.len(), 1);
+    assert!(polygons[0].contains(33.72, -84.38));
+    assert!(!polygons[0].contains(33.75, -84.35));
+    assert!(!polygons[0].contains(33.9, -84.38));
+}
+
+#[test]
+fn rounding_keeps_in_bounds_points_in_bounds() {
+    let mut near_edge = vec![33.7, -84.13];
+    round_coordinates(&mut near_edge, 0);
+    assert_eq!(near_edge, vec![34.0, ATLANTA_BOUNDS.max_lng]);
+}
@@ -0,0 +1,53 @@
+use actix_web::web::Bytes;
+use backend::export::events_csv_stream;
+use backend::types::{EventNotification, NeighborhoodMetrics};
+use futures_util::StreamExt;
+
+#[tokio::test]
+async fn csv_export_has_stable_header_and_flattened_metrics() {
+    let event = EventNotification {
+        id: "event-1".to_string(),
+        zone_id: "Midtown".to_string(),
+        zone_name: "Midtown".to_string(),
+        event_type: "housing".to_string(),
+        title: "Tower Opens, Rents Ease".to_string(),
+        severity: 0.5,
+        positivity: 0.25,
+        coordinates: vec![33.78, -84.38],
+        metrics: Some(NeighborhoodMetrics {
+            zone_id: "Midtown".to_string(),
+            zone_name: "Midtown".to_string(),
+            housing_units: Some(1200),
+            ..NeighborhoodMetrics::default()
+        }),
+        ..EventNotification::default()
+    };
+
+    let rows: Vec<Bytes> = events_csv_stream(vec![event])
+        .map(|row| row.expect("rows should encode"))
+        .collect()
+        .await;
+    let csv = String::from_utf8(rows.concat()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    assert!(
+        lines[0].starts_with("id,zone,type,title,severity,positivity,lat,lng,population_total,")
+    );
+    assert!(lines[0].ends_with(",derived.higher_ed_percent,derived.density_index"));
+
+    let header: Vec<&str> = lines[0].split(',').collect();
+    let row = &lines[1];
+    assert!(
+        row.starts_with(
+            "event-1,Midtown,housing,\"Tower Opens, Rents Ease\",0.5,0.25,33.78,-84.38,"
+        )
+    );
+
+    let housing_column = header.iter().position(|c| *c == "housing_units").unwrap();
+    let unquoted = row.replacen("\"Tower Opens, Rents Ease\"", "title", 1);
+    let values: Vec<&str> = unquoted.split(',').collect();
+    assert_eq!(values.len(), header.len());
+    assert_eq!(values[housing_column], "1200");
+    assert_eq!(values[header.len() - 1], "");
+}
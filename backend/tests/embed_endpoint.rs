@@ -0,0 +1,141 @@
+use actix_web::http::StatusCode;
+use actix_web::{App, test, web};
+use backend::SimulationConfig;
+use backend::embeddings::{EmbedResponse, EmbeddingCache};
+use backend::handlers::embed;
+use serde_json::json;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+
+fn config(server: &MockServer) -> SimulationConfig {
+    SimulationConfig {
+        azure_embedding_url: format!("{}/embeddings", server.uri()),
+        api_token: Some("secret-token".to_string()),
+        ..SimulationConfig::default()
+    }
+}
+
+#[actix_web::test]
+async fn cached_text_is_not_embedded_twice() {
+    common::use_test_api_key();
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [{ "embedding": [0.1, 0.2, 0.3], "index": 0 }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let cache_dir = std::env::temp_dir().join(format!("embed-cache-{}", uuid::Uuid::new_v4()));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(config(&server)))
+            .app_data(web::Data::new(EmbeddingCache::new(
+                Some(cache_dir.clone()),
+                "text-embedding-3-small",
+            )))
+            .route("/api/embed", web::post().to(embed)),
+    )
+    .await;
+
+    let mut responses = Vec::new();
+    for _ in 0..2 {
+        let request = test::TestRequest::post()
+            .uri("/api/embed")
+            .insert_header(("Authorization", "Bearer secret-token"))
+            .set_json(json!({ "texts": ["New light rail station opens"] }))
+            .to_request();
+        let response: EmbedResponse = test::call_and_read_body_json(&app, request).await;
+        responses.push(response);
+    }
+
+    assert_eq!(responses[0].embeddings, vec![vec![0.1, 0.2, 0.3]]);
+    assert_eq!(responses[0].cached, 0);
+    assert_eq!(responses[1].embeddings, responses[0].embeddings);
+    assert_eq!(responses[1].cached, 1);
+    let _ = std::fs::remove_dir_all(cache_dir);
+}
+
+#[actix_web::test]
+async fn embed_requires_the_api_token() {
+    let server = MockServer::start().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(config(&server)))
+            .app_data(web::Data::new(EmbeddingCache::new(
+                None,
+                "text-embedding-3-small",
+            )))
+            .route("/api/embed", web::post().to(embed)),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/embed")
+        .insert_header(("Authorization", "Bearer wrong-token"))
+        .set_json(json!({ "texts": ["anything"] }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn cached_embeddings_are_not_served_for_another_model() {
+    let cache_dir = std::env::temp_dir().join(format!("embed-cache-{}", uuid::Uuid::new_v4()));
+    let small = EmbeddingCache::new(Some(cache_dir.clone()), "text-embedding-3-small");
+    let large = EmbeddingCache::new(Some(cache_dir.clone()), "text-embedding-3-large");
+
+    small.insert("New light rail station opens", &[0.1, 0.2, 0.3]);
+
+    assert_eq!(
+        small.get("New light rail station opens"),
+        Some(vec![0.1, 0.2, 0.3])
+    );
+    assert_eq!(large.get("New light rail station opens"), None);
+    let _ = std::fs::remove_dir_all(cache_dir);
+}
+
+#[actix_web::test]
+async fn mock_embeddings_are_not_served_to_a_real_cache() {
+    common::use_test_api_key();
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [{ "embedding": [0.1, 0.2, 0.3], "index": 0 }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let cache_dir = std::env::temp_dir().join(format!("embed-cache-{}", uuid::Uuid::new_v4()));
+
+    let mut responses = Vec::new();
+    for mock_azure in [true, false] {
+        let config = SimulationConfig {
+            mock_azure,
+            embedding_cache_dir: Some(cache_dir.clone()),
+            ..config(&server)
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(EmbeddingCache::from_config(&config)))
+                .app_data(web::Data::new(config))
+                .route("/api/embed", web::post().to(embed)),
+        )
+        .await;
+        let request = test::TestRequest::post()
+            .uri("/api/embed")
+            .insert_header(("Authorization", "Bearer secret-token"))
+            .set_json(json!({ "texts": ["New light rail station opens"] }))
+            .to_request();
+        let response: EmbedResponse = test::call_and_read_body_json(&app, request).await;
+        responses.push(response);
+    }
+
+    assert_eq!(responses[1].cached, 0);
+    assert_eq!(responses[1].embeddings, vec![vec![0.1, 0.2, 0.3]]);
+    assert_ne!(responses[0].embeddings, responses[1].embeddings);
+    let _ = std::fs::remove_dir_all(cache_dir);
+}
@@ -0,0 +1,26 @@
+use actix_web::ResponseError;
+use actix_web::http::StatusCode;
+use backend::SimulationError;
+
+#[test]
+fn simulation_errors_map_to_http_statuses() {
+    let cases = [
+        (
+            SimulationError::InvalidRequest("prompt must not be empty".to_string()),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            SimulationError::SimulationNotFound("missing-id".to_string()),
+            StatusCode::NOT_FOUND,
+        ),
+        (
+            SimulationError::Upstream("Azure returned 502".to_string()),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    ];
+
+    for (error, status) in cases {
+        assert_eq!(error.status_code(), status, "{}", error);
+        assert_eq!(error.error_response().status(), status, "{}", error);
+    }
+}
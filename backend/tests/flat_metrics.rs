@@ -0,0 +1,71 @@
+use actix_web::web::Bytes;
+use backend::sse::flatten_frame_metrics;
+use backend::types::{Derived, NeighborhoodMetrics, PartialCommute};
+use serde_json::json;
+
+fn downtown_metrics() -> NeighborhoodMetrics {
+    NeighborhoodMetrics {
+        zone_id: "Downtown".to_string(),
+        zone_name: "Downtown".to_string(),
+        population_total: Some(4800),
+        vacancy_rate: Some(11.5),
+        commute: Some(PartialCommute {
+            avg_minutes: Some(31.5),
+            car_dependence: None,
+            transit_usage: Some(22.0),
+        }),
+        derived: Some(Derived {
+            higher_ed_percent: 48.0,
+            density_index: 12.5,
+        }),
+        ..NeighborhoodMetrics::default()
+    }
+}
+
+#[test]
+fn flat_metrics_round_trip_to_the_nested_form() {
+    let metrics = downtown_metrics();
+
+    let flat = metrics.to_flat();
+
+    assert_eq!(
+        serde_json::Value::Object(flat.clone()),
+        json!({
+            "zoneId": "Downtown",
+            "zoneName": "Downtown",
+            "population_total": 4800,
+            "vacancy_rate": 11.5,
+            "commute.avg_minutes": 31.5,
+            "commute.transit_usage": 22.0,
+            "derived.higher_ed_percent": 48.0,
+            "derived.density_index": 12.5
+        })
+    );
+    let nested = NeighborhoodMetrics::from_flat(&flat).unwrap();
+    assert_eq!(
+        serde_json::to_value(&nested).unwrap(),
+        serde_json::to_value(&metrics).unwrap()
+    );
+}
+
+#[test]
+fn only_event_frames_are_flattened() {
+    let event = json!({
+        "type": "event",
+        "data": { "id": "event-1", "zoneId": "Downtown", "metrics": downtown_metrics() }
+    });
+    let frame = Bytes::from(format!("id: 3\ndata: {}\n\n", event));
+
+    let flattened = String::from_utf8(flatten_frame_metrics(frame).to_vec()).unwrap();
+
+    let (id, rest) = flattened.split_once('\n').unwrap();
+    assert_eq!(id, "id: 3");
+    assert!(rest.ends_with("\n\n"));
+    let chunk: serde_json::Value =
+        serde_json::from_str(rest.trim().strip_prefix("data: ").unwrap()).unwrap();
+    assert_eq!(chunk["data"]["metrics"]["derived.density_index"], 12.5);
+    assert!(chunk["data"]["metrics"].get("derived").is_none());
+
+    let complete = Bytes::from_static(b"id: 4\ndata: {\"type\": \"complete\"}\n\n");
+    assert_eq!(flatten_frame_metrics(complete.clone()), complete);
+}
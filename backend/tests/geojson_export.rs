@@ -0,0 +1,36 @@
+use backend::export::events_geojson;
+use backend::types::EventNotification;
+
+#[test]
+fn geojson_export_swaps_lat_lng_to_lng_lat() {
+    let event = EventNotification {
+        id: "event-1".to_string(),
+        zone_id: "Downtown".to_string(),
+        event_type: "transportation".to_string(),
+        title: "Streetcar Extension Approved".to_string(),
+        severity: 0.7,
+        positivity: 0.6,
+        coordinates: vec![33.755, -84.389],
+        ..EventNotification::default()
+    };
+    let no_coordinates = EventNotification {
+        id: "event-2".to_string(),
+        ..EventNotification::default()
+    };
+
+    let collection = events_geojson(&[event, no_coordinates]);
+
+    assert_eq!(collection["type"], "FeatureCollection");
+    let features = collection["features"].as_array().unwrap();
+    assert_eq!(features.len(), 1);
+    assert_eq!(features[0]["geometry"]["type"], "Point");
+    assert_eq!(
+        features[0]["geometry"]["coordinates"],
+        serde_json::json!([-84.389, 33.755])
+    );
+    assert_eq!(
+        features[0]["properties"]["title"],
+        "Streetcar Extension Approved"
+    );
+    assert_eq!(features[0]["properties"]["type"], "transportation");
+}
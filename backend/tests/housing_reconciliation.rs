@@ -0,0 +1,47 @@
+use backend::NeighborhoodDatabase;
+use backend::metrics::vacancy;
+use backend::types::NeighborhoodMetrics;
+use backend::utils::complete_interdependent_metrics;
+
+#[test]
+fn new_housing_units_start_vacant() {
+    let cabbagetown = NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load from the backend directory")
+        .find_by_name("Cabbagetown")
+        .expect("Cabbagetown should exist");
+    let mut metrics = NeighborhoodMetrics {
+        housing_units: Some(cabbagetown.housing_units + 200),
+        ..NeighborhoodMetrics::default()
+    };
+
+    complete_interdependent_metrics(&mut metrics, &cabbagetown);
+
+    let vacant_units = cabbagetown.vacant_units + 200;
+    assert_eq!(metrics.vacant_units, Some(vacant_units));
+    assert_eq!(metrics.households, None);
+    assert_eq!(
+        metrics.vacancy_rate,
+        Some(vacancy(
+            vacant_units as f64,
+            (cabbagetown.housing_units + 200) as f64
+        ))
+    );
+}
+
+#[test]
+fn removing_more_units_than_are_vacant_removes_households() {
+    let cabbagetown = NeighborhoodDatabase::new()
+        .unwrap()
+        .find_by_name("Cabbagetown")
+        .unwrap();
+    let removed = cabbagetown.vacant_units + 50;
+    let mut metrics = NeighborhoodMetrics {
+        housing_units: Some(cabbagetown.housing_units - removed),
+        ..NeighborhoodMetrics::default()
+    };
+
+    complete_interdependent_metrics(&mut metrics, &cabbagetown);
+
+    assert_eq!(metrics.vacant_units, Some(0));
+    assert_eq!(metrics.households, Some(cabbagetown.households - 50));
+}
@@ -0,0 +1,170 @@
+use backend::utils::{JsonArrayChunkParser, strip_markdown_fences};
+
+fn parse_all(parser: &mut JsonArrayChunkParser, input: &str) -> Vec<String> {
+    input
+        .chars()
+        .filter_map(|ch| parser.process_char(ch))
+        .collect()
+}
+
+fn parse_in_pieces(input: &str, piece_len: usize) -> Vec<String> {
+    let mut parser = JsonArrayChunkParser::new();
+    let chars: Vec<char> = input.chars().collect();
+    chars
+        .chunks(piece_len)
+        .flat_map(|piece| parse_all(&mut parser, &piece.iter().collect::<String>()))
+        .collect()
+}
+
+fn assert_valid_json(chunks: &[String]) {
+    for chunk in chunks {
+        serde_json::from_str::<serde_json::Value>(chunk)
+            .unwrap_or_else(|e| panic!("chunk is not valid JSON ({}): {}", e, chunk));
+    }
+}
+
+#[test]
+fn extracts_each_element_of_a_simple_array() {
+    let mut parser = JsonArrayChunkParser::new();
+    let chunks = parse_all(&mut parser, r#"[{"a": 1}, {"b": 2}]"#);
+
+    assert_eq!(chunks, vec![r#"{"a": 1}"#, r#"{"b": 2}"#]);
+}
+
+#[test]
+fn keeps_nested_arrays_inside_an_element() {
+    let mut parser = JsonArrayChunkParser::new();
+    let input = r#"[{"type": "event", "data": {"coordinates": [33.75, -84.39]}}, {"type": "complete", "data": {"summary": "done"}}]"#;
+    let chunks = parse_all(&mut parser, input);
+
+    assert_eq!(chunks.len(), 2);
+    assert_valid_json(&chunks);
+    assert!(chunks[0].contains("[33.75, -84.39]"));
+}
+
+#[test]
+fn keeps_nested_objects_inside_an_element() {
+    let mut parser = JsonArrayChunkParser::new();
+    let input = r#"[{"type": "event", "data": {"metrics": {"zoneId": "Downtown", "derived": {"higher_ed_percent": 45.2, "density_index": 12.5}}}}]"#;
+    let chunks = parse_all(&mut parser, input);
+
+    assert_eq!(chunks.len(), 1);
+    assert_valid_json(&chunks);
+    let value: serde_json::Value = serde_json::from_str(&chunks[0]).unwrap();
+    assert_eq!(value["data"]["metrics"]["derived"]["density_index"], 12.5);
+}
+
+#[test]
+fn keeps_objects_nested_inside_arrays_inside_an_element() {
+    let mut parser = JsonArrayChunkParser::new();
+    let input = r#"[{"items": [{"x": [1, {"y": 2}]}, {"x": []}]}, {"z": {}}]"#;
+    let chunks = parse_all(&mut parser, input);
+
+    assert_eq!(
+        chunks,
+        vec![
+            r#"{"items": [{"x": [1, {"y": 2}]}, {"x": []}]}"#,
+            r#"{"z": {}}"#
+        ]
+    );
+}
+
+#[test]
+fn ignores_brackets_and_braces_inside_strings() {
+    let mut parser = JsonArrayChunkParser::new();
+    let input = r#"[{"title": "a [weird] {name}"}, {"title": "}]{["}]"#;
+    let chunks = parse_all(&mut parser, input);
+
+    assert_eq!(
+        chunks,
+        vec![r#"{"title": "a [weird] {name}"}"#, r#"{"title": "}]{["}"#]
+    );
+}
+
+#[test]
+fn handles_escaped_quotes_inside_strings() {
+    let mut parser = JsonArrayChunkParser::new();
+    let input = r#"[{"description": "Residents call it \"the {big} dig\" [sic]"}, {"escaped": "slash \\"}]"#;
+    let chunks = parse_all(&mut parser, input);
+
+    assert_eq!(chunks.len(), 2);
+    assert_valid_json(&chunks);
+    let value: serde_json::Value = serde_json::from_str(&chunks[0]).unwrap();
+    assert_eq!(
+        value["description"],
+        r#"Residents call it "the {big} dig" [sic]"#
+    );
+}
+
+#[test]
+fn produces_the_same_chunks_regardless_of_split_boundaries() {
+    let input = r#"[
+  {"type": "event", "data": {"id": "event-1", "title": "Rail [phase 1] opens", "coordinates": [33.755, -84.389],
+    "metrics": {"zoneId": "Downtown", "zoneName": "Downtown", "population_total": 4800,
+      "derived": {"higher_ed_percent": 45.2, "density_index": 12.5}}}},
+  {"type": "event", "data": {"id": "event-2", "description": "Quote \"{here}\"", "coordinates": [33.784, -84.384]}},
+  {"type": "complete", "data": {"summary": "Done."}}
+]"#;
+
+    let expected = parse_in_pieces(input, input.len());
+    assert_eq!(expected.len(), 3);
+    assert_valid_json(&expected);
+
+    for piece_len in [1, 2, 3, 5, 7, 11, 64] {
+        assert_eq!(
+            parse_in_pieces(input, piece_len),
+            expected,
+            "piece length {}",
+            piece_len
+        );
+    }
+}
+
+#[test]
+fn ignores_text_before_the_array_starts() {
+    let mut parser = JsonArrayChunkParser::new();
+    let chunks = parse_all(&mut parser, "```json\n[{\"a\": 1}]\n```");
+
+    assert_eq!(chunks, vec![r#"{"a": 1}"#]);
+}
+
+#[test]
+fn salvages_a_chunk_cut_off_between_key_and_value() {
+    let mut parser = JsonArrayChunkParser::new();
+    for ch in r#"[{"type": "event", "data": {"id": "e1", "title": "Park", "severity": "#.chars() {
+        assert_eq!(parser.process_char(ch), None);
+    }
+
+    assert!(parser.is_incomplete());
+    assert_eq!(
+        parser.salvage_partial_chunk().as_deref(),
+        Some(r#"{"type": "event", "data": {"id": "e1", "title": "Park"}}"#)
+    );
+}
+
+#[test]
+fn ignores_markdown_fences_around_the_array() {
+    let input = "```json\n[{\"a\": 1}, {\"b\": \"x\"}]\n```\n";
+
+    let chunks = parse_in_pieces(input, 3);
+
+    assert_eq!(chunks, vec![r#"{"a": 1}"#, r#"{"b": "x"}"#]);
+}
+
+#[test]
+fn skips_prose_with_brackets_before_the_array() {
+    let mut parser = JsonArrayChunkParser::new();
+    let input = r#"Here are the events [as requested] for "Midtown": [{"a": 1}] Let me know if you need more {"b": 2}"#;
+
+    let chunks = parse_all(&mut parser, input);
+
+    assert_eq!(chunks, vec![r#"{"a": 1}"#]);
+    assert!(!parser.is_incomplete());
+}
+
+#[test]
+fn strips_fences_like_phase_one() {
+    assert_eq!(strip_markdown_fences("```json\n[1]\n```"), "[1]");
+    assert_eq!(strip_markdown_fences("  ```\n{}```  "), "{}");
+    assert_eq!(strip_markdown_fences("[]"), "[]");
+}
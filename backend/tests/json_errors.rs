@@ -0,0 +1,29 @@
+use actix_web::http::StatusCode;
+use actix_web::{App, test, web};
+use backend::SimulationConfig;
+use backend::handlers::{ErrorBody, handle_messages, json_config};
+
+#[actix_web::test]
+async fn truncated_json_gets_a_structured_400() {
+    let app = test::init_service(
+        App::new()
+            .app_data(json_config())
+            .app_data(web::Data::new(SimulationConfig::default()))
+            .route("/api/messages", web::post().to(handle_messages)),
+    )
+    .await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/messages")
+        .insert_header(("Content-Type", "application/json"))
+        .set_payload(r#"{"title": "Library Opens", "description": "#)
+        .to_request();
+    let response = test::call_service(&app, request).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: ErrorBody = test::read_body_json(response).await;
+    assert_eq!(body.error.code, "invalid_json");
+    assert!(body.error.message.contains("EOF"));
+    assert_eq!(body.error.line, Some(1));
+    assert!(body.error.column.is_some());
+}
@@ -0,0 +1,27 @@
+use backend::logging::{banner, rule, to_ascii};
+
+#[test]
+fn banners_are_well_formed_utf8_with_an_ascii_fallback() {
+    let banner = banner("City Simulation Backend API");
+    let lines: Vec<&str> = banner.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|line| line.chars().count() == 62));
+    assert!(std::str::from_utf8(banner.as_bytes()).is_ok());
+    let latin1_mojibake_lead = 'â';
+    assert!(!banner.contains(latin1_mojibake_lead) && !rule().contains(latin1_mojibake_lead));
+
+    let ascii = to_ascii(&banner);
+    assert!(ascii.is_ascii());
+    assert_eq!(ascii.lines().count(), 3);
+    assert!(to_ascii(&rule()).chars().all(|c| c == '-'));
+}
+
+#[test]
+fn status_markers_map_to_ascii_tags() {
+    assert_eq!(
+        to_ascii("   ⚠️  Dropped event → retry ✓ ✗"),
+        "   [!]  Dropped event -> retry [ok] [x]"
+    );
+    assert_eq!(to_ascii("plain text"), "plain text");
+}
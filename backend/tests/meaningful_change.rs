@@ -0,0 +1,68 @@
+use backend::NeighborhoodDatabase;
+use backend::types::{NeighborhoodMetrics, NeighborhoodProperties};
+use backend::utils::has_meaningful_change;
+
+fn downtown() -> NeighborhoodProperties {
+    NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load from the backend directory")
+        .find_by_name("Downtown")
+        .expect("Downtown should exist")
+}
+
+#[test]
+fn changes_below_every_threshold_are_not_meaningful() {
+    let baseline = downtown();
+    let metrics = NeighborhoodMetrics {
+        housing_units: Some(baseline.housing_units + 1),
+        median_income: Some(baseline.median_income + 100),
+        vacancy_rate: Some(baseline.vacancy_rate + 0.5),
+        livability_index: Some(baseline.livability_index + 20.0),
+        ..NeighborhoodMetrics::default()
+    };
+
+    assert!(!has_meaningful_change(&metrics, &baseline));
+}
+
+#[test]
+fn a_single_change_above_its_threshold_is_meaningful() {
+    let baseline = downtown();
+    let changes = [
+        NeighborhoodMetrics {
+            housing_units: Some(baseline.housing_units + 1_000),
+            ..NeighborhoodMetrics::default()
+        },
+        NeighborhoodMetrics {
+            median_income: Some(baseline.median_income + baseline.median_income / 10),
+            ..NeighborhoodMetrics::default()
+        },
+        NeighborhoodMetrics {
+            vacancy_rate: Some(baseline.vacancy_rate + 2.0),
+            ..NeighborhoodMetrics::default()
+        },
+    ];
+
+    for metrics in changes {
+        assert!(has_meaningful_change(&metrics, &baseline), "{:?}", metrics);
+    }
+}
+
+#[test]
+fn density_changes_need_an_absolute_minimum_over_a_zero_baseline() {
+    let baseline = NeighborhoodProperties {
+        population_density: 0.0,
+        housing_density: 0.0,
+        ..downtown()
+    };
+    let tiny = NeighborhoodMetrics {
+        population_density: Some(0.01),
+        housing_density: Some(0.01),
+        ..NeighborhoodMetrics::default()
+    };
+    let large = NeighborhoodMetrics {
+        population_density: Some(2.0),
+        ..NeighborhoodMetrics::default()
+    };
+
+    assert!(!has_meaningful_change(&tiny, &baseline));
+    assert!(has_meaningful_change(&large, &baseline));
+}
@@ -0,0 +1,49 @@
+use backend::metrics::{density, diversity, higher_ed, vacancy};
+use backend::types::{EducationDistribution, RaceDistribution};
+
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn higher_ed_sums_bachelors_and_graduate() {
+    let education = EducationDistribution {
+        high_school_or_less: 30.0,
+        some_college: 25.0,
+        bachelors: 28.5,
+        graduate: 16.5,
+    };
+    assert!(approx_eq(higher_ed(&education), 45.0));
+}
+
+#[test]
+fn diversity_is_zero_for_one_group_and_higher_for_even_mix() {
+    let single = RaceDistribution {
+        white: 100.0,
+        black: 0.0,
+        asian: 0.0,
+        mixed: 0.0,
+        hispanic: 0.0,
+    };
+    let even = RaceDistribution {
+        white: 20.0,
+        black: 20.0,
+        asian: 20.0,
+        mixed: 20.0,
+        hispanic: 20.0,
+    };
+    assert!(approx_eq(diversity(&single), 0.0));
+    assert!(approx_eq(diversity(&even), 0.8));
+}
+
+#[test]
+fn density_divides_population_by_area() {
+    assert!(approx_eq(density(496.0, 137.02), 496.0 / 137.02));
+    assert!(approx_eq(density(500.0, 0.0), 0.0));
+}
+
+#[test]
+fn vacancy_is_a_percentage_of_housing_units() {
+    assert!(approx_eq(vacancy(25.0, 500.0), 5.0));
+    assert!(approx_eq(vacancy(3.0, 0.0), 0.0));
+}
@@ -0,0 +1,161 @@
+use backend::NeighborhoodDatabase;
+use backend::types::{ContextField, ContextualFields, MinimalNeighborhoodContext};
+use backend::utils::{build_neighborhoods_context_within_budget, estimate_tokens};
+use backend::{
+    ContextPrecision, build_minimal_context, build_minimal_context_with_fields,
+    build_neighborhoods_context, build_neighborhoods_context_with_precision,
+};
+
+#[test]
+fn housing_only_context_omits_commute_and_distributions() {
+    let downtown = NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load from the backend directory")
+        .find_by_name("Downtown")
+        .expect("Downtown should exist");
+
+    let context =
+        build_neighborhoods_context(std::slice::from_ref(&downtown), &[ContextField::Housing]);
+
+    assert!(context.contains(r#"Neighborhood: <client_data>"Downtown"</client_data>"#));
+    assert!(context.contains(&format!("Housing Units: {}", downtown.housing_units)));
+    for omitted in [
+        "Average Commute",
+        "Transit Usage",
+        "Education:",
+        "Race Distribution",
+        "Median Income",
+    ] {
+        assert!(!context.contains(omitted), "{} should be omitted", omitted);
+    }
+
+    let full = build_neighborhoods_context(std::slice::from_ref(&downtown), &[]);
+    assert!(full.contains("Average Commute") && full.contains("Race Distribution"));
+}
+
+#[test]
+fn neighborhoods_with_policy_related_current_events_are_emphasized() {
+    let context = [
+        MinimalNeighborhoodContext {
+            name: "West End".to_string(),
+            current_events: Some(vec![
+                "Ongoing transit construction on Ralph David Abernathy".to_string(),
+            ]),
+            baseline_description: None,
+            neighboring_neighborhoods: None,
+        },
+        MinimalNeighborhoodContext {
+            name: "Buckhead".to_string(),
+            current_events: Some(vec!["New luxury retail openings".to_string()]),
+            baseline_description: None,
+            neighboring_neighborhoods: None,
+        },
+    ];
+
+    let formatted =
+        build_minimal_context(&context, "Expand bus rapid transit and fund construction");
+
+    let blocks: Vec<&str> = formatted.split("\n\n---\n\n").collect();
+    assert!(blocks[0].contains("Policy Relevance: current events mention construction, transit"));
+    assert!(!blocks[1].contains("Policy Relevance"));
+    assert!(!build_minimal_context(&context, "").contains("Policy Relevance"));
+}
+
+#[test]
+fn full_precision_context_keeps_every_digit() {
+    let mut downtown = NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load from the backend directory")
+        .find_by_name("Downtown")
+        .expect("Downtown should exist");
+    downtown.vacancy_rate = 12.3456;
+    downtown.livability_index = 61.875;
+    let neighborhoods = std::slice::from_ref(&downtown);
+
+    let compact =
+        build_neighborhoods_context_with_precision(neighborhoods, &[], ContextPrecision::Compact);
+    assert!(compact.contains("Vacancy Rate: 12.3%"));
+    assert!(compact.contains("Livability Index: 61.9\n"));
+
+    let full =
+        build_neighborhoods_context_with_precision(neighborhoods, &[], ContextPrecision::Full);
+    assert!(full.contains("Vacancy Rate: 12.3456%"));
+    assert!(full.contains("Livability Index: 61.875\n"));
+    assert!(full.contains(&format!("Median Income: ${}\n", downtown.median_income)));
+}
+
+#[test]
+fn each_phase_includes_only_its_configured_contextual_fields() {
+    let downtown = NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load from the backend directory")
+        .find_by_name("Downtown")
+        .expect("Downtown should exist");
+    let minimal = [MinimalNeighborhoodContext {
+        name: "Downtown".to_string(),
+        current_events: Some(vec!["Stadium renovation".to_string()]),
+        baseline_description: Some("Dense central business district".to_string()),
+        neighboring_neighborhoods: Some(vec!["Midtown".to_string()]),
+    }];
+    let phase1: ContextualFields = "neighbors".parse().unwrap();
+    let phase2: ContextualFields = "baseline_description, current_events".parse().unwrap();
+
+    let phase1_context = build_minimal_context_with_fields(&minimal, "", phase1);
+    let phase2_context = build_neighborhoods_context_within_budget(
+        std::slice::from_ref(&downtown),
+        &[],
+        phase2,
+        usize::MAX,
+        ContextPrecision::Compact,
+    );
+
+    assert!(
+        phase1_context
+            .contains(r#"Neighboring Neighborhoods: <client_data>"Midtown"</client_data>"#)
+    );
+    assert!(!phase1_context.contains("Baseline Description"));
+    assert!(!phase1_context.contains("Current Events"));
+    assert!(phase2_context.contains("Baseline Description"));
+    assert!(phase2_context.contains("Current Events"));
+    assert!(!phase2_context.contains("Neighboring Neighborhoods"));
+    assert!("neighbours".parse::<ContextualFields>().is_err());
+}
+
+#[test]
+fn context_over_budget_is_trimmed_in_order() {
+    let mut downtown = NeighborhoodDatabase::new()
+        .unwrap()
+        .find_by_name("Downtown")
+        .unwrap();
+    downtown.baseline_description = Some("Dense core of offices and stadiums.".to_string());
+    downtown.current_events = Some(vec!["Convention center expansion".to_string()]);
+    downtown.neighboring_neighborhoods = Some(vec!["Midtown".to_string()]);
+    let build = |max_tokens| {
+        build_neighborhoods_context_within_budget(
+            std::slice::from_ref(&downtown),
+            &[],
+            ContextualFields::ALL,
+            max_tokens,
+            ContextPrecision::Compact,
+        )
+    };
+
+    let dropped_in_order = [
+        vec!["Current Events"],
+        vec!["Education", "Race Distribution"],
+        vec!["Baseline Description"],
+        vec!["Average Commute", "Neighboring Neighborhoods"],
+    ];
+    let mut context = build(usize::MAX);
+    let mut remaining: Vec<&str> = dropped_in_order.iter().flatten().copied().collect();
+    for dropped in dropped_in_order {
+        let one_token_short_of_previous = estimate_tokens(&context) - 1;
+        context = build(one_token_short_of_previous);
+
+        assert!(estimate_tokens(&context) <= one_token_short_of_previous);
+        remaining.retain(|part| !dropped.contains(part));
+        for part in &dropped {
+            assert!(!context.contains(part), "{} should be trimmed", part);
+        }
+        for part in &remaining {
+            assert!(context.contains(part), "{} should be kept", part);
+        }
+    }
+}
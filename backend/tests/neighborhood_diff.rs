@@ -0,0 +1,23 @@
+use backend::NeighborhoodDatabase;
+use backend::diff::diff_neighborhoods;
+
+#[test]
+fn only_the_changed_field_appears_in_the_diff() {
+    let db = NeighborhoodDatabase::new().expect("neighborhood GeoJSON should load");
+    let baseline = vec![
+        db.find_by_name("Cabbagetown").unwrap(),
+        db.find_by_name("Midtown").unwrap(),
+    ];
+    let mut modified = baseline.clone();
+    modified[0].median_income += 5000;
+
+    let diff = diff_neighborhoods(&baseline, &modified);
+
+    assert_eq!(diff.len(), 1);
+    assert_eq!(diff[0].name, "Cabbagetown");
+    assert_eq!(diff[0].changes.len(), 1);
+    let change = &diff[0].changes[0];
+    assert_eq!(change.field, "median_income");
+    assert_eq!(change.delta, 5000.0);
+    assert!(change.percent_change.unwrap() > 0.0);
+}
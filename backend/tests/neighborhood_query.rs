@@ -0,0 +1,45 @@
+use backend::NeighborhoodDatabase;
+use backend::query::{filter_neighborhoods, parse_range_filters};
+use std::collections::HashMap;
+
+fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn combined_filters_return_the_intersection() {
+    let db = NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load from the backend directory");
+    let filters = parse_range_filters(&params(&[
+        ("median_income_max", "40000"),
+        ("vacancy_rate_min", "15"),
+    ]))
+    .expect("filters should parse");
+
+    let matches = filter_neighborhoods(db.all(), &filters);
+
+    let expected: Vec<String> = {
+        let mut names: Vec<String> = db
+            .all()
+            .into_iter()
+            .filter(|n| n.median_income <= 40000 && n.vacancy_rate >= 15.0)
+            .map(|n| n.name)
+            .collect();
+        names.sort();
+        names
+    };
+    let names: Vec<String> = matches.into_iter().map(|n| n.name).collect();
+    assert!(!names.is_empty());
+    assert!(names.len() < db.count());
+    assert_eq!(names, expected);
+}
+
+#[test]
+fn unknown_fields_are_rejected() {
+    assert!(parse_range_filters(&params(&[("shoe_size_min", "9")])).is_err());
+    assert!(parse_range_filters(&params(&[("median_income", "9")])).is_err());
+    assert!(parse_range_filters(&params(&[("median_income_min", "lots")])).is_err());
+}
@@ -0,0 +1,70 @@
+use backend::NeighborhoodDatabase;
+use backend::neighborhoods::MAX_GEOJSON_BYTES;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn neighborhoods_load_from_a_url() {
+    let geojson = std::fs::read_to_string("data/neighborhoods.geojson")
+        .expect("neighborhood GeoJSON should be readable from the backend directory");
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/neighborhoods.geojson"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(geojson))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/neighborhoods.geojson", server.uri());
+    let db = NeighborhoodDatabase::load(Some(&url))
+        .await
+        .expect("neighborhoods should load from the mock server");
+
+    let from_file = NeighborhoodDatabase::new().unwrap();
+    assert_eq!(db.count(), from_file.count());
+    assert!(db.find_by_name("Cabbagetown").is_some());
+}
+
+#[tokio::test]
+async fn a_failing_url_is_an_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/neighborhoods.geojson", server.uri());
+    assert!(NeighborhoodDatabase::from_url(&url).await.is_err());
+}
+
+#[tokio::test]
+async fn an_oversized_response_is_an_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(4096)))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/neighborhoods.geojson", server.uri());
+    let error = NeighborhoodDatabase::from_url_bounded(&url, 1024, Duration::from_secs(5))
+        .await
+        .err()
+        .expect("a body over the limit should be rejected");
+    assert!(error.to_string().contains("larger than 1024 bytes"));
+}
+
+#[tokio::test]
+async fn a_slow_response_times_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/neighborhoods.geojson", server.uri());
+    let result =
+        NeighborhoodDatabase::from_url_bounded(&url, MAX_GEOJSON_BYTES, Duration::from_millis(200))
+            .await;
+    assert!(result.is_err());
+}
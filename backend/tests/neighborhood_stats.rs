@@ -0,0 +1,74 @@
+use actix_web::{App, test, web};
+use backend::NeighborhoodDatabase;
+use backend::handlers::configure_routes;
+use backend::stats::CityStats;
+use serde_json::json;
+
+fn fixture_database() -> NeighborhoodDatabase {
+    let db = NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load from the backend directory");
+    let rows = [
+        ("Cabbagetown", "N", 2_000, 40_000, 1_000, 900, 100, 60.0),
+        ("Reynoldstown", "N", 3_000, 60_000, 1_500, 1_400, 200, 70.0),
+        (
+            "Buckhead Village",
+            "B",
+            5_000,
+            100_000,
+            2_500,
+            2_300,
+            300,
+            80.0,
+        ),
+    ];
+    let features: Vec<_> = rows
+        .iter()
+        .map(
+            |&(name, npu, population, income, units, households, vacant, livability)| {
+                let mut n = db
+                    .find_by_name(name)
+                    .expect("fixture neighborhood should exist");
+                n.npu = npu.to_string();
+                n.population_total = population;
+                n.median_income = income;
+                n.housing_units = units;
+                n.households = households;
+                n.vacant_units = vacant;
+                n.livability_index = livability;
+                json!({ "type": "Feature", "properties": n, "geometry": null })
+            },
+        )
+        .collect();
+    let geojson = json!({ "type": "FeatureCollection", "features": features });
+    NeighborhoodDatabase::from_geojson(&geojson.to_string()).unwrap()
+}
+
+#[actix_web::test]
+async fn stats_endpoint_aggregates_the_loaded_neighborhoods() {
+    let db = fixture_database();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db))
+            .service(web::scope("/api").configure(configure_routes)),
+    )
+    .await;
+
+    let request = test::TestRequest::get()
+        .uri("/api/neighborhoods/stats")
+        .to_request();
+    let stats: CityStats = test::call_and_read_body_json(&app, request).await;
+
+    assert_eq!(stats.neighborhoods, 3);
+    assert_eq!(stats.total_population, 10_000);
+    assert_eq!(stats.total_housing_units, 5_000);
+    assert_eq!(stats.median_income, 60_000.0);
+    let household_weighted_income =
+        (900.0 * 40_000.0 + 1_400.0 * 60_000.0 + 2_300.0 * 100_000.0) / 4_600.0;
+    assert!((stats.mean_income - household_weighted_income).abs() < 0.01);
+    assert!((stats.vacancy_rate - 12.0).abs() < 1e-9);
+    let population_weighted_livability =
+        (2_000.0 * 60.0 + 3_000.0 * 70.0 + 5_000.0 * 80.0) / 10_000.0;
+    assert!((stats.average_livability - population_weighted_livability).abs() < 1e-9);
+    assert_eq!(stats.neighborhoods_by_npu["N"], 2);
+    assert_eq!(stats.neighborhoods_by_npu["B"], 1);
+}
@@ -0,0 +1,104 @@
+use backend::constituents::{
+    MIN_MESSAGE_PERSONAS, Persona, check_persona_embedding_dimensions, find_persona,
+    persona_count_for_severity, select_personas,
+};
+use backend::{load_personas, rank_personas};
+
+#[test]
+fn known_persona_is_found_by_name() {
+    let personas = load_personas().expect("personas.json should load from the backend directory");
+
+    let persona = find_persona(&personas, "  derek chen ").expect("Derek Chen should exist");
+
+    assert_eq!(persona.name, "Derek Chen");
+}
+
+#[test]
+fn unknown_persona_is_not_found() {
+    let personas = load_personas().expect("personas.json should load from the backend directory");
+
+    assert!(find_persona(&personas, "Nobody In Particular").is_none());
+}
+
+#[test]
+fn high_severity_events_select_more_personas() {
+    let low = persona_count_for_severity(0.1, 6);
+    let high = persona_count_for_severity(1.0, 6);
+
+    assert_eq!(low, MIN_MESSAGE_PERSONAS);
+    assert_eq!(high, 6);
+    assert!(high > low);
+    assert_eq!(
+        persona_count_for_severity(f64::NAN, 6),
+        MIN_MESSAGE_PERSONAS
+    );
+}
+
+#[test]
+fn personas_below_the_similarity_threshold_are_not_selected() {
+    let personas = load_personas().expect("personas.json should load from the backend directory");
+    let opposite_of_first_persona: Vec<f64> = personas[0].embeddings.iter().map(|x| -x).collect();
+    let ranked = rank_personas(&opposite_of_first_persona, &personas, &[]);
+
+    assert_eq!(select_personas(&ranked, 2, f64::NEG_INFINITY).len(), 2);
+    assert!(select_personas(&ranked, 2, 0.3).is_empty());
+}
+
+fn persona(name: &str, embeddings: Vec<f64>) -> Persona {
+    Persona {
+        name: name.to_string(),
+        agent_prompt: String::new(),
+        description: String::new(),
+        embeddings,
+    }
+}
+
+#[test]
+fn equally_similar_personas_are_ranked_by_name() {
+    let personas = vec![
+        persona("Zoe", vec![1.0, 0.0]),
+        persona("Amir", vec![2.0, 0.0]),
+        persona("Maya", vec![0.0, 1.0]),
+    ];
+
+    let ranked = rank_personas(&[1.0, 0.0], &personas, &[]);
+    let names: Vec<_> = ranked
+        .iter()
+        .map(|(i, _)| personas[*i].name.as_str())
+        .collect();
+
+    assert_eq!(names, vec!["Amir", "Zoe", "Maya"]);
+}
+
+#[test]
+fn degenerate_embeddings_rank_without_panicking() {
+    let personas = vec![
+        persona("Broken", vec![f64::NAN, 1.0]),
+        persona("Empty", vec![0.0, 0.0]),
+        persona("Match", vec![1.0, 0.0]),
+    ];
+
+    let ranked = rank_personas(&[1.0, 0.0], &personas, &[]);
+
+    assert_eq!(personas[ranked[0].0].name, "Match");
+    assert!(ranked.iter().all(|(_, similarity)| similarity.is_finite()));
+    assert_eq!(ranked[1].1, 0.0);
+    assert_eq!(personas[ranked[1].0].name, "Broken");
+}
+
+#[test]
+fn stored_persona_embeddings_match_the_default_model() {
+    let personas = load_personas().expect("personas.json should load from the backend directory");
+
+    assert!(check_persona_embedding_dimensions(&personas, "text-embedding-3-small").is_ok());
+}
+
+#[test]
+fn embedding_model_with_other_dimensions_is_rejected() {
+    let personas = load_personas().expect("personas.json should load from the backend directory");
+
+    let err = check_persona_embedding_dimensions(&personas, "text-embedding-3-large")
+        .expect_err("3072-dimensional model should not match the stored embeddings");
+
+    assert!(err.to_string().contains("text-embedding-3-large"));
+}
@@ -0,0 +1,33 @@
+use backend::constituents::{Persona, persona_similarity_matrix};
+
+fn persona(name: &str, embeddings: Vec<f64>) -> Persona {
+    Persona {
+        name: name.to_string(),
+        agent_prompt: String::new(),
+        description: String::new(),
+        embeddings,
+    }
+}
+
+#[test]
+fn similarity_matrix_is_symmetric_with_a_unit_diagonal() {
+    let personas = vec![
+        persona("Alice", vec![1.0, 0.0, 0.0]),
+        persona("Bob", vec![1.0, 1.0, 0.0]),
+        persona("Cara", vec![0.0, 0.0, 2.0]),
+    ];
+
+    let matrix = persona_similarity_matrix(&personas);
+
+    assert_eq!(matrix.len(), 3);
+    for a in &personas {
+        let row = &matrix[&a.name];
+        assert_eq!(row.len(), 3);
+        assert!((row[&a.name] - 1.0).abs() < 1e-9);
+        for b in &personas {
+            assert_eq!(row[&b.name], matrix[&b.name][&a.name]);
+        }
+    }
+    assert!((matrix["Alice"]["Bob"] - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-9);
+    assert_eq!(matrix["Alice"]["Cara"], 0.0);
+}
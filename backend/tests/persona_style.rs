@@ -0,0 +1,106 @@
+use backend::constituents::{
+    CustomPersona, EventRequest, PersonaTone, generate_named_persona_message, load_personas,
+};
+use backend::{SimulationConfig, generate_constituent_messages};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+
+#[tokio::test]
+async fn reply_length_and_tone_reach_the_chat_request() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(
+            json!({ "max_tokens": 100, "temperature": 0.5 }),
+        ))
+        .and(body_string_contains(
+            "Generate a realistic one sentence response",
+        ))
+        .and(body_string_contains("angry, frustrated tone"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{ "message": { "role": "assistant", "content": "Unacceptable." } }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    common::use_test_api_key();
+    let config = SimulationConfig {
+        azure_chat_url: format!("{}/chat/completions", server.uri()),
+        persona_temperature: 0.5,
+        ..SimulationConfig::default()
+    };
+    let persona = load_personas()
+        .expect("personas.json should load")
+        .remove(0);
+    let event = EventRequest {
+        title: "Bus Route Cut".to_string(),
+        description: "The city ends the route 12 bus.".to_string(),
+        zone: "Midtown".to_string(),
+        positivity: -0.6,
+        severity: 0.5,
+        exclusions: vec![],
+        min_similarity: None,
+        history: vec![],
+        max_sentences: Some(1),
+        tone: Some(PersonaTone::Angry),
+        custom_persona: None,
+    };
+
+    let response = generate_named_persona_message(&persona.name, &event, &config)
+        .await
+        .unwrap();
+
+    assert_eq!(response.message, "Unacceptable.");
+}
+
+#[tokio::test]
+async fn custom_persona_responds_without_an_embedding() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_string_contains("You speak for the mayor's office"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{ "message": { "role": "assistant", "content": "We hear you." } }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    common::use_test_api_key();
+    let config = SimulationConfig {
+        azure_chat_url: format!("{}/chat/completions", server.uri()),
+        azure_embedding_url: format!("{}/embeddings", server.uri()),
+        ..SimulationConfig::default()
+    };
+    let event = EventRequest {
+        title: "Bus Route Cut".to_string(),
+        description: "The city ends the route 12 bus.".to_string(),
+        zone: "Midtown".to_string(),
+        positivity: -0.6,
+        severity: 0.5,
+        exclusions: vec![],
+        min_similarity: None,
+        history: vec![],
+        max_sentences: None,
+        tone: None,
+        custom_persona: Some(CustomPersona {
+            name: "Mayor's Office".to_string(),
+            agent_prompt: "You speak for the mayor's office.".to_string(),
+        }),
+    };
+
+    let responses = generate_constituent_messages(&event, &config)
+        .await
+        .unwrap();
+
+    assert_eq!(responses.len(), 1);
+    assert_eq!(responses[0].name, "Mayor's Office");
+    assert_eq!(responses[0].message, "We hear you.");
+}
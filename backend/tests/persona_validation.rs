@@ -0,0 +1,49 @@
+use actix_web::{App, test, web};
+use backend::SimulationConfig;
+use backend::constituents::PersonaValidation;
+use backend::handlers::validate_personas;
+use serde_json::json;
+
+#[actix_web::test]
+async fn persona_without_embeddings_is_reported() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SimulationConfig {
+                azure_embedding_model: "custom-embedder".to_string(),
+                ..SimulationConfig::default()
+            }))
+            .route("/api/personas/validate", web::post().to(validate_personas)),
+    )
+    .await;
+    let personas = json!([
+        { "name": "Ada", "agent_prompt": "You are Ada.", "description": "Nurse",
+          "embeddings": [0.1, 0.2, 0.3] },
+        { "name": "Ben", "agent_prompt": "You are Ben.", "description": "Barber" },
+        { "name": "ada", "agent_prompt": "You are Ada again.", "description": "Nurse",
+          "embeddings": [0.1, 0.2] }
+    ]);
+
+    let request = test::TestRequest::post()
+        .uri("/api/personas/validate")
+        .set_json(&personas)
+        .to_request();
+    let report: PersonaValidation = test::call_and_read_body_json(&app, request).await;
+
+    assert!(!report.valid);
+    assert_eq!(report.personas, 3);
+    assert_eq!(report.expected_dimensions, Some(3));
+    let messages: Vec<(usize, &str)> = report
+        .issues
+        .iter()
+        .map(|issue| (issue.index, issue.message.as_str()))
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            (1, "embeddings are missing or empty"),
+            (2, "duplicate name; persona 0 has the same name"),
+            (2, "embeddings have 2 dimensions, expected 3"),
+        ]
+    );
+    assert_eq!(report.issues[0].name.as_deref(), Some("Ben"));
+}
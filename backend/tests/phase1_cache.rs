@@ -0,0 +1,73 @@
+use backend::Phase1Cache;
+use backend::types::PromptProfile;
+use std::time::Duration;
+
+fn zones(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn second_identical_request_hits_the_cache() {
+    let cache = Phase1Cache::new(8, Duration::from_secs(60));
+    let key = Phase1Cache::key(
+        "Add bike lanes",
+        &zones(&["Midtown"]),
+        "Neighborhood: Midtown",
+        PromptProfile::Atlanta,
+    );
+
+    assert_eq!(cache.get(key), None);
+    cache.insert(key, zones(&["Midtown"]));
+
+    let again = Phase1Cache::key(
+        "Add bike lanes",
+        &zones(&["Midtown"]),
+        "Neighborhood: Midtown",
+        PromptProfile::Atlanta,
+    );
+    assert_eq!(cache.get(again), Some(zones(&["Midtown"])));
+}
+
+#[test]
+fn key_ignores_zone_order_but_not_zone_selection() {
+    let context = "Neighborhood: Downtown\n\nNeighborhood: Midtown";
+    let a = Phase1Cache::key(
+        "Policy",
+        &zones(&["Downtown", "Midtown"]),
+        context,
+        PromptProfile::Atlanta,
+    );
+    let b = Phase1Cache::key(
+        "Policy",
+        &zones(&["Midtown", "Downtown"]),
+        context,
+        PromptProfile::Atlanta,
+    );
+    let c = Phase1Cache::key(
+        "Policy",
+        &zones(&["Midtown"]),
+        context,
+        PromptProfile::Atlanta,
+    );
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn expired_and_evicted_entries_miss() {
+    let expired = Phase1Cache::new(8, Duration::ZERO);
+    expired.insert(1, zones(&["Midtown"]));
+    std::thread::sleep(Duration::from_millis(5));
+    assert_eq!(expired.get(1), None);
+
+    let small = Phase1Cache::new(1, Duration::from_secs(60));
+    small.insert(1, zones(&["Midtown"]));
+    small.insert(2, zones(&["Downtown"]));
+    assert_eq!(small.get(1), None);
+    assert_eq!(small.get(2), Some(zones(&["Downtown"])));
+
+    let disabled = Phase1Cache::disabled();
+    disabled.insert(1, zones(&["Midtown"]));
+    assert_eq!(disabled.get(1), None);
+}
@@ -0,0 +1,96 @@
+use backend::NeighborhoodDatabase;
+use backend::azure::{SamplingPenalties, build_phase2_request};
+use backend::events::{Phase2State, StreamOptions};
+use backend::types::{SimulationChunk, SimulationRequest};
+
+#[test]
+fn penalties_reach_the_phase2_request_body() {
+    let request = SimulationRequest {
+        presence_penalty: Some(0.5),
+        frequency_penalty: Some(1.25),
+        ..SimulationRequest::default()
+    };
+
+    let body = build_phase2_request(
+        "system".to_string(),
+        "user".to_string(),
+        0.8,
+        SamplingPenalties::from_request(&request),
+        false,
+    );
+    let json = serde_json::to_value(&body).unwrap();
+
+    assert_eq!(json["presence_penalty"], 0.5);
+    assert_eq!(json["frequency_penalty"], 1.25);
+}
+
+#[test]
+fn penalties_are_clamped_and_default_to_zero() {
+    let request = SimulationRequest {
+        frequency_penalty: Some(7.0),
+        ..SimulationRequest::default()
+    };
+
+    let penalties = SamplingPenalties::from_request(&request);
+
+    assert_eq!(
+        penalties,
+        SamplingPenalties {
+            presence: 0.0,
+            frequency: 2.0,
+        }
+    );
+}
+
+#[test]
+fn few_shot_example_turns_precede_the_user_prompt() {
+    let body = build_phase2_request(
+        "system".to_string(),
+        "user".to_string(),
+        0.8,
+        SamplingPenalties::default(),
+        true,
+    );
+    let json = serde_json::to_value(&body).unwrap();
+    let roles: Vec<_> = json["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["role"].as_str().unwrap())
+        .collect();
+
+    assert_eq!(roles, vec!["system", "user", "assistant", "user"]);
+    let example = json["messages"][2]["content"].as_str().unwrap();
+    assert!(
+        serde_json::from_str::<serde_json::Value>(example)
+            .unwrap()
+            .is_array()
+    );
+    assert_eq!(json["messages"][3]["content"], "user");
+}
+
+#[test]
+fn few_shot_example_event_survives_phase2_validation() {
+    let body = build_phase2_request(
+        "system".to_string(),
+        "user".to_string(),
+        0.8,
+        SamplingPenalties::default(),
+        true,
+    );
+    let json = serde_json::to_value(&body).unwrap();
+    let example: Vec<serde_json::Value> =
+        serde_json::from_str(json["messages"][2]["content"].as_str().unwrap()).unwrap();
+    let mut example_park = NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load from the backend directory")
+        .find_by_name("Cabbagetown")
+        .unwrap();
+    example_park.name = "Example Park".to_string();
+    example_park.median_home_value = 235_000;
+    let mut state = Phase2State::new(vec![example_park], StreamOptions::default());
+
+    let event = state.handle_chunk_json(&example[0].to_string());
+
+    assert!(matches!(event, Some(SimulationChunk::Event { .. })));
+    assert_eq!(state.diagnostics.dropped_sub_threshold, 0);
+}
@@ -0,0 +1,1207 @@
+use actix_web::web::Bytes;
+use backend::azure::process_phase2_stream;
+use backend::events::StreamOptions;
+use backend::geo::distance_meters;
+use backend::telemetry::ParseTelemetry;
+use backend::types::{
+    NeighborhoodProperties, SimulationChunk, SimulationDiagnostics, StreamTermination,
+};
+use backend::{NeighborhoodDatabase, collect_chunks};
+use futures_util::{StreamExt, stream};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+/// Latitude of a point in Buckhead, well north of Cabbagetown
+const BUCKHEAD_LAT: f64 = 33.84;
+
+/// Longitude of the same Buckhead point
+const BUCKHEAD_LNG: f64 = -84.38;
+
+fn azure_sse_body(content: &str, piece_len: usize) -> Vec<Result<Bytes, Infallible>> {
+    let chars: Vec<char> = content.chars().collect();
+    let mut body = String::new();
+    for piece in chars.chunks(piece_len) {
+        let delta = serde_json::json!({
+            "choices": [{ "delta": { "content": piece.iter().collect::<String>() } }]
+        });
+        body.push_str(&format!("data: {}\n\n", delta));
+    }
+    body.push_str("data: [DONE]\n\n");
+
+    body.as_bytes()
+        .chunks(37)
+        .map(|bytes| Ok(Bytes::copy_from_slice(bytes)))
+        .collect()
+}
+
+fn baseline(name: &str) -> NeighborhoodProperties {
+    NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load from the backend directory")
+        .find_by_name(name)
+        .expect("fixture neighborhood should exist")
+}
+
+async fn run(content: &str, full_properties: Vec<NeighborhoodProperties>) -> Vec<SimulationChunk> {
+    run_with(content, full_properties, StreamOptions::default()).await
+}
+
+async fn run_with(
+    content: &str,
+    full_properties: Vec<NeighborhoodProperties>,
+    options: StreamOptions,
+) -> Vec<SimulationChunk> {
+    let azure = stream::iter(azure_sse_body(content, 9));
+    let boundaries = NeighborhoodDatabase::new().unwrap().boundaries_for(
+        &full_properties
+            .iter()
+            .map(|n| n.name.clone())
+            .collect::<Vec<_>>(),
+    );
+    collect_chunks(process_phase2_stream(
+        azure,
+        full_properties,
+        Vec::new(),
+        boundaries,
+        options,
+    ))
+    .await
+}
+
+fn count(chunks: &[SimulationChunk]) -> (usize, usize) {
+    let events = chunks
+        .iter()
+        .filter(|c| matches!(c, SimulationChunk::Event { .. }))
+        .count();
+    let completes = chunks
+        .iter()
+        .filter(|c| matches!(c, SimulationChunk::Complete { .. }))
+        .count();
+    (events, completes)
+}
+
+#[tokio::test]
+async fn mock_run_yields_events_and_exactly_one_complete_chunk() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        r#"[
+  {{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "New Mixed-Income Units Open", "description": "Two hundred units open.",
+    "severity": 0.6, "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}}}}},
+  {{"type": "complete", "data": {{"summary": "Housing supply grew."}}}}
+]"#,
+        cabbagetown.housing_units + 200
+    );
+
+    let chunks = run(&content, vec![cabbagetown]).await;
+
+    let (events, completes) = count(&chunks);
+    assert!(events >= 1);
+    assert_eq!(completes, 1);
+}
+
+#[tokio::test]
+async fn fallback_complete_chunk_is_emitted_when_the_model_omits_it() {
+    let content = r#"[{"type": "event", "data": {"id": "event-1", "zoneId": "Nowhere", "zoneName": "Nowhere",
+        "type": "economic", "title": "Shop Opens", "description": "A shop opens.", "severity": 0.2,
+        "positivity": 0.4, "coordinates": [33.75, -84.39]}}]"#;
+
+    let chunks = run(content, vec![]).await;
+
+    assert_eq!(count(&chunks), (1, 1));
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => {
+            assert!(data.summary.contains("1 events generated"))
+        }
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn summary_only_mode_emits_only_the_complete_frame() {
+    let content = r#"[{"type": "event", "data": {"id": "event-1", "zoneId": "Nowhere", "zoneName": "Nowhere",
+        "type": "economic", "title": "Shop Opens", "description": "A shop opens.", "severity": 0.2,
+        "positivity": 0.4, "coordinates": [33.75, -84.39]}},
+      {"type": "complete", "data": {"summary": "One shop opened."}}]"#;
+    let options = StreamOptions {
+        summary_only: true,
+        ..StreamOptions::default()
+    };
+
+    let chunks = run_with(content, vec![], options).await;
+
+    assert_eq!(count(&chunks), (0, 1));
+    assert!(
+        chunks
+            .iter()
+            .all(|c| matches!(c, SimulationChunk::Complete { .. })),
+        "expected only a complete chunk, got {:?}",
+        chunks
+    );
+}
+
+#[tokio::test]
+async fn update_chunks_from_the_model_are_dropped() {
+    let content = r#"[{"type": "update", "data": {"total": 3}},
+      {"type": "complete", "data": {"summary": "Nothing happened."}}]"#;
+
+    let chunks = run(content, vec![]).await;
+
+    assert!(
+        !chunks
+            .iter()
+            .any(|c| matches!(c, SimulationChunk::Update { .. }))
+    );
+    assert_eq!(count(&chunks), (0, 1));
+}
+
+#[tokio::test]
+async fn duplicate_model_ids_are_reassigned_uniquely() {
+    let content = r#"[
+      {"type": "event", "data": {"id": "event-1", "zoneId": "Nowhere", "zoneName": "Nowhere",
+        "type": "economic", "title": "Shop Opens", "description": "A shop opens.", "severity": 0.2,
+        "positivity": 0.4, "coordinates": [33.75, -84.39]}},
+      {"type": "event", "data": {"id": "event-1", "zoneId": "Nowhere", "zoneName": "Nowhere",
+        "type": "economic", "title": "Cafe Opens", "description": "A cafe opens.", "severity": 0.2,
+        "positivity": 0.5, "coordinates": [33.76, -84.38]}}]"#;
+
+    let chunks = run(content, vec![]).await;
+
+    let events: Vec<_> = chunks
+        .iter()
+        .filter_map(|c| match c {
+            SimulationChunk::Event { data } => Some(data),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].id, "event-1");
+    assert_eq!(events[0].source_id, None);
+    assert_eq!(events[1].id, "event-2");
+    assert_eq!(events[1].source_id.as_deref(), Some("event-1"));
+}
+
+#[tokio::test]
+async fn complete_chunk_reports_accurate_drop_diagnostics() {
+    let cabbagetown = baseline("Cabbagetown");
+    let event = |title: &str, zone: &str, housing_units: i32| {
+        let lat = if zone == "Cabbagetown" {
+            33.749
+        } else {
+            BUCKHEAD_LAT
+        };
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "{zone}", "zoneName": "{zone}",
+    "type": "housing", "title": "{title}", "description": "Units change.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [{lat}, -84.365],
+    "metrics": {{"zoneId": "{zone}", "zoneName": "{zone}", "housing_units": {housing_units}}}}}}}"#
+        )
+    };
+    let grown = cabbagetown.housing_units + 200;
+    let content = format!(
+        "[{}, {}, {}, {}, {{\"type\": \"event\", \"data\": 42}}]",
+        event("New Units Open", "Cabbagetown", grown),
+        event("New Units Open", "Cabbagetown", grown),
+        event("Tiny Change", "Cabbagetown", cabbagetown.housing_units),
+        event("Elsewhere", "Buckhead Village", 500),
+    );
+
+    let chunks = run(&content, vec![cabbagetown]).await;
+
+    assert_eq!(count(&chunks), (1, 1));
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => assert_eq!(
+            data.diagnostics,
+            Some(SimulationDiagnostics {
+                parse_errors: 1,
+                dropped_off_target: 1,
+                dropped_sub_threshold: 1,
+                dropped_duplicate: 1,
+                dropped_near_duplicate: 0,
+                dropped_out_of_bounds: 0,
+                dropped_disallowed_type: 0,
+                dropped_over_limit: 0,
+                dropped_missing_rationale: 0,
+                hidden_by_filter: 0,
+                truncated: false,
+                recovered_partial: false,
+                termination: Some(StreamTermination::Done),
+            })
+        ),
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn event_cut_off_mid_object_is_recovered_and_truncation_reported() {
+    let content = r#"[{"type": "event", "data": {"id": "event-1", "zoneId": "Nowhere", "zoneName": "Nowhere",
+        "type": "economic", "coordinates": [33.75, -84.39], "severity": 0.2, "positivity": 0.4,
+        "title": "Shop Opens", "description": "A new shop opens on the cor"#;
+
+    let chunks = run(content, vec![]).await;
+
+    assert_eq!(count(&chunks), (1, 1));
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => {
+            let diagnostics = data.diagnostics.as_ref().unwrap();
+            assert!(diagnostics.truncated);
+            assert!(diagnostics.recovered_partial);
+        }
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn raw_model_metrics_are_kept_when_auto_completion_is_disabled() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        r#"[{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "New Residents", "description": "Demographics shift.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {},
+    "race_distribution": {{"white": 40.0, "black": 30.0, "asian": 10.0, "mixed": 10.0, "hispanic": 10.0}}}}}}}}]"#,
+        cabbagetown.housing_units + 200
+    );
+    let diversity = |chunks: &[SimulationChunk]| match chunks.get(1) {
+        Some(SimulationChunk::Event { data }) => data.metrics.as_ref().unwrap().diversity_index,
+        other => panic!("expected an event chunk, got {:?}", other),
+    };
+
+    let completed = run(&content, vec![cabbagetown.clone()]).await;
+    let raw = run_with(
+        &content,
+        vec![cabbagetown],
+        StreamOptions {
+            auto_complete_metrics: false,
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    assert!(diversity(&completed).is_some());
+    assert_eq!(diversity(&raw), None);
+}
+
+#[tokio::test]
+async fn event_with_a_wrong_zone_is_rezoned_by_its_coordinates() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        r#"[{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbage Town", "zoneName": "Cabbage Town",
+    "type": "housing", "title": "New Units Open", "description": "Units change.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [33.7509, -84.3669],
+    "metrics": {{"zoneId": "Cabbage Town", "zoneName": "Cabbage Town", "housing_units": {}}}}}}}]"#,
+        cabbagetown.housing_units + 200
+    );
+
+    let chunks = run(&content, vec![cabbagetown]).await;
+
+    match chunks.get(1) {
+        Some(SimulationChunk::Event { data }) => {
+            assert_eq!(data.zone_id, "Cabbagetown");
+            assert_eq!(data.metrics.as_ref().unwrap().zone_name, "Cabbagetown");
+        }
+        other => panic!("expected a re-zoned event, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn summary_is_streamed_incrementally_before_the_complete_chunk() {
+    let summary = r#"Bike lanes cut \"car\" trips\nacross three neighborhoods, with gains concentrated downtown."#;
+    let content = format!(r#"[{{"type": "complete", "data": {{"summary": "{summary}"}}}}]"#);
+
+    let chunks = run(&content, vec![]).await;
+
+    let deltas: Vec<&str> = chunks
+        .iter()
+        .filter_map(|c| match c {
+            SimulationChunk::Summary { data } => Some(data.delta.as_str()),
+            _ => None,
+        })
+        .collect();
+    let expected = "Bike lanes cut \"car\" trips\nacross three neighborhoods, with gains concentrated downtown.";
+    assert!(deltas.len() > 1);
+    assert_eq!(deltas.concat(), expected);
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => assert_eq!(data.summary, expected),
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn events_outside_the_type_allowlist_are_dropped() {
+    let cabbagetown = baseline("Cabbagetown");
+    let event = |event_type: &str, title: &str| {
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "{event_type}", "title": "{title}", "description": "Units change.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}}}}}"#,
+            cabbagetown.housing_units + 200
+        )
+    };
+    let content = format!(
+        "[{}, {}]",
+        event("Housing", "New Units Open"),
+        event("transportation", "Bus Route Added")
+    );
+
+    let chunks = run_with(
+        &content,
+        vec![cabbagetown.clone()],
+        StreamOptions {
+            allowed_event_types: vec!["housing".to_string(), "economic".to_string()],
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    assert_eq!(count(&chunks), (1, 1));
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => {
+            assert_eq!(
+                data.diagnostics.as_ref().unwrap().dropped_disallowed_type,
+                1
+            )
+        }
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn event_plotted_outside_its_zone_is_snapped_inside() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        r#"[{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "New Units Open", "description": "Units change.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [{BUCKHEAD_LAT}, {BUCKHEAD_LNG}],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}}}}}]"#,
+        cabbagetown.housing_units + 200
+    );
+
+    let chunks = run(&content, vec![cabbagetown]).await;
+
+    let boundaries = NeighborhoodDatabase::new()
+        .unwrap()
+        .boundaries_for(&["Cabbagetown".to_string()]);
+    match chunks.get(1) {
+        Some(SimulationChunk::Event { data }) => {
+            let &[lat, lng] = data.coordinates.as_slice() else {
+                panic!("expected a [lat, lng] pair, got {:?}", data.coordinates);
+            };
+            assert_eq!(boundaries.locate(lat, lng), Some("Cabbagetown"));
+        }
+        other => panic!("expected a snapped event, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn commute_update_with_only_minutes_keeps_the_baseline_shares() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        r#"[{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "transportation", "title": "Bus Route Added", "description": "Commutes shorten.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "commute": {{"avg_minutes": {}}}}}}}}}]"#,
+        cabbagetown.commute.avg_minutes - 5.0
+    );
+
+    let chunks = run(&content, vec![cabbagetown.clone()]).await;
+
+    match chunks.get(1) {
+        Some(SimulationChunk::Event { data }) => {
+            let commute = data.metrics.as_ref().unwrap().commute.as_ref().unwrap();
+            assert_eq!(
+                commute.avg_minutes,
+                Some(cabbagetown.commute.avg_minutes - 5.0)
+            );
+            assert_eq!(
+                commute.car_dependence,
+                Some(cabbagetown.commute.car_dependence)
+            );
+            assert_eq!(
+                commute.transit_usage,
+                Some(cabbagetown.commute.transit_usage)
+            );
+        }
+        other => panic!("expected a commute event, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn debug_mode_streams_unparseable_chunks_in_full() {
+    let bad_chunk = r#"{"type": "event", "data": {"id": "event-1", "zoneId": "Cabbagetown", "severity": "high"}}"#;
+    let content = format!("[{}]", bad_chunk);
+
+    let chunks = run_with(
+        &content,
+        vec![baseline("Cabbagetown")],
+        StreamOptions {
+            debug_parse_errors: true,
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    match chunks.get(1) {
+        Some(SimulationChunk::Warning { data }) => {
+            assert_eq!(data.chunk.as_deref(), Some(bad_chunk));
+            assert!(data.message.contains("invalid type"), "{}", data.message);
+        }
+        other => panic!("expected a warning chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn grouped_mode_streams_each_zone_together_in_target_order() {
+    let cabbagetown = baseline("Cabbagetown");
+    let inman_park = baseline("Inman Park");
+    let event = |zone: &NeighborhoodProperties, title: &str| {
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "{zone}", "zoneName": "{zone}",
+    "type": "housing", "title": "{title}", "description": "Units change.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "{zone}", "zoneName": "{zone}", "housing_units": {}}}}}}}"#,
+            zone.housing_units + 200,
+            zone = zone.name,
+        )
+    };
+    let content = format!(
+        "[{}, {}, {}, {}]",
+        event(&inman_park, "First Inman Park Event"),
+        event(&cabbagetown, "First Cabbagetown Event"),
+        event(&inman_park, "Second Inman Park Event"),
+        event(&cabbagetown, "Second Cabbagetown Event"),
+    );
+
+    let chunks = run_with(
+        &content,
+        vec![cabbagetown, inman_park],
+        StreamOptions {
+            group_by_zone: true,
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    let titles: Vec<&str> = chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            SimulationChunk::Event { data } => Some(data.title.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        titles,
+        [
+            "First Cabbagetown Event",
+            "Second Cabbagetown Event",
+            "First Inman Park Event",
+            "Second Inman Park Event",
+        ]
+    );
+    assert!(matches!(
+        chunks.last(),
+        Some(SimulationChunk::Complete { .. })
+    ));
+}
+
+#[tokio::test]
+async fn baseline_chunk_with_the_grounding_properties_comes_first() {
+    let cabbagetown = baseline("Cabbagetown");
+    let inman_park = baseline("Inman Park");
+
+    let chunks = run("[]", vec![cabbagetown.clone(), inman_park.clone()]).await;
+
+    match chunks.first() {
+        Some(SimulationChunk::Baseline { data }) => {
+            assert_eq!(
+                serde_json::to_value(&data.neighborhoods).unwrap(),
+                serde_json::to_value([cabbagetown, inman_park]).unwrap()
+            );
+        }
+        other => panic!("expected a leading baseline chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn severe_events_list_their_zones_neighbors_as_spillover() {
+    let cabbagetown = baseline("Cabbagetown");
+    let event = |title: &str, severity: f64| {
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "{title}", "description": "Units change.", "severity": {severity},
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}}}}}"#,
+            cabbagetown.housing_units + 200
+        )
+    };
+    let content = format!(
+        "[{}, {}]",
+        event("Mill Lofts Redeveloped", 0.9),
+        event("Corner Duplex Renovated", 0.1)
+    );
+
+    let chunks = run(&content, vec![cabbagetown.clone()]).await;
+
+    let spillover: Vec<&Vec<String>> = chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            SimulationChunk::Event { data } => Some(&data.spillover),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        spillover,
+        [&cabbagetown.neighboring_neighborhoods.unwrap(), &Vec::new()]
+    );
+}
+
+#[tokio::test]
+async fn events_past_the_total_limit_are_not_forwarded() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        "[{}]",
+        (1..=20)
+            .map(|seq| format!(
+                r#"{{"type": "event", "data": {{"id": "event-{seq}", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "Phase {seq} Units Open", "description": "Units change.", "severity": 0.3,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}}}}}"#,
+                cabbagetown.housing_units + 200
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let chunks = run_with(
+        &content,
+        vec![cabbagetown],
+        StreamOptions {
+            max_total_events: 13,
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    let titles: Vec<&str> = chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            SimulationChunk::Event { data } => Some(data.title.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(titles.len(), 13);
+    assert_eq!(titles.first(), Some(&"Phase 1 Units Open"));
+    assert_eq!(titles.last(), Some(&"Phase 13 Units Open"));
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => {
+            let diagnostics = data.diagnostics.as_ref().unwrap();
+            assert_eq!(diagnostics.termination, Some(StreamTermination::CapReached));
+            assert_eq!(diagnostics.dropped_over_limit, 0);
+            assert!(!diagnostics.truncated);
+        }
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn spillover_metrics_for_a_non_target_neighbor_are_completed_from_its_baseline() {
+    let cabbagetown = baseline("Cabbagetown");
+    let inman_park = baseline("Inman Park");
+    let content = format!(
+        r#"[{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "Renters Move Next Door", "description": "Units change.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Inman Park", "zoneName": "Inman Park", "housing_units": {}}}}}}}]"#,
+        inman_park.housing_units + 200
+    );
+    let boundaries = NeighborhoodDatabase::new()
+        .unwrap()
+        .boundaries_for(&["Cabbagetown".to_string()]);
+
+    let chunks = collect_chunks(process_phase2_stream(
+        stream::iter(azure_sse_body(&content, 9)),
+        vec![cabbagetown],
+        vec![inman_park.clone()],
+        boundaries,
+        StreamOptions::default(),
+    ))
+    .await;
+
+    assert_eq!(count(&chunks), (1, 1));
+    let metrics = chunks
+        .iter()
+        .find_map(|chunk| match chunk {
+            SimulationChunk::Event { data } => data.metrics.clone(),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(metrics.zone_id, "Inman Park");
+    assert_eq!(metrics.vacant_units, Some(inman_park.vacant_units + 200));
+    assert!(metrics.vacancy_rate.is_some());
+}
+
+#[tokio::test]
+async fn final_state_accumulates_sequential_events_on_one_zone() {
+    let cabbagetown = baseline("Cabbagetown");
+    let event = |title: &str, metrics: String| {
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "{title}", "description": "Change.", "severity": 0.3,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", {metrics}}}}}}}"#
+        )
+    };
+    let content = format!(
+        "[{}, {}]",
+        event(
+            "New Units Open",
+            format!("\"housing_units\": {}", cabbagetown.housing_units + 200)
+        ),
+        event(
+            "Wages Rise",
+            format!("\"median_income\": {}", cabbagetown.median_income + 8000)
+        ),
+    );
+
+    let chunks = run(&content, vec![cabbagetown.clone()]).await;
+
+    let final_states: Vec<_> = chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            SimulationChunk::FinalState { data } => Some(data),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(final_states.len(), 1);
+    let state = &final_states[0].properties;
+    assert_eq!(final_states[0].zone_id, "Cabbagetown");
+    assert_eq!(state.housing_units, cabbagetown.housing_units + 200);
+    assert_eq!(state.vacant_units, cabbagetown.vacant_units + 200);
+    assert_eq!(state.median_income, cabbagetown.median_income + 8000);
+    assert!(matches!(
+        chunks[chunks.len() - 2],
+        SimulationChunk::FinalState { .. }
+    ));
+}
+
+#[tokio::test]
+async fn rationale_is_preserved_and_required_when_requested() {
+    let cabbagetown = baseline("Cabbagetown");
+    let event = |title: &str, rationale: &str| {
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "{title}", "description": "Units change.", "severity": 0.3,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}{rationale}}}}}"#,
+            cabbagetown.housing_units + 200
+        )
+    };
+    let content = format!(
+        "[{}, {}]",
+        event(
+            "Lofts Open",
+            r#", "rationale": " 200 new lofts add housing units. ""#
+        ),
+        event("Townhomes Open", "")
+    );
+
+    let chunks = run_with(
+        &content,
+        vec![cabbagetown],
+        StreamOptions {
+            require_rationale: true,
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    assert_eq!(count(&chunks), (1, 1));
+    match chunks.get(1) {
+        Some(SimulationChunk::Event { data }) => assert_eq!(
+            data.rationale.as_deref(),
+            Some("200 new lofts add housing units.")
+        ),
+        other => panic!("expected an event chunk, got {:?}", other),
+    }
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => assert_eq!(
+            data.diagnostics.as_ref().unwrap().dropped_missing_rationale,
+            1
+        ),
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn events_keep_only_valid_policy_attributions() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        r#"[{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "Lofts Open", "description": "Units change.", "severity": 0.3,
+    "positivity": 0.5, "coordinates": [33.749, -84.365], "drivenBy": [2, 0, 7, 2],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}}}}}]"#,
+        cabbagetown.housing_units + 200
+    );
+
+    let chunks = run_with(
+        &content,
+        vec![cabbagetown],
+        StreamOptions {
+            policy_count: 3,
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    match chunks.get(1) {
+        Some(SimulationChunk::Event { data }) => assert_eq!(data.driven_by, vec![0, 2]),
+        other => panic!("expected an event chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn event_ids_follow_the_configured_template() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        "[{}]",
+        (1..=3)
+            .map(|seq| format!(
+                r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "Phase {seq} Units Open", "description": "Units change.", "severity": 0.3,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}}}}}"#,
+                cabbagetown.housing_units + 200
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let chunks = run_with(
+        &content,
+        vec![cabbagetown],
+        StreamOptions {
+            event_id_template: "sim-{request_id}-evt-{n}".to_string(),
+            request_id: "abc123".to_string(),
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    let ids: Vec<&str> = chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            SimulationChunk::Event { data } => Some(data.id.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        ids,
+        vec!["sim-abc123-evt-1", "sim-abc123-evt-2", "sim-abc123-evt-3"]
+    );
+}
+
+#[tokio::test]
+async fn targets_without_events_are_marked_stable_when_requested() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        r#"[{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "New Units Open", "description": "Units change.", "severity": 0.3,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}}}}}]"#,
+        cabbagetown.housing_units + 200
+    );
+    let targets = vec![
+        cabbagetown,
+        baseline("Reynoldstown"),
+        baseline("Grant Park"),
+    ];
+
+    let unmarked = run(&content, targets.clone()).await;
+    assert!(
+        !unmarked
+            .iter()
+            .any(|chunk| matches!(chunk, SimulationChunk::Stable { .. }))
+    );
+
+    let options = StreamOptions {
+        mark_stable_zones: true,
+        ..StreamOptions::default()
+    };
+    let chunks = run_with(&content, targets, options).await;
+
+    let stable: Vec<&str> = chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            SimulationChunk::Stable { data } => Some(data.zone_id.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(stable, vec!["Reynoldstown", "Grant Park"]);
+    assert!(matches!(
+        chunks.last(),
+        Some(SimulationChunk::Complete { .. })
+    ));
+}
+
+#[tokio::test]
+async fn near_duplicate_events_are_dropped_when_enabled() {
+    let event = |zone: &str, title: &str, description: &str| {
+        let housing_units = baseline(zone).housing_units + 200;
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "{zone}", "zoneName": "{zone}",
+    "type": "economic", "title": "{title}", "description": "{description}", "severity": 0.3,
+    "positivity": -0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "{zone}", "zoneName": "{zone}", "housing_units": {housing_units}}}}}}}"#
+        )
+    };
+    let content = format!(
+        "[{}, {}]",
+        event(
+            "Cabbagetown",
+            "Water Crisis Closures",
+            "Business closures due to water crisis hit local shops."
+        ),
+        event(
+            "Reynoldstown",
+            "Water Crisis Closures Spread",
+            "Business closures due to the water crisis hit local shops."
+        ),
+    );
+    let targets = vec![baseline("Cabbagetown"), baseline("Reynoldstown")];
+
+    let (kept, _) = count(&run(&content, targets.clone()).await);
+    assert_eq!(kept, 2);
+
+    let options = StreamOptions {
+        near_duplicate_threshold: 0.8,
+        ..StreamOptions::default()
+    };
+    let chunks = run_with(&content, targets, options).await;
+
+    assert_eq!(count(&chunks).0, 1);
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => {
+            assert_eq!(data.diagnostics.as_ref().unwrap().dropped_near_duplicate, 1)
+        }
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn severity_sort_breaks_ties_by_zone_then_arrival() {
+    let event = |zone: &str, title: &str, severity: f64| {
+        let housing_units = baseline(zone).housing_units + 200;
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "{zone}", "zoneName": "{zone}",
+    "type": "housing", "title": "{title}", "description": "Units change.", "severity": {severity},
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "{zone}", "zoneName": "{zone}", "housing_units": {housing_units}}}}}}}"#
+        )
+    };
+    let content = format!(
+        "[{}]",
+        [
+            event("Reynoldstown", "R1", 0.5),
+            event("Cabbagetown", "C1", 0.5),
+            event("Reynoldstown", "R2", 0.9),
+            event("Reynoldstown", "R3", 0.5),
+            event("Cabbagetown", "C2", 0.5),
+        ]
+        .join(", ")
+    );
+    let options = StreamOptions {
+        sort_by_severity: true,
+        ..StreamOptions::default()
+    };
+
+    let chunks = run_with(
+        &content,
+        vec![baseline("Reynoldstown"), baseline("Cabbagetown")],
+        options,
+    )
+    .await;
+
+    let titles: Vec<&str> = chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            SimulationChunk::Event { data } => Some(data.title.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(titles, vec!["R2", "C1", "C2", "R1", "R3"]);
+}
+
+#[tokio::test]
+async fn complete_chunk_carries_highlights_aggregated_from_events() {
+    let event = |zone: &str, event_type: &str, title: &str, positivity: f64, population: i32| {
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "{zone}", "zoneName": "{zone}",
+    "type": "{event_type}", "title": "{title}", "description": "Residents move.", "severity": 0.3,
+    "positivity": {positivity}, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "{zone}", "zoneName": "{zone}", "population_total": {population}}}}}}}"#
+        )
+    };
+    let cabbagetown = baseline("Cabbagetown");
+    let reynoldstown = baseline("Reynoldstown");
+    let content = format!(
+        "[{}, {}, {}]",
+        event(
+            "Cabbagetown",
+            "Housing",
+            "Lofts Open",
+            0.5,
+            cabbagetown.population_total + 300
+        ),
+        event(
+            "Reynoldstown",
+            "economic",
+            "Plant Closes",
+            -0.5,
+            reynoldstown.population_total - 300
+        ),
+        event(
+            "Cabbagetown",
+            "housing",
+            "Townhomes Open",
+            0.3,
+            cabbagetown.population_total + 500
+        ),
+    );
+
+    let chunks = run(&content, vec![cabbagetown, reynoldstown]).await;
+
+    let Some(SimulationChunk::Complete { data }) = chunks.last() else {
+        panic!("expected a trailing complete chunk");
+    };
+    let highlights = &data.highlights;
+    assert_eq!(
+        highlights.affected_neighborhoods,
+        vec!["Cabbagetown", "Reynoldstown"]
+    );
+    assert_eq!(highlights.net_population_change, 200);
+    assert_eq!(highlights.dominant_event_type.as_deref(), Some("housing"));
+    assert!((highlights.overall_positivity.unwrap() - 0.1).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn authoritative_coordinates_spread_events_around_the_centroid() {
+    let cabbagetown = baseline("Cabbagetown");
+    let event = |id: u32, title: &str, units: i32| {
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-{id}", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "{title}", "description": "Units change.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [{BUCKHEAD_LAT}, {BUCKHEAD_LNG}],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {units}}}}}}}"#
+        )
+    };
+    let content = format!(
+        "[{}, {}]",
+        event(1, "New Units Open", cabbagetown.housing_units + 200),
+        event(2, "Lofts Convert", cabbagetown.housing_units + 300)
+    );
+
+    let chunks = run_with(
+        &content,
+        vec![cabbagetown],
+        StreamOptions {
+            authoritative_coordinates: true,
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    let boundaries = NeighborhoodDatabase::new()
+        .unwrap()
+        .boundaries_for(&["Cabbagetown".to_string()]);
+    let (centroid_lat, centroid_lng) = boundaries
+        .snap_into("Cabbagetown", BUCKHEAD_LAT, BUCKHEAD_LNG)
+        .unwrap();
+    let points: Vec<(f64, f64)> = chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            SimulationChunk::Event { data } => Some((data.coordinates[0], data.coordinates[1])),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(points.len(), 2);
+    assert_ne!(points[0], points[1]);
+    for &(lat, lng) in &points {
+        assert_eq!(boundaries.locate(lat, lng), Some("Cabbagetown"));
+        assert!(distance_meters(lat, lng, centroid_lat, centroid_lng) <= 125.0 + 1e-6);
+    }
+}
+
+#[tokio::test]
+async fn parse_errors_are_counted_per_model() {
+    let telemetry = Arc::new(ParseTelemetry::new());
+    let content =
+        r#"[{"type": "event", "data": 42}, {"type": "complete", "data": {"summary": "Done."}}]"#;
+
+    run_with(
+        content,
+        vec![baseline("Cabbagetown")],
+        StreamOptions {
+            model: "model-a".to_string(),
+            parse_telemetry: Some(telemetry.clone()),
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    let stats = telemetry
+        .model("model-a")
+        .expect("the stream should be recorded");
+    assert_eq!((stats.streams, stats.chunks, stats.parse_errors), (1, 2, 1));
+    assert_eq!(stats.error_rate, 0.5);
+    assert!(telemetry.model("model-b").is_none());
+}
+
+#[tokio::test]
+async fn event_coordinates_are_rounded_to_the_configured_precision() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        r#"[{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "New Units Open", "description": "Units change.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [33.749123456789, -84.365987654321],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}}}}}]"#,
+        cabbagetown.housing_units + 200
+    );
+
+    let chunks = run_with(
+        &content,
+        vec![cabbagetown],
+        StreamOptions {
+            coordinate_decimals: 3,
+            ..StreamOptions::default()
+        },
+    )
+    .await;
+
+    match chunks.get(1) {
+        Some(SimulationChunk::Event { data }) => {
+            assert_eq!(data.coordinates, vec![33.749, -84.366])
+        }
+        other => panic!("expected an event, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn done_marker_ends_the_stream_without_waiting_for_the_connection() {
+    let cabbagetown = baseline("Cabbagetown");
+    let content = format!(
+        r#"[{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "New Units Open", "description": "Units open.", "severity": 0.5,
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {}}}}}}}]"#,
+        cabbagetown.housing_units + 200
+    );
+    let connection_held_open_after_done =
+        stream::iter(azure_sse_body(&content, 9)).chain(stream::pending());
+
+    let chunks = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        collect_chunks(process_phase2_stream(
+            connection_held_open_after_done,
+            vec![cabbagetown],
+            Vec::new(),
+            Default::default(),
+            StreamOptions::default(),
+        )),
+    )
+    .await
+    .expect("the stream should end at [DONE]");
+
+    assert_eq!(count(&chunks), (1, 1));
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => assert_eq!(
+            data.diagnostics.as_ref().unwrap().termination,
+            Some(StreamTermination::Done)
+        ),
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn events_below_the_severity_floor_are_hidden_but_counted() {
+    let cabbagetown = baseline("Cabbagetown");
+    let event = |title: &str, severity: f64, housing_units: i32| {
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "{title}", "description": "{title}.", "severity": {severity},
+    "positivity": 0.5, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {housing_units}}}}}}}"#
+        )
+    };
+    let content = format!(
+        "[{}, {}]",
+        event("Corner Lot Infill", 0.05, cabbagetown.housing_units + 30),
+        event("Mill Lofts Expansion", 0.6, cabbagetown.housing_units + 200),
+    );
+    let options = StreamOptions {
+        min_severity: 0.3,
+        ..StreamOptions::default()
+    };
+
+    let chunks = run_with(&content, vec![cabbagetown], options).await;
+
+    let titles: Vec<&str> = chunks
+        .iter()
+        .filter_map(|c| match c {
+            SimulationChunk::Event { data } => Some(data.title.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(titles, vec!["Mill Lofts Expansion"]);
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => {
+            assert_eq!(data.diagnostics.as_ref().unwrap().hidden_by_filter, 1)
+        }
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn max_positivity_zero_streams_only_non_positive_events() {
+    let cabbagetown = baseline("Cabbagetown");
+    let event = |title: &str, positivity: f64, housing_units: i32| {
+        format!(
+            r#"{{"type": "event", "data": {{"id": "event-1", "zoneId": "Cabbagetown", "zoneName": "Cabbagetown",
+    "type": "housing", "title": "{title}", "description": "{title}.", "severity": 0.5,
+    "positivity": {positivity}, "coordinates": [33.749, -84.365],
+    "metrics": {{"zoneId": "Cabbagetown", "zoneName": "Cabbagetown", "housing_units": {housing_units}}}}}}}"#
+        )
+    };
+    let content = format!(
+        "[{}, {}, {}]",
+        event(
+            "Mill Lofts Demolished",
+            -0.4,
+            cabbagetown.housing_units - 200
+        ),
+        event("Rezoning Hearing Held", 0.0, cabbagetown.housing_units + 40),
+        event("Mill Lofts Expansion", 0.6, cabbagetown.housing_units + 300),
+    );
+    let options = StreamOptions {
+        max_positivity: Some(0.0),
+        ..StreamOptions::default()
+    };
+
+    let chunks = run_with(&content, vec![cabbagetown], options).await;
+
+    let positivities: Vec<f64> = chunks
+        .iter()
+        .filter_map(|c| match c {
+            SimulationChunk::Event { data } => Some(data.positivity),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(positivities, vec![-0.4, 0.0]);
+    match chunks.last() {
+        Some(SimulationChunk::Complete { data }) => {
+            assert_eq!(data.diagnostics.as_ref().unwrap().hidden_by_filter, 1)
+        }
+        other => panic!("expected a trailing complete chunk, got {:?}", other),
+    }
+}
@@ -0,0 +1,62 @@
+use actix_web::web::Bytes;
+use backend::prompt_log::PromptLog;
+use futures_util::{StreamExt, stream};
+use std::convert::Infallible;
+use std::path::PathBuf;
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("prompt-log-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn read_logged(dir: &PathBuf, suffix: &str) -> String {
+    let path = std::fs::read_dir(dir)
+        .expect("log directory should exist")
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_string_lossy().ends_with(suffix))
+        .unwrap_or_else(|| panic!("no file ending in {}", suffix));
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[tokio::test]
+async fn enabled_log_writes_redacted_request_and_response_files() {
+    let dir = scratch_dir("enabled");
+    let log = PromptLog::new(Some(dir.clone()), "req-1", "secret-key");
+
+    log.log_request(
+        "phase1",
+        &serde_json::json!({ "messages": ["uses secret-key"] }),
+    );
+    let body = vec![
+        Ok::<_, Infallible>(Bytes::from("data: {\"a\":1}\n\n")),
+        Ok(Bytes::from("data: [DONE]\n\n")),
+    ];
+    let forwarded: Vec<_> = log
+        .tee_response("phase2", stream::iter(body))
+        .collect()
+        .await;
+
+    assert_eq!(forwarded.len(), 2);
+    let request = read_logged(&dir, "-req-1-phase1-request.json");
+    assert!(request.contains("[REDACTED]"));
+    assert!(!request.contains("secret-key"));
+    assert_eq!(
+        read_logged(&dir, "-req-1-phase2-response.txt"),
+        "data: {\"a\":1}\n\ndata: [DONE]\n\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn disabled_log_writes_nothing() {
+    let dir = scratch_dir("disabled");
+    let log = PromptLog::disabled();
+
+    log.log_request("phase1", &serde_json::json!({}));
+    log.log_response("phase1", "{}");
+
+    assert!(!log.is_enabled());
+    assert!(!dir.exists());
+}
@@ -0,0 +1,56 @@
+use actix_web::http::StatusCode;
+use actix_web::{App, test, web};
+use backend::SimulationConfig;
+use backend::azure::SystemPrompts;
+use backend::handlers::get_prompts;
+
+#[actix_web::test]
+async fn prompts_endpoint_returns_both_system_prompts() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(SimulationConfig::default()))
+            .route("/api/prompts", web::get().to(get_prompts)),
+    )
+    .await;
+
+    let request = test::TestRequest::get().uri("/api/prompts").to_request();
+    let prompts: SystemPrompts = test::call_and_read_body_json(&app, request).await;
+
+    assert!(prompts.phase1_system.contains("Atlanta, Georgia"));
+    assert!(prompts.phase1_system.contains("\"neighborhoods\" array"));
+    assert!(prompts.phase1_system.contains("Example Park"));
+    assert!(
+        prompts
+            .phase2_system
+            .contains("You MUST return a valid JSON array")
+    );
+    assert!(
+        prompts
+            .phase2_system
+            .contains("NEVER emit \"update\" chunks")
+    );
+    assert!(
+        prompts
+            .phase2_system
+            .contains(r#"Neighborhood: <client_data>"Example Park"</client_data>"#)
+    );
+}
+
+#[actix_web::test]
+async fn prompts_endpoint_can_be_disabled() {
+    let config = SimulationConfig {
+        expose_prompts: false,
+        ..SimulationConfig::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(config))
+            .route("/api/prompts", web::get().to(get_prompts)),
+    )
+    .await;
+
+    let request = test::TestRequest::get().uri("/api/prompts").to_request();
+    let response = test::call_service(&app, request).await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
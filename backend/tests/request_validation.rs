@@ -0,0 +1,53 @@
+use backend::breaker::CircuitBreaker;
+use backend::constituents::EventRequest;
+use backend::telemetry::ParseTelemetry;
+use backend::types::SimulationRequest;
+use backend::utils::{MIN_PROMPT_CHARS, validate_prompt};
+use backend::{NeighborhoodDatabase, Phase1Cache, SimulationConfig, SimulationError};
+use std::sync::Arc;
+
+#[test]
+fn empty_whitespace_and_short_prompts_are_rejected() {
+    assert!(validate_prompt("").is_err());
+    assert!(validate_prompt(" \n\t ").is_err());
+    assert!(validate_prompt("  transit ").is_err());
+    assert!(validate_prompt(&"x".repeat(MIN_PROMPT_CHARS)).is_ok());
+    assert!(validate_prompt("Build light rail to the airport").is_ok());
+}
+
+#[tokio::test]
+async fn blank_prompt_fails_before_any_azure_call() {
+    let request = SimulationRequest {
+        prompt: "   ".to_string(),
+        ..SimulationRequest::default()
+    };
+
+    let result = backend::generate_simulation(
+        request,
+        Arc::new(NeighborhoodDatabase::default()),
+        Arc::new(SimulationConfig::default()),
+        Arc::new(Phase1Cache::disabled()),
+        Arc::new(ParseTelemetry::new()),
+        Arc::new(CircuitBreaker::disabled()),
+    )
+    .await;
+
+    assert!(matches!(result, Err(SimulationError::InvalidRequest(_))));
+}
+
+#[test]
+fn events_need_a_title_and_description() {
+    let event: EventRequest = serde_json::from_value(serde_json::json!({
+        "title": "  ",
+        "description": "A new park opens.",
+        "zone": "Midtown",
+        "positivity": 0.5,
+        "severity": 0.3
+    }))
+    .unwrap();
+
+    assert!(matches!(
+        event.validate(),
+        Err(SimulationError::InvalidRequest(message)) if message.contains("title")
+    ));
+}
@@ -0,0 +1,23 @@
+use actix_web::ResponseError;
+use actix_web::http::{StatusCode, header};
+use backend::SimulationError;
+use backend::limiter::SimulationLimiter;
+use futures_util::{StreamExt, stream};
+
+#[tokio::test]
+async fn simulation_beyond_the_limit_is_rejected_until_one_finishes() {
+    let limiter = SimulationLimiter::new(2);
+    let first = limiter.try_acquire().unwrap();
+    let _second = limiter.try_acquire().unwrap();
+
+    let rejected = limiter.try_acquire().err().unwrap();
+    assert!(matches!(rejected, SimulationError::TooManySimulations));
+    let response = rejected.error_response();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+    let held = first.hold(stream::iter([1, 2, 3]));
+    assert!(limiter.try_acquire().is_err());
+    assert_eq!(held.collect::<Vec<_>>().await, vec![1, 2, 3]);
+    assert!(limiter.try_acquire().is_ok());
+}
@@ -0,0 +1,19 @@
+use backend::sse::{parse_frame, sse_frame};
+use backend::types::{SimulationChunk, SimulationUpdate};
+
+#[test]
+fn frame_round_trips_through_the_parser() {
+    let frame = sse_frame(&SimulationChunk::Update {
+        data: SimulationUpdate { total: 3 },
+    });
+    let text = std::str::from_utf8(&frame).unwrap();
+
+    assert_eq!(
+        text,
+        "data: {\"type\":\"update\",\"data\":{\"total\":3}}\n\n"
+    );
+    assert!(matches!(
+        parse_frame(text),
+        Some(SimulationChunk::Update { data }) if data.total == 3
+    ));
+}
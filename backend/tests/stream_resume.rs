@@ -0,0 +1,82 @@
+use actix_web::{App, test, web};
+use backend::SimulationStore;
+use backend::handlers::resume_simulation;
+use backend::sse::sse_frame;
+use backend::types::{SimulationChunk, SimulationComplete, SimulationUpdate};
+use std::time::Duration;
+
+fn finished_simulation(store: &SimulationStore) -> String {
+    let id = store.create("Add protected bike lanes");
+    for total in [3, 4] {
+        let update = SimulationChunk::Update {
+            data: SimulationUpdate { total },
+        };
+        store.record_frame(&id, &sse_frame(&update));
+    }
+    let complete = SimulationChunk::Complete {
+        data: SimulationComplete {
+            summary: "Bike lanes added.".to_string(),
+            schema_version: 0,
+            fallback_model: None,
+            diagnostics: None,
+            highlights: Default::default(),
+        },
+    };
+    store.record_frame(&id, &sse_frame(&complete));
+    store.finish(&id);
+    id
+}
+
+#[actix_web::test]
+async fn reconnecting_with_last_event_id_replays_only_later_frames() {
+    let store = web::Data::new(SimulationStore::new());
+    let id = finished_simulation(&store);
+    let app = test::init_service(App::new().app_data(store.clone()).route(
+        "/api/simulate/{id}/stream",
+        web::get().to(resume_simulation),
+    ))
+    .await;
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/simulate/{}/stream", id))
+        .insert_header(("Last-Event-ID", "1"))
+        .to_request();
+    let body = test::call_and_read_body(&app, request).await;
+    let body = String::from_utf8(body.to_vec()).unwrap();
+
+    let ids: Vec<&str> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("id: "))
+        .collect();
+    assert_eq!(ids, vec!["2", "3"]);
+    assert!(!body.contains(r#""total":3"#));
+    assert!(body.contains(r#""total":4"#));
+    assert!(body.contains("Bike lanes added."));
+}
+
+#[actix_web::test]
+async fn finished_simulations_expire_after_the_retention_period() {
+    let store = SimulationStore::with_limits(8, Duration::from_millis(20));
+    let finished = finished_simulation(&store);
+    let running = store.create("Add a streetcar line");
+
+    assert!(store.get(&finished).is_some());
+    tokio::time::sleep(Duration::from_millis(40)).await;
+
+    assert!(store.get(&finished).is_none());
+    assert!(store.get(&running).is_some());
+}
+
+#[actix_web::test]
+async fn a_full_store_evicts_the_least_recently_used_simulation() {
+    let store = SimulationStore::with_limits(2, Duration::from_secs(3600));
+    let first = finished_simulation(&store);
+    let second = finished_simulation(&store);
+    assert!(store.get(&first).is_some());
+
+    let third = store.create("Add a streetcar line");
+
+    assert!(store.get(&first).is_some());
+    assert!(store.get(&second).is_none());
+    assert!(store.get(&third).is_some());
+}
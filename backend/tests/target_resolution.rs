@@ -0,0 +1,59 @@
+use backend::NeighborhoodDatabase;
+use backend::types::{SimulationChunk, SimulationTargets};
+use backend::utils::{resolve_target_neighborhoods, validate_neighborhood_properties};
+
+#[test]
+fn targets_match_phase_one_output_and_record_their_source() {
+    let db = NeighborhoodDatabase::new().expect("neighborhood GeoJSON should load");
+    let from_request = db.find_by_name("Cabbagetown").unwrap();
+    let phase1 = vec![
+        "Midtown".to_string(),
+        "Cabbagetown".to_string(),
+        "Atlantis".to_string(),
+    ];
+
+    let (lookup, targets) = resolve_target_neighborhoods(&phase1, &[from_request], &db);
+
+    assert_eq!(
+        targets,
+        SimulationTargets {
+            neighborhoods: phase1,
+            from_request: vec!["Cabbagetown".to_string()],
+            from_database: vec!["Midtown".to_string()],
+            missing: vec!["Atlantis".to_string()],
+        }
+    );
+    assert!(lookup.contains_key("Midtown") && lookup.contains_key("Cabbagetown"));
+
+    let json = serde_json::to_value(SimulationChunk::Targets { data: targets }).unwrap();
+    assert_eq!(json["type"], "targets");
+    assert_eq!(json["data"]["fromDatabase"][0], "Midtown");
+}
+
+#[test]
+fn invalid_request_properties_fall_back_to_the_database() {
+    let db = NeighborhoodDatabase::new().expect("neighborhood GeoJSON should load");
+    let stored = db.find_by_name("Cabbagetown").unwrap();
+    let mut conflicting = stored.clone();
+    conflicting.vacancy_rate = 140.0;
+    conflicting.median_income = stored.median_income * 3;
+    let phase1 = vec!["Cabbagetown".to_string()];
+
+    let (lookup, targets) = resolve_target_neighborhoods(&phase1, &[conflicting], &db);
+
+    assert_eq!(targets.from_database, phase1);
+    assert!(targets.from_request.is_empty());
+    assert_eq!(lookup["Cabbagetown"].vacancy_rate, stored.vacancy_rate);
+    assert_eq!(lookup["Cabbagetown"].median_income, stored.median_income);
+}
+
+#[test]
+fn every_database_neighborhood_passes_request_validation() {
+    let db = NeighborhoodDatabase::new().expect("neighborhood GeoJSON should load");
+
+    for neighborhood in db.all() {
+        if let Err(reason) = validate_neighborhood_properties(&neighborhood) {
+            panic!("{} failed validation: {}", neighborhood.name, reason);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use backend::NeighborhoodDatabase;
+use backend::types::NeighborhoodProperties;
+use backend::utils::{population_weighted_average, weighted_average};
+
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+fn neighborhood(name: &str, population: i32, median_income: i32) -> NeighborhoodProperties {
+    let mut neighborhood = NeighborhoodDatabase::new()
+        .expect("neighborhood GeoJSON should load")
+        .find_by_name(name)
+        .expect("fixture neighborhood should exist");
+    neighborhood.population_total = population;
+    neighborhood.median_income = median_income;
+    neighborhood
+}
+
+#[test]
+fn larger_populations_pull_the_average_toward_them() {
+    let neighborhoods = vec![
+        neighborhood("Midtown", 3000, 100_000),
+        neighborhood("Cabbagetown", 1000, 20_000),
+    ];
+
+    let average = population_weighted_average(&neighborhoods, |n| n.median_income as f64);
+
+    assert!(approx_eq(average, 80_000.0));
+}
+
+#[test]
+fn zero_total_weight_falls_back_to_the_plain_mean() {
+    let neighborhoods = vec![
+        neighborhood("Midtown", 0, 100_000),
+        neighborhood("Cabbagetown", 0, 20_000),
+    ];
+
+    let average = population_weighted_average(&neighborhoods, |n| n.median_income as f64);
+    let negative = weighted_average(&neighborhoods, |_| -1.0, |n| n.median_income as f64);
+
+    assert!(approx_eq(average, 60_000.0));
+    assert!(approx_eq(negative, 60_000.0));
+    assert!(approx_eq(population_weighted_average(&[], |_| 1.0), 0.0));
+}